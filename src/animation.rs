@@ -0,0 +1,80 @@
+//! Contains utilities to evaluate animation curves connected to properties of objects.
+//!
+//! Times are FBX times in ticks (see `TICKS_PER_SECOND`).
+//! Animation stacks and layers are not considered, and the first animation curve node
+//! connected to a property animates the property.
+
+pub use objects::animation_curve::{TICKS_PER_SECOND, time_from_seconds};
+
+use math::Mat4;
+use objects::{AnimationCurve, AnimationCurveNode};
+use objects::model::ModelTransform;
+use scene::FbxScene;
+
+
+impl<I> FbxScene<I> {
+    /// Returns the animation curve node connected to the given property of the object.
+    pub fn property_curve_node(&self, object: i64, property: &str) -> Option<&AnimationCurveNode> {
+        self.connections.iter()
+            .filter(|conn| conn.parent == object && conn.parent_is_property && !conn.child_is_property && conn.has_attribute(property))
+            .filter_map(|conn| self.objects.animation_curve_nodes.get(&conn.child))
+            .next()
+    }
+
+    /// Returns the animation curve connected to the given channel (such as `X`) of the curve node.
+    pub fn curve_node_curve(&self, curve_node: i64, channel: &str) -> Option<&AnimationCurve> {
+        let attribute = format!("d|{}", channel);
+        self.connections.iter()
+            .filter(|conn| conn.parent == curve_node && conn.parent_is_property && !conn.child_is_property && conn.has_attribute(&attribute))
+            .filter_map(|conn| self.objects.animation_curves.get(&conn.child))
+            .next()
+    }
+
+    /// Returns the value of the channel of the curve node at the given time.
+    ///
+    /// The default value of the channel is used if no curve is connected to the channel.
+    /// Returns `None` if the curve node or the channel does not exist.
+    pub fn curve_node_value_at_time(&self, curve_node: i64, channel: &str, time: i64) -> Option<f64> {
+        self.curve_node_curve(curve_node, channel)
+            .and_then(|curve| curve.evaluate(time))
+            .or_else(|| self.objects.animation_curve_nodes.get(&curve_node).and_then(|node| node.defaults.get(channel).cloned()))
+    }
+
+    /// Returns the value of the channel of the animated property at the given time.
+    ///
+    /// Returns `None` if the property is not animated.
+    pub fn property_value_at_time(&self, object: i64, property: &str, channel: &str, time: i64) -> Option<f64> {
+        self.property_curve_node(object, property).and_then(|node| self.curve_node_value_at_time(node.id, channel, time))
+    }
+
+    /// Returns the transform of the model at the given time.
+    ///
+    /// Animated channels of `Lcl Translation`, `Lcl Rotation` and `Lcl Scaling` replace the
+    /// values of the model, and the other channels and properties are not changed.
+    /// Returns `None` if the model does not exist.
+    pub fn model_transform_at_time(&self, model: i64, time: i64) -> Option<ModelTransform> {
+        self.model(model).map(|model| {
+            let mut transform = model.transform;
+            let animate = |property: &str, values: &mut [f64; 3]| {
+                if let Some(node) = self.property_curve_node(model.id, property) {
+                    for (value, channel) in values.iter_mut().zip(&["X", "Y", "Z"]) {
+                        if let Some(v) = self.curve_node_value_at_time(node.id, channel, time) {
+                            *value = v;
+                        }
+                    }
+                }
+            };
+            animate("Lcl Translation", &mut transform.translation);
+            animate("Lcl Rotation", &mut transform.rotation);
+            animate("Lcl Scaling", &mut transform.scaling);
+            transform
+        })
+    }
+
+    /// Returns the local transform matrix of the model at the given time.
+    ///
+    /// See `FbxScene::model_transform_at_time()`.
+    pub fn local_transform_at_time(&self, model: i64, time: i64) -> Option<Mat4> {
+        self.model_transform_at_time(model, time).map(|transform| transform.local_matrix())
+    }
+}
@@ -4,6 +4,7 @@ use std::error;
 use std::fmt;
 use std::io;
 use fbx_binary_reader;
+use objects::MappingMode;
 
 
 pub type Result<T> = ::std::result::Result<T, Error>;
@@ -60,3 +61,77 @@ impl From<fbx_binary_reader::Error> for Error {
         Error::ParseError(err)
     }
 }
+
+pub type MeshResult<T> = ::std::result::Result<T, MeshError>;
+
+/// A type for errors on accessing mesh data.
+///
+/// These errors are caused by malformed data, and they are not critical to the whole scene.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshError {
+    /// ID of the mesh.
+    pub mesh: i64,
+    /// Channel and name of the layer element, if the error is related to a layer element.
+    pub layer_element: Option<(i32, String)>,
+    pub kind: MeshErrorKind,
+}
+
+/// Kind of `MeshError`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeshErrorKind {
+    /// The mesh is not triangulated.
+    NotTriangulated,
+    /// The layer element has no data.
+    NoData,
+    /// The mapping mode of the layer element is unsupported.
+    UnsupportedMappingMode(MappingMode),
+    /// The polygon vertex index is out of range.
+    PolygonVertexOutOfRange(usize),
+    /// The control point index is out of range of the mesh vertices.
+    ControlPointOutOfRange(usize),
+    /// The mapped index (index of control point, polygon vertex, etc.) is out of range of the
+    /// layer element indices.
+    IndexOutOfRange(usize),
+    /// The element index is out of range of the layer element data.
+    ElementOutOfRange(usize),
+    /// No edge is found for the polygon vertex.
+    EdgeNotFound(usize),
+    /// No polygon is found for the polygon vertex.
+    PolygonNotFound(usize),
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "Mesh error (mesh id={}", self.mesh));
+        if let Some((channel, ref name)) = self.layer_element {
+            try!(write!(f, ", layer element channel={} name=`{}`", channel, name));
+        }
+        match self.kind {
+            MeshErrorKind::NotTriangulated => write!(f, "): mesh is not triangulated"),
+            MeshErrorKind::NoData => write!(f, "): layer element has no data"),
+            MeshErrorKind::UnsupportedMappingMode(mode) => write!(f, "): unsupported mapping mode `{:?}`", mode),
+            MeshErrorKind::PolygonVertexOutOfRange(i) => write!(f, "): polygon vertex index {} is out of range", i),
+            MeshErrorKind::ControlPointOutOfRange(i) => write!(f, "): control point index {} is out of range", i),
+            MeshErrorKind::IndexOutOfRange(i) => write!(f, "): index {} is out of range of layer element indices", i),
+            MeshErrorKind::ElementOutOfRange(i) => write!(f, "): element index {} is out of range of layer element data", i),
+            MeshErrorKind::EdgeNotFound(i) => write!(f, "): edge not found for polygon vertex {}", i),
+            MeshErrorKind::PolygonNotFound(i) => write!(f, "): polygon not found for polygon vertex {}", i),
+        }
+    }
+}
+
+impl error::Error for MeshError {
+    fn description(&self) -> &str {
+        match self.kind {
+            MeshErrorKind::NotTriangulated => "Mesh is not triangulated",
+            MeshErrorKind::NoData => "Layer element has no data",
+            MeshErrorKind::UnsupportedMappingMode(_) => "Unsupported mapping mode",
+            MeshErrorKind::PolygonVertexOutOfRange(_) => "Polygon vertex index out of range",
+            MeshErrorKind::ControlPointOutOfRange(_) => "Control point index out of range",
+            MeshErrorKind::IndexOutOfRange(_) => "Layer element index out of range",
+            MeshErrorKind::ElementOutOfRange(_) => "Layer element data index out of range",
+            MeshErrorKind::EdgeNotFound(_) => "Edge not found",
+            MeshErrorKind::PolygonNotFound(_) => "Polygon not found",
+        }
+    }
+}
@@ -11,18 +11,37 @@ pub use scene::FbxScene;
 use std::io::Read;
 use std::path::Path;
 
+pub mod animation;
 pub mod connections;
 pub mod definitions;
 pub mod error;
 pub mod fbx_header_extension;
+pub mod morph;
 pub mod objects;
+pub mod point_cache;
 pub mod property;
 pub mod scene;
+pub mod skinning;
 pub mod utils;
 
+mod math;
 mod node_loader;
 
 
+/// Floating point type of positions and matrices.
+///
+/// This is `f64` if the `double-precision` feature is enabled, and `f32` otherwise.
+/// FBX stores positions and matrices in `f64`.
+#[cfg(feature = "double-precision")]
+pub type Real = f64;
+/// Floating point type of positions and matrices.
+///
+/// This is `f64` if the `double-precision` feature is enabled, and `f32` otherwise.
+/// FBX stores positions and matrices in `f64`.
+#[cfg(not(feature = "double-precision"))]
+pub type Real = f32;
+
+
 /// Load FBX from the given path.
 pub fn load_from_file<P: AsRef<Path>, C: FormatConvert>(path: P, converter: C) -> error::Result<FbxScene<C::ImageResult>> {
     use std::fs::File;
@@ -0,0 +1,283 @@
+//! Contains small vector helpers used by the geometry utilities.
+
+use Real;
+
+pub fn vec3_add(v1: [Real; 3], v2: [Real; 3]) -> [Real; 3] {
+    [v1[0] + v2[0], v1[1] + v2[1], v1[2] + v2[2]]
+}
+
+pub fn vec3_sub(v1: [Real; 3], v2: [Real; 3]) -> [Real; 3] {
+    [v1[0] - v2[0], v1[1] - v2[1], v1[2] - v2[2]]
+}
+
+pub fn vec3_scale(v: [Real; 3], s: Real) -> [Real; 3] {
+    [v[0] * s, v[1] * s, v[2] * s]
+}
+
+pub fn vec3_dot(v1: [Real; 3], v2: [Real; 3]) -> Real {
+    v1[0] * v2[0] + v1[1] * v2[1] + v1[2] * v2[2]
+}
+
+pub fn vec3_cross(v1: [Real; 3], v2: [Real; 3]) -> [Real; 3] {
+    [
+        v1[1] * v2[2] - v1[2] * v2[1],
+        v1[2] * v2[0] - v1[0] * v2[2],
+        v1[0] * v2[1] - v1[1] * v2[0],
+    ]
+}
+
+pub fn vec3_length(v: [Real; 3]) -> Real {
+    vec3_dot(v, v).sqrt()
+}
+
+/// Returns the normalized vector, or zero vector if the length of `v` is zero.
+pub fn vec3_normalize(v: [Real; 3]) -> [Real; 3] {
+    let len = vec3_length(v);
+    if len > 0.0 {
+        vec3_scale(v, 1.0 / len)
+    } else {
+        [0.0; 3]
+    }
+}
+
+/// Returns the angle between two vectors in radians.
+///
+/// Zero vectors are treated as perpendicular to any vector.
+pub fn vec3_angle(v1: [Real; 3], v2: [Real; 3]) -> Real {
+    let len = vec3_length(v1) * vec3_length(v2);
+    if len > 0.0 {
+        (vec3_dot(v1, v2) / len).max(-1.0).min(1.0).acos()
+    } else {
+        ::std::f64::consts::FRAC_PI_2 as Real
+    }
+}
+
+/// Returns the non-normalized normal of the polygon, computed with Newell's method.
+///
+/// The length of the result is twice the area of the (planar) polygon.
+pub fn polygon_normal<I>(points: I) -> [Real; 3]
+    where I: Clone + IntoIterator<Item = [Real; 3]>
+{
+    let mut normal = [0.0; 3];
+    let next_iter = points.clone().into_iter().skip(1).chain(points.clone().into_iter().take(1));
+    for (cur, next) in points.into_iter().zip(next_iter) {
+        normal[0] += (cur[1] - next[1]) * (cur[2] + next[2]);
+        normal[1] += (cur[2] - next[2]) * (cur[0] + next[0]);
+        normal[2] += (cur[0] - next[0]) * (cur[1] + next[1]);
+    }
+    normal
+}
+
+/// Floating point types (`f32` and `f64`).
+pub trait Float: Copy {
+    fn is_finite(self) -> bool;
+    fn zero() -> Self;
+}
+
+impl Float for f32 {
+    fn is_finite(self) -> bool {
+        f32::is_finite(self)
+    }
+
+    fn zero() -> Self {
+        0.0
+    }
+}
+
+impl Float for f64 {
+    fn is_finite(self) -> bool {
+        f64::is_finite(self)
+    }
+
+    fn zero() -> Self {
+        0.0
+    }
+}
+
+/// Replaces non-finite values with zero, and returns the number of replaced values.
+pub fn sanitize_values<F: Float, T: AsMut<[F]>>(values: &mut [T]) -> usize {
+    let mut count = 0;
+    for value in values.iter_mut().flat_map(|v| v.as_mut().iter_mut()) {
+        if !value.is_finite() {
+            *value = F::zero();
+            count += 1;
+        }
+    }
+    count
+}
+
+/// 4x4 matrix, stored as an array of columns (same layout as matrices in FBX).
+pub type Mat4 = [[Real; 4]; 4];
+
+pub fn mat4_identity() -> Mat4 {
+    [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]
+}
+
+pub fn mat4_mul(m1: &Mat4, m2: &Mat4) -> Mat4 {
+    let mut result = [[0.0; 4]; 4];
+    for col in 0..4 {
+        for row in 0..4 {
+            result[col][row] = (0..4).map(|i| m1[i][row] * m2[col][i]).sum();
+        }
+    }
+    result
+}
+
+pub fn mat4_translation(v: [Real; 3]) -> Mat4 {
+    let mut m = mat4_identity();
+    m[3] = [v[0], v[1], v[2], 1.0];
+    m
+}
+
+pub fn mat4_scaling(v: [Real; 3]) -> Mat4 {
+    let mut m = mat4_identity();
+    m[0][0] = v[0];
+    m[1][1] = v[1];
+    m[2][2] = v[2];
+    m
+}
+
+/// Returns the rotation matrix around the given axis (0: X, 1: Y, 2: Z) in degrees.
+pub fn mat4_rotation_axis(axis: usize, degrees: Real) -> Mat4 {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+    let mut m = mat4_identity();
+    m[a][a] = cos;
+    m[a][b] = sin;
+    m[b][a] = -sin;
+    m[b][b] = cos;
+    m
+}
+
+pub fn mat4_transform_point(m: &Mat4, p: [Real; 3]) -> [Real; 3] {
+    let mut result = [m[3][0], m[3][1], m[3][2]];
+    for (i, r) in result.iter_mut().enumerate() {
+        *r += m[0][i] * p[0] + m[1][i] * p[1] + m[2][i] * p[2];
+    }
+    result
+}
+
+/// Returns the inverse matrix, or `None` if the matrix is singular.
+pub fn mat4_inverse(m: &Mat4) -> Option<Mat4> {
+    // Gauss-Jordan elimination with partial pivoting.
+    let mut a = *m;
+    let mut inv = mat4_identity();
+    for col in 0..4 {
+        let pivot = (col..4).max_by(|&r1, &r2| a[col][r1].abs().partial_cmp(&a[col][r2].abs()).unwrap_or(::std::cmp::Ordering::Equal)).unwrap();
+        if a[col][pivot] == 0.0 || !a[col][pivot].is_finite() {
+            return None;
+        }
+        for c in 0..4 {
+            a[c].swap(col, pivot);
+            inv[c].swap(col, pivot);
+        }
+        let scale = 1.0 / a[col][col];
+        for c in 0..4 {
+            a[c][col] *= scale;
+            inv[c][col] *= scale;
+        }
+        for row in 0..4 {
+            if row == col {
+                continue;
+            }
+            let factor = a[col][row];
+            if factor != 0.0 {
+                for c in 0..4 {
+                    a[c][row] -= factor * a[c][col];
+                    inv[c][row] -= factor * inv[c][col];
+                }
+            }
+        }
+    }
+    Some(inv)
+}
+
+pub fn mat4_transform_vector(m: &Mat4, v: [Real; 3]) -> [Real; 3] {
+    let mut result = [0.0; 3];
+    for (i, r) in result.iter_mut().enumerate() {
+        *r = m[0][i] * v[0] + m[1][i] * v[1] + m[2][i] * v[2];
+    }
+    result
+}
+
+pub fn mat4_transpose(m: &Mat4) -> Mat4 {
+    let mut result = [[0.0; 4]; 4];
+    for col in 0..4 {
+        for row in 0..4 {
+            result[col][row] = m[row][col];
+        }
+    }
+    result
+}
+
+/// Returns the maximum absolute difference between elements of two matrices.
+pub fn mat4_max_difference(m1: &Mat4, m2: &Mat4) -> Real {
+    m1.iter().zip(m2.iter())
+        .flat_map(|(c1, c2)| c1.iter().zip(c2.iter()))
+        .fold(0.0, |max, (v1, v2)| max.max((v1 - v2).abs()))
+}
+
+/// Quaternion, stored as `[x, y, z, w]`.
+pub type Quat = [Real; 4];
+
+pub fn quat_mul(q1: Quat, q2: Quat) -> Quat {
+    [
+        q1[3] * q2[0] + q1[0] * q2[3] + q1[1] * q2[2] - q1[2] * q2[1],
+        q1[3] * q2[1] - q1[0] * q2[2] + q1[1] * q2[3] + q1[2] * q2[0],
+        q1[3] * q2[2] + q1[0] * q2[1] - q1[1] * q2[0] + q1[2] * q2[3],
+        q1[3] * q2[3] - q1[0] * q2[0] - q1[1] * q2[1] - q1[2] * q2[2],
+    ]
+}
+
+pub fn quat_conjugate(q: Quat) -> Quat {
+    [-q[0], -q[1], -q[2], q[3]]
+}
+
+pub fn quat_rotate(q: Quat, v: [Real; 3]) -> [Real; 3] {
+    let r = quat_mul(quat_mul(q, [v[0], v[1], v[2], 0.0]), quat_conjugate(q));
+    [r[0], r[1], r[2]]
+}
+
+/// Returns the rotation of the matrix as a unit quaternion.
+///
+/// Scaling is removed by normalizing the columns.
+pub fn quat_from_mat4(m: &Mat4) -> Quat {
+    let x = vec3_normalize([m[0][0], m[0][1], m[0][2]]);
+    let y = vec3_normalize([m[1][0], m[1][1], m[1][2]]);
+    let z = vec3_normalize([m[2][0], m[2][1], m[2][2]]);
+    let trace = x[0] + y[1] + z[2];
+    let q = if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        [(y[2] - z[1]) / s, (z[0] - x[2]) / s, (x[1] - y[0]) / s, 0.25 * s]
+    } else if x[0] > y[1] && x[0] > z[2] {
+        let s = (1.0 + x[0] - y[1] - z[2]).sqrt() * 2.0;
+        [0.25 * s, (y[0] + x[1]) / s, (z[0] + x[2]) / s, (y[2] - z[1]) / s]
+    } else if y[1] > z[2] {
+        let s = (1.0 + y[1] - x[0] - z[2]).sqrt() * 2.0;
+        [(y[0] + x[1]) / s, 0.25 * s, (z[1] + y[2]) / s, (z[0] - x[2]) / s]
+    } else {
+        let s = (1.0 + z[2] - x[0] - y[1]).sqrt() * 2.0;
+        [(z[0] + x[2]) / s, (z[1] + y[2]) / s, 0.25 * s, (x[1] - y[0]) / s]
+    };
+    let len = (q[0] * q[0] + q[1] * q[1] + q[2] * q[2] + q[3] * q[3]).sqrt();
+    if len > 0.0 {
+        [q[0] / len, q[1] / len, q[2] / len, q[3] / len]
+    } else {
+        [0.0, 0.0, 0.0, 1.0]
+    }
+}
+
+/// Converts a vector of `f32` (such as a normal) into `Real`.
+pub fn vec3_to_real(v: [f32; 3]) -> [Real; 3] {
+    [v[0] as Real, v[1] as Real, v[2] as Real]
+}
+
+/// Converts a vector of `Real` into `f32`.
+pub fn vec3_to_f32(v: [Real; 3]) -> [f32; 3] {
+    [v[0] as f32, v[1] as f32, v[2] as f32]
+}
@@ -0,0 +1,188 @@
+//! Contains evaluation of blend shapes.
+
+use std::cmp::Ordering;
+use math::{Mat4, vec3_add, vec3_normalize, vec3_scale, vec3_to_f32, vec3_to_real};
+use objects::geometry::Mesh;
+use objects::geometry::mesh::FlattenedMesh;
+use scene::FbxScene;
+use skinning::{DeformedMesh, SkinDeformer};
+use Real;
+use super::{MorphChannel, MorphTarget};
+
+
+impl MorphChannel {
+    /// Returns the weight of each target at the given weight (in percent) of the channel.
+    ///
+    /// Targets are sorted by full weights, and the channel weight is interpolated
+    /// piecewise-linearly between the undeformed mesh (at 0 percent) and the targets, so that
+    /// at most two adjacent targets have non-zero weights.
+    /// Weights below 0 or beyond the last full weight extrapolate the nearest segment.
+    pub fn target_weights(&self, percent: f64) -> Vec<Real> {
+        let num_targets = self.targets.len();
+        let mut weights = vec![0.0; num_targets];
+        if num_targets == 0 {
+            return weights;
+        }
+        let full_weight = |target: usize| self.targets[target].full_weight as Real;
+        let mut order = (0..num_targets).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| full_weight(a).partial_cmp(&full_weight(b)).unwrap_or(Ordering::Equal));
+
+        let percent = percent as Real;
+        // Segment from the previous target (or the undeformed mesh) to the target.
+        let segment = order.iter().position(|&target| percent <= full_weight(target)).unwrap_or(num_targets - 1);
+        let to = order[segment];
+        let from = if segment == 0 { None } else { Some(order[segment - 1]) };
+        let (w0, w1) = (from.map_or(0.0, |target| full_weight(target)), full_weight(to));
+        let t = if w1 > w0 { (percent - w0) / (w1 - w0) } else { 1.0 };
+        weights[to] += t;
+        if let Some(from) = from {
+            weights[from] += 1.0 - t;
+        }
+        weights
+    }
+
+    /// Returns the weight (in percent) of the channel at the given time (in ticks).
+    ///
+    /// `deform_percent` is returned if the channel is not animated.
+    pub fn weight_at_time(&self, time: i64) -> f64 {
+        self.deform_percent_curve.as_ref().and_then(|curve| curve.evaluate(time)).unwrap_or(self.deform_percent)
+    }
+}
+
+/// Blend shape deformer of a mesh, created by `FbxScene::morph_deformer()`.
+///
+/// Weights of channels are given in percent, in the order of `channels`.
+/// `MorphDeformer::current_weights()` returns the static weights, and
+/// `MorphDeformer::weights_at_time()` returns the animated weights.
+#[derive(Debug, Clone)]
+pub struct MorphDeformer {
+    /// Mesh id.
+    pub mesh: i64,
+    pub channels: Vec<MorphChannel>,
+}
+
+impl MorphDeformer {
+    /// Returns `deform_percent` of each channel.
+    pub fn current_weights(&self) -> Vec<f64> {
+        self.channels.iter().map(|channel| channel.deform_percent).collect()
+    }
+
+    /// Returns the weight of each channel at the given time (in ticks).
+    ///
+    /// See `MorphChannel::weight_at_time()`.
+    pub fn weights_at_time(&self, time: i64) -> Vec<f64> {
+        self.channels.iter().map(|channel| channel.weight_at_time(time)).collect()
+    }
+
+    /// Returns the deformer for vertices of the flattened mesh.
+    ///
+    /// See `MorphTarget::flattened()`.
+    pub fn flattened(&self, flattened: &FlattenedMesh) -> MorphDeformer {
+        MorphDeformer {
+            mesh: self.mesh,
+            channels: self.channels.iter().map(|channel| channel.flattened(flattened)).collect(),
+        }
+    }
+
+    /// Returns targets with non-zero weights.
+    fn weighted_targets(&self, weights: &[f64]) -> Vec<(&MorphTarget, Real)> {
+        if weights.len() != self.channels.len() {
+            warn!("Number of weights ({}) does not match the number of blend shape channels ({}) of mesh (id={})",
+                  weights.len(), self.channels.len(), self.mesh);
+        }
+        self.channels.iter().enumerate()
+            .flat_map(|(i, channel)| {
+                let weight = weights.get(i).cloned().unwrap_or(0.0);
+                channel.targets.iter().zip(channel.target_weights(weight))
+            })
+            .filter(|&(_, weight)| weight != 0.0)
+            .collect()
+    }
+
+    /// Deforms the control points (or vertices, for flattened deformers).
+    ///
+    /// Deltas of targets are added in proportion to their weights.
+    pub fn deform_positions(&self, positions: &[[Real; 3]], weights: &[f64]) -> Vec<[Real; 3]> {
+        let mut result = positions.to_vec();
+        add_position_deltas(&mut result, &self.weighted_targets(weights));
+        result
+    }
+
+    /// Deforms the normals of control points (or vertices, for flattened deformers).
+    ///
+    /// Deltas of targets with normals are added in proportion to their weights, and results
+    /// are normalized.
+    pub fn deform_normals(&self, normals: &[[f32; 3]], weights: &[f64]) -> Vec<[f32; 3]> {
+        let mut result = normals.iter().map(|&n| vec3_to_real(n)).collect::<Vec<_>>();
+        for (target, weight) in self.weighted_targets(weights) {
+            if let Some(ref deltas) = target.normal_deltas {
+                for (n, &delta) in result.iter_mut().zip(deltas.iter()) {
+                    *n = vec3_add(*n, vec3_scale(vec3_to_real(delta), weight));
+                }
+            }
+        }
+        result.into_iter().map(|n| vec3_to_f32(vec3_normalize(n))).collect()
+    }
+
+    /// Deforms the mesh control points and normals.
+    ///
+    /// Normals of polygon vertices are deformed by normal deltas of their control points.
+    pub fn deform_mesh(&self, mesh: &Mesh, weights: &[f64]) -> DeformedMesh {
+        let source = DeformedMesh::from_mesh(mesh);
+        let targets = self.weighted_targets(weights);
+        let mut positions = source.positions;
+        add_position_deltas(&mut positions, &targets);
+        let normals = source.normals.map(|normals| {
+            if targets.iter().all(|&(target, _)| target.normal_deltas.is_none()) {
+                return normals;
+            }
+            mesh.polygons().flat_map(|polygon| polygon.control_points()).zip(normals.into_iter()).map(|(cp, n)| {
+                let n = targets.iter().fold(vec3_to_real(n), |n, &(target, weight)| {
+                    match target.normal_deltas.as_ref().and_then(|deltas| cp.as_ref().ok().and_then(|&cp| deltas.get(cp))) {
+                        Some(&delta) => vec3_add(n, vec3_scale(vec3_to_real(delta), weight)),
+                        None => n,
+                    }
+                });
+                vec3_to_f32(vec3_normalize(n))
+            }).collect()
+        });
+        DeformedMesh {
+            positions: positions,
+            normals: normals,
+        }
+    }
+
+    /// Deforms the mesh by blend shapes and then by the skin, in the order the FBX SDK applies
+    /// deformers.
+    ///
+    /// See `MorphDeformer::deform_mesh()` and `SkinDeformer::deform_mesh_from()`.
+    pub fn deform_skinned_mesh(&self, mesh: &Mesh, weights: &[f64], skin: &SkinDeformer, skinning_matrices: &[Mat4]) -> DeformedMesh {
+        skin.deform_mesh_from(mesh, &self.deform_mesh(mesh, weights), skinning_matrices)
+    }
+}
+
+/// Adds position deltas of targets in proportion to their weights.
+fn add_position_deltas(positions: &mut [[Real; 3]], targets: &[(&MorphTarget, Real)]) {
+    for &(target, weight) in targets {
+        for (p, &delta) in positions.iter_mut().zip(target.position_deltas.iter()) {
+            *p = vec3_add(*p, vec3_scale(delta, weight));
+        }
+    }
+}
+
+impl<I> FbxScene<I> {
+    /// Creates the blend shape deformer of the given mesh.
+    ///
+    /// Returns `None` if the mesh has no blend shape channels.
+    /// See `FbxScene::mesh_morph_channels()`.
+    pub fn morph_deformer(&self, mesh: i64) -> Option<MorphDeformer> {
+        let channels = self.mesh_morph_channels(mesh);
+        if channels.is_empty() {
+            return None;
+        }
+        Some(MorphDeformer {
+            mesh: mesh,
+            channels: channels,
+        })
+    }
+}
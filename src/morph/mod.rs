@@ -0,0 +1,8 @@
+//! Contains blend shape (morph target) utilities built on top of blend shapes, blend shape
+//! channels and shapes.
+
+pub use self::evaluate::MorphDeformer;
+pub use self::targets::{MorphChannel, MorphTarget};
+
+mod evaluate;
+mod targets;
@@ -0,0 +1,168 @@
+//! Contains resolution of blend shapes into dense morph targets.
+
+use objects::AnimationCurve;
+use objects::geometry::Shape;
+use objects::geometry::mesh::FlattenedMesh;
+use scene::FbxScene;
+use Real;
+
+
+/// Target of a blend shape channel, with deltas expanded from a sparse `Shape`.
+#[derive(Debug, Clone)]
+pub struct MorphTarget {
+    /// Shape id.
+    pub shape: i64,
+    pub name: String,
+    /// Weight (in percent) of the channel at which the target is fully applied.
+    pub full_weight: f32,
+    /// Position deltas for each control point (or each vertex, for targets created by
+    /// `MorphTarget::flattened()`).
+    ///
+    /// Control points not in the shape have zero deltas.
+    pub position_deltas: Vec<[Real; 3]>,
+    /// Normal deltas for each control point (or each vertex), if the shape has normals.
+    pub normal_deltas: Option<Vec<[f32; 3]>>,
+}
+
+impl MorphTarget {
+    /// Expands the shape into deltas for the given number of control points.
+    fn from_shape(shape: &Shape, full_weight: f32, num_control_points: usize) -> Self {
+        if shape.indices.len() != shape.vertices.len() {
+            warn!("Shape (id={}) has {} indices and {} vertices", shape.id, shape.indices.len(), shape.vertices.len());
+        }
+        let mut position_deltas = vec![[0.0; 3]; num_control_points];
+        let mut normal_deltas = shape.normals.as_ref().map(|_| vec![[0.0; 3]; num_control_points]);
+        let mut num_ignored = 0;
+        for (i, (&cp, &delta)) in shape.indices.iter().zip(shape.vertices.iter()).enumerate() {
+            let cp = cp as usize;
+            if cp >= num_control_points {
+                num_ignored += 1;
+                continue;
+            }
+            position_deltas[cp] = delta;
+            if let (Some(deltas), Some(normals)) = (normal_deltas.as_mut(), shape.normals.as_ref()) {
+                deltas[cp] = normals.get(i).cloned().unwrap_or([0.0; 3]);
+            }
+        }
+        if num_ignored > 0 {
+            warn!("Ignored {} out-of-range indices of shape (id={})", num_ignored, shape.id);
+        }
+        MorphTarget {
+            shape: shape.id,
+            name: shape.name.clone(),
+            full_weight: full_weight,
+            position_deltas: position_deltas,
+            normal_deltas: normal_deltas,
+        }
+    }
+
+    /// Returns the target with deltas for vertices of the flattened mesh.
+    ///
+    /// The flattened mesh should be created from the mesh of the target.
+    pub fn flattened(&self, flattened: &FlattenedMesh) -> MorphTarget {
+        let position_deltas = flattened.control_points.iter()
+            .map(|&cp| self.position_deltas.get(cp as usize).cloned().unwrap_or([0.0; 3]))
+            .collect();
+        let normal_deltas = self.normal_deltas.as_ref().map(|deltas| {
+            flattened.control_points.iter()
+                .map(|&cp| deltas.get(cp as usize).cloned().unwrap_or([0.0; 3]))
+                .collect()
+        });
+        MorphTarget {
+            shape: self.shape,
+            name: self.name.clone(),
+            full_weight: self.full_weight,
+            position_deltas: position_deltas,
+            normal_deltas: normal_deltas,
+        }
+    }
+}
+
+/// Blend shape channel of a mesh, created by `FbxScene::mesh_morph_channels()`.
+#[derive(Debug, Clone)]
+pub struct MorphChannel {
+    /// Blend shape id.
+    pub blend_shape: i64,
+    /// Blend shape channel id.
+    pub channel: i64,
+    pub name: String,
+    /// Current weight (in percent) of the channel.
+    pub deform_percent: f64,
+    /// Animation curve of `DeformPercent`, if the weight of the channel is animated.
+    pub deform_percent_curve: Option<AnimationCurve>,
+    /// Targets in the order of connections, which is the order of `full_weights`.
+    ///
+    /// A channel with several targets has in-between targets, and the last one is usually
+    /// fully applied at 100 percent.
+    pub targets: Vec<MorphTarget>,
+}
+
+impl MorphChannel {
+    /// Returns full weights of targets.
+    pub fn full_weights(&self) -> Vec<f32> {
+        self.targets.iter().map(|target| target.full_weight).collect()
+    }
+
+    /// Returns the channel with deltas of targets for vertices of the flattened mesh.
+    ///
+    /// See `MorphTarget::flattened()`.
+    pub fn flattened(&self, flattened: &FlattenedMesh) -> MorphChannel {
+        MorphChannel {
+            blend_shape: self.blend_shape,
+            channel: self.channel,
+            name: self.name.clone(),
+            deform_percent: self.deform_percent,
+            deform_percent_curve: self.deform_percent_curve.clone(),
+            targets: self.targets.iter().map(|target| target.flattened(flattened)).collect(),
+        }
+    }
+}
+
+impl<I> FbxScene<I> {
+    /// Resolves blend shapes of the given mesh into channels with dense morph targets.
+    ///
+    /// Connections Mesh → BlendShape → BlendShapeChannel → Shape are followed.
+    /// If the number of full weights of a channel does not match the number of its shapes,
+    /// full weights are evenly spaced up to 100 percent.
+    /// Returns an empty vector if the mesh does not exist or has no blend shapes.
+    pub fn mesh_morph_channels(&self, mesh: i64) -> Vec<MorphChannel> {
+        let num_control_points = match self.objects.geometry_meshes.get(&mesh) {
+            Some(mesh) => mesh.vertices.len(),
+            None => return Vec::new(),
+        };
+        let mut result = Vec::new();
+        for blend_shape in self.mesh_blend_shapes(mesh) {
+            for channel_id in self.children(blend_shape) {
+                let channel = match self.objects.blend_shape_channels.get(&channel_id) {
+                    Some(channel) => channel,
+                    None => continue,
+                };
+                let shapes = self.children(channel_id).into_iter()
+                    .filter_map(|id| self.objects.geometry_shapes.get(&id))
+                    .collect::<Vec<_>>();
+                let full_weights = if channel.full_weights.len() == shapes.len() {
+                    channel.full_weights.clone()
+                } else {
+                    if !channel.full_weights.is_empty() || shapes.len() > 1 {
+                        warn!("Blend shape channel (id={}) has {} full weights for {} shapes, evenly spaced weights are used",
+                              channel_id, channel.full_weights.len(), shapes.len());
+                    }
+                    (0..shapes.len()).map(|i| 100.0 * (i + 1) as f32 / shapes.len() as f32).collect()
+                };
+                result.push(MorphChannel {
+                    blend_shape: blend_shape,
+                    channel: channel_id,
+                    name: channel.name.clone(),
+                    deform_percent: channel.deform_percent,
+                    deform_percent_curve: self.property_curve_node(channel_id, "DeformPercent")
+                        .and_then(|node| self.curve_node_curve(node.id, "DeformPercent"))
+                        .cloned(),
+                    targets: shapes.iter().zip(full_weights.into_iter())
+                        .map(|(shape, full_weight)| MorphTarget::from_shape(shape, full_weight, num_control_points))
+                        .collect(),
+                });
+            }
+        }
+        result
+    }
+}
@@ -2,8 +2,9 @@
 
 use std::io::Read;
 use std::path::Path;
-use fbx_binary_reader::{EventReader, FbxEvent, DelayedProperties};
+use fbx_binary_reader::{EventReader, FbxEvent, DelayedProperties, Property};
 use error::Result;
+use Real;
 
 
 #[derive(Debug, Clone)]
@@ -70,6 +71,39 @@ pub fn ignore_current_node<R: Read>(reader: &mut EventReader<R>) -> Result<()> {
     }
 }
 
+/// Returns the property value as a vector of `Real`.
+#[cfg(not(feature = "double-precision"))]
+pub fn as_vec_real(property: &Property) -> Option<Vec<Real>> {
+    property.as_vec_f32().map(|v| v.into_owned())
+}
+
+/// Returns the property value as a vector of `Real`.
+#[cfg(feature = "double-precision")]
+pub fn as_vec_real(property: &Property) -> Option<Vec<Real>> {
+    property.as_vec_f64().map(|v| v.into_owned())
+}
+
+/// Returns the property value as a vector of `i64`.
+pub fn as_vec_i64(property: &Property) -> Option<Vec<i64>> {
+    match *property {
+        Property::VecI64(ref vec) => Some(vec.to_vec()),
+        Property::VecI32(ref vec) => Some(vec.iter().map(|&v| v as i64).collect()),
+        _ => None,
+    }
+}
+
+/// Returns the property value as a 4x4 matrix (an array of columns).
+pub fn as_matrix(property: &Property) -> Option<[[Real; 4]; 4]> {
+    as_vec_real(property).into_iter().find(|v| v.len() >= 16).map(|v| {
+        [
+            [v[0], v[1], v[2], v[3]],
+            [v[4], v[5], v[6], v[7]],
+            [v[8], v[9], v[10], v[11]],
+            [v[12], v[13], v[14], v[15]],
+        ]
+    })
+}
+
 pub trait FormatConvert {
     type ImageResult;
 
@@ -0,0 +1,274 @@
+//! Contains `/Objects/AnimationCurve` node-related stuff.
+
+use std::io::Read;
+use fbx_binary_reader::EventReader;
+use definitions::Definitions;
+use error::Result;
+use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, as_vec_i64, ignore_current_node};
+use objects::properties::ObjectProperties;
+
+
+/// Number of FBX time units (ticks) per second.
+pub const TICKS_PER_SECOND: i64 = 46_186_158_000;
+
+/// Converts seconds to FBX time (ticks).
+pub fn time_from_seconds(seconds: f64) -> i64 {
+    (seconds * TICKS_PER_SECOND as f64).round() as i64
+}
+
+/// Animation curve, which is a keyframed function of time.
+#[derive(Debug, Clone)]
+pub struct AnimationCurve {
+    pub id: i64,
+    pub name: String,
+    /// Value of the curve without keys.
+    pub default: Option<f64>,
+    /// Keys, sorted by time.
+    pub keys: Vec<AnimationKey>,
+}
+
+impl AnimationCurve {
+    /// Returns the value of the curve at the given time (in ticks).
+    ///
+    /// Values before the first key and after the last key are the values of the first and last
+    /// keys. Returns `Default` if the curve has no keys.
+    /// Tangent weights and velocities are not supported, and cubic segments are evaluated as
+    /// Hermite curves of `right_slope` and `next_left_slope`.
+    pub fn evaluate(&self, time: i64) -> Option<f64> {
+        let (first, last) = match (self.keys.first(), self.keys.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return self.default,
+        };
+        if time <= first.time {
+            return Some(first.value as f64);
+        }
+        if time >= last.time {
+            return Some(last.value as f64);
+        }
+        // `first.time < time < last.time`, so `0 < index < keys.len()`.
+        let index = match self.keys.binary_search_by(|key| key.time.cmp(&time)) {
+            Ok(index) => return Some(self.keys[index].value as f64),
+            Err(index) => index,
+        };
+        let (key, next) = (&self.keys[index - 1], &self.keys[index]);
+        let (v0, v1) = (key.value as f64, next.value as f64);
+        let duration = (next.time - key.time) as f64;
+        let s = (time - key.time) as f64 / duration;
+        Some(match key.interpolation {
+            KeyInterpolation::Constant => v0,
+            KeyInterpolation::ConstantNext => v1,
+            KeyInterpolation::Linear => v0 + (v1 - v0) * s,
+            KeyInterpolation::Cubic => {
+                // Slopes are values per second.
+                let seconds = duration / TICKS_PER_SECOND as f64;
+                let m0 = key.right_slope as f64 * seconds;
+                let m1 = key.next_left_slope as f64 * seconds;
+                let (s2, s3) = (s * s, s * s * s);
+                (2.0 * s3 - 3.0 * s2 + 1.0) * v0 + (s3 - 2.0 * s2 + s) * m0 + (-2.0 * s3 + 3.0 * s2) * v1 + (s3 - s2) * m1
+            },
+        })
+    }
+}
+
+/// Key of an animation curve.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationKey {
+    /// Time in ticks (see `TICKS_PER_SECOND`).
+    pub time: i64,
+    pub value: f32,
+    /// Interpolation between this key and the next key.
+    pub interpolation: KeyInterpolation,
+    /// Slope (value per second) at the right of the key.
+    pub right_slope: f32,
+    /// Slope (value per second) at the left of the next key.
+    pub next_left_slope: f32,
+}
+
+/// Interpolation between keys of an animation curve.
+///
+/// See [Help: FbxAnimCurveDef Class
+/// Reference](http://help.autodesk.com/view/FBX/2016/ENU/?guid=__cpp_ref_class_fbx_anim_curve_def_html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyInterpolation {
+    /// The value of the key is held until the next key.
+    Constant,
+    /// The value of the next key is used from the key.
+    ConstantNext,
+    Linear,
+    Cubic,
+}
+
+impl KeyInterpolation {
+    /// Gets interpolation from `KeyAttrFlags` value.
+    pub fn from_flags(flags: i32) -> Option<Self> {
+        if flags & 0x08 != 0 {
+            Some(KeyInterpolation::Cubic)
+        } else if flags & 0x04 != 0 {
+            Some(KeyInterpolation::Linear)
+        } else if flags & 0x02 != 0 {
+            if flags & 0x100 != 0 {
+                Some(KeyInterpolation::ConstantNext)
+            } else {
+                Some(KeyInterpolation::Constant)
+            }
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AnimationCurveLoader<'a> {
+    obj_props: &'a ObjectProperties<'a>,
+    default: Option<f64>,
+    key_time: Option<Vec<i64>>,
+    key_value_float: Option<Vec<f32>>,
+    key_attr_flags: Option<Vec<i32>>,
+    key_attr_data_float: Option<Vec<f32>>,
+    key_attr_ref_count: Option<Vec<i32>>,
+}
+
+impl<'a> AnimationCurveLoader<'a> {
+    pub fn new(_definitions: &'a Definitions, obj_props: &'a ObjectProperties<'a>) -> Self {
+        AnimationCurveLoader {
+            obj_props: obj_props,
+            default: None,
+            key_time: None,
+            key_value_float: None,
+            key_attr_flags: None,
+            key_attr_data_float: None,
+            key_attr_ref_count: None,
+        }
+    }
+}
+
+impl<'a> NodeLoaderCommon for AnimationCurveLoader<'a> {
+    type Target = Option<AnimationCurve>;
+
+    fn on_finish(self) -> Result<Self::Target> {
+        if_all_some!{(
+            key_time=self.key_time,
+            key_value_float=self.key_value_float,
+        ) {
+            if key_time.len() != key_value_float.len() {
+                error!("Numbers of key times and key values differ at `/Objects/AnimationCurve` (id={})", self.obj_props.id);
+                return Ok(None);
+            }
+            let flags = self.key_attr_flags.unwrap_or_default();
+            let data = self.key_attr_data_float.unwrap_or_default();
+            let ref_counts = self.key_attr_ref_count.unwrap_or_default();
+            // Each key attribute is shared by `KeyAttrRefCount` consecutive keys.
+            let mut attributes = ref_counts.iter().enumerate()
+                .flat_map(|(i, &count)| ::std::iter::repeat(i).take(count as usize));
+            let keys = key_time.into_iter().zip(key_value_float).map(|(time, value)| {
+                let attr = attributes.next();
+                let slope = |offset: usize| attr.and_then(|i| data.get(i * 4 + offset)).cloned().unwrap_or(0.0);
+                AnimationKey {
+                    time: time,
+                    value: value,
+                    // Keys without attributes are linearly interpolated.
+                    interpolation: attr.and_then(|i| flags.get(i)).and_then(|&f| KeyInterpolation::from_flags(f)).unwrap_or(KeyInterpolation::Linear),
+                    right_slope: slope(0),
+                    next_left_slope: slope(1),
+                }
+            }).collect::<Vec<_>>();
+            if keys.windows(2).any(|w| w[0].time > w[1].time) {
+                error!("Keys are not sorted by time at `/Objects/AnimationCurve` (id={})", self.obj_props.id);
+                return Ok(None);
+            }
+            Ok(Some(AnimationCurve {
+                id: self.obj_props.id,
+                name: self.obj_props.name.to_owned(),
+                default: self.default,
+                keys: keys,
+            }))
+        } else {
+            error!("Required property not found for `/Objects/AnimationCurve`");
+            Ok(None)
+        }}
+    }
+}
+
+impl<'a, R: Read> NodeLoader<R> for AnimationCurveLoader<'a> {
+    fn on_child_node(&mut self, reader: &mut EventReader<R>, node_info: RawNodeInfo) -> Result<()> {
+        let RawNodeInfo { name, properties } = node_info;
+        match name.as_ref() {
+            "KeyVer" => {
+                match properties.iter().next().and_then(|p| p.get_i32()) {
+                    Some(4008) | Some(4009) => {},
+                    Some(v) => {
+                        warn!("Maybe unsupported version of `/Objects/AnimationCurve` node: ver={}", v);
+                    },
+                    None => {
+                        error!("Invalid proprety at `/Objects/AnimationCurve/KeyVer`: type error");
+                    },
+                }
+            },
+            "Default" => {
+                self.default = properties.iter().next().and_then(|p| p.as_f64());
+            },
+            "KeyTime" => {
+                self.key_time = properties.iter().next().and_then(|p| as_vec_i64(&p));
+            },
+            "KeyValueFloat" => {
+                self.key_value_float = properties.iter().next().and_then(|p| p.into_vec_f32().ok());
+            },
+            "KeyAttrFlags" => {
+                self.key_attr_flags = properties.iter().next().and_then(|p| p.extract_vec_i32().ok());
+            },
+            "KeyAttrDataFloat" => {
+                self.key_attr_data_float = properties.iter().next().and_then(|p| p.into_vec_f32().ok());
+            },
+            "KeyAttrRefCount" => {
+                self.key_attr_ref_count = properties.iter().next().and_then(|p| p.extract_vec_i32().ok());
+            },
+            _ => {
+                warn!("Unknown node: `/Objects/AnimationCurve/{}`", name);
+            },
+        }
+        try!(ignore_current_node(reader));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnimationCurve, AnimationKey, KeyInterpolation, TICKS_PER_SECOND};
+
+    fn curve(interpolation: KeyInterpolation, right_slope: f32, next_left_slope: f32) -> AnimationCurve {
+        let key = |time, value| AnimationKey {
+            time: time,
+            value: value,
+            interpolation: interpolation,
+            right_slope: right_slope,
+            next_left_slope: next_left_slope,
+        };
+        AnimationCurve {
+            id: 0,
+            name: String::new(),
+            default: Some(5.0),
+            keys: vec![key(0, 0.0), key(TICKS_PER_SECOND, 10.0)],
+        }
+    }
+
+    #[test]
+    fn interpolation() {
+        let half = TICKS_PER_SECOND / 2;
+        assert_eq!(curve(KeyInterpolation::Constant, 0.0, 0.0).evaluate(half), Some(0.0));
+        assert_eq!(curve(KeyInterpolation::ConstantNext, 0.0, 0.0).evaluate(half), Some(10.0));
+        assert_eq!(curve(KeyInterpolation::Linear, 0.0, 0.0).evaluate(half), Some(5.0));
+        // Cubic segment with linear slopes is a line.
+        let cubic = curve(KeyInterpolation::Cubic, 10.0, 10.0);
+        assert!((cubic.evaluate(half / 2).unwrap() - 2.5).abs() < 1e-9);
+        // Outside of keys.
+        assert_eq!(cubic.evaluate(-1), Some(0.0));
+        assert_eq!(cubic.evaluate(2 * TICKS_PER_SECOND), Some(10.0));
+    }
+
+    #[test]
+    fn no_keys() {
+        let mut curve = curve(KeyInterpolation::Linear, 0.0, 0.0);
+        curve.keys.clear();
+        assert_eq!(curve.evaluate(0), Some(5.0));
+    }
+}
@@ -0,0 +1,75 @@
+//! Contains `/Objects/AnimationCurveNode` node-related stuff.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use fbx_binary_reader::EventReader;
+use definitions::Definitions;
+use error::Result;
+use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, ignore_current_node};
+use objects::properties::ObjectProperties;
+use property::{GenericProperties, GenericPropertiesLoader};
+
+
+/// Animation curve node, which animates a property of an object.
+///
+/// A curve node is connected to the animated property (such as `Lcl Translation` of a model or
+/// `DeformPercent` of a blend shape channel), and animation curves are connected to channels of
+/// the curve node (such as `d|X`).
+#[derive(Debug, Clone)]
+pub struct AnimationCurveNode {
+    pub id: i64,
+    pub name: String,
+    /// Values of channels used when no curve is connected to the channel.
+    ///
+    /// Keys are channel names without `d|` prefix (such as `X` or `DeformPercent`).
+    pub defaults: BTreeMap<String, f64>,
+}
+
+#[derive(Debug)]
+pub struct AnimationCurveNodeLoader<'a> {
+    obj_props: &'a ObjectProperties<'a>,
+    properties: Option<GenericProperties>,
+}
+
+impl<'a> AnimationCurveNodeLoader<'a> {
+    pub fn new(_definitions: &'a Definitions, obj_props: &'a ObjectProperties<'a>) -> Self {
+        AnimationCurveNodeLoader {
+            obj_props: obj_props,
+            properties: None,
+        }
+    }
+}
+
+impl<'a> NodeLoaderCommon for AnimationCurveNodeLoader<'a> {
+    type Target = Option<AnimationCurveNode>;
+
+    fn on_finish(self) -> Result<Self::Target> {
+        let defaults = self.properties.map_or_else(BTreeMap::new, |properties| {
+            properties.properties.into_iter()
+                .filter(|&(ref name, _)| name.starts_with("d|"))
+                .filter_map(|(name, node)| node.value.get_f64().map(|v| (name[2..].to_owned(), v)))
+                .collect()
+        });
+        Ok(Some(AnimationCurveNode {
+            id: self.obj_props.id,
+            name: self.obj_props.name.to_owned(),
+            defaults: defaults,
+        }))
+    }
+}
+
+impl<'a, R: Read> NodeLoader<R> for AnimationCurveNodeLoader<'a> {
+    fn on_child_node(&mut self, reader: &mut EventReader<R>, node_info: RawNodeInfo) -> Result<()> {
+        let RawNodeInfo { name, .. } = node_info;
+        match name.as_ref() {
+            "Properties70" => {
+                self.properties = Some(try!(GenericPropertiesLoader::new(70).load(reader)));
+            },
+            _ => {
+                warn!("Unknown node: `/Objects/AnimationCurveNode/{}`", name);
+                try!(ignore_current_node(reader));
+            },
+        }
+        Ok(())
+    }
+}
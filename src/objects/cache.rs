@@ -0,0 +1,135 @@
+//! Contains `/Objects/Cache` node-related stuff.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use fbx_binary_reader::EventReader;
+use definitions::Definitions;
+use error::Result;
+use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, ignore_current_node};
+use objects::properties::ObjectProperties;
+use property::{GenericProperties, GenericPropertiesLoader, OptionalProperties};
+use utils::resolve_external_path;
+
+
+/// Point cache referring to external cache files, used by `VertexCacheDeformer`.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    pub id: i64,
+    pub name: String,
+    pub file_format: CacheFileFormat,
+    /// Absolute path of the cache file.
+    ///
+    /// For Maya caches, this is the path of the XML description file, and point data is in
+    /// `.mc` or `.mcx` files next to it.
+    pub filename: PathBuf,
+    /// Path of the cache file relative to the FBX file.
+    pub relative_filename: PathBuf,
+}
+
+impl Cache {
+    /// Returns the path of the existing cache file, or `None` if not found.
+    ///
+    /// `base_dir` is the directory of the FBX file. See `utils::resolve_external_path()`.
+    pub fn resolve_filename(&self, base_dir: &Path) -> Option<PathBuf> {
+        resolve_external_path(base_dir, &self.filename, &self.relative_filename)
+    }
+}
+
+/// Format of cache files.
+///
+/// See [Help: FbxCache Class
+/// Reference](http://help.autodesk.com/view/FBX/2016/ENU/?guid=__cpp_ref_class_fbx_cache_html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheFileFormat {
+    Unknown,
+    /// 3ds Max point cache (`.pc2`).
+    MaxPointCacheV2,
+    /// Maya cache (`.xml` with `.mc` or `.mcx`).
+    MayaCache,
+    Alembic,
+}
+
+impl CacheFileFormat {
+    pub fn from_i64(val: i64) -> Option<Self> {
+        match val {
+            0 => Some(CacheFileFormat::Unknown),
+            1 => Some(CacheFileFormat::MaxPointCacheV2),
+            2 => Some(CacheFileFormat::MayaCache),
+            3 => Some(CacheFileFormat::Alembic),
+            _ => None,
+        }
+    }
+}
+
+impl Default for CacheFileFormat {
+    fn default() -> Self {
+        CacheFileFormat::Unknown
+    }
+}
+
+#[derive(Debug)]
+pub struct CacheLoader<'a> {
+    definitions: &'a Definitions,
+    obj_props: &'a ObjectProperties<'a>,
+    properties: Option<GenericProperties>,
+}
+
+impl<'a> CacheLoader<'a> {
+    pub fn new(definitions: &'a Definitions, obj_props: &'a ObjectProperties<'a>) -> Self {
+        CacheLoader {
+            definitions: definitions,
+            obj_props: obj_props,
+            properties: None,
+        }
+    }
+}
+
+impl<'a> NodeLoaderCommon for CacheLoader<'a> {
+    type Target = Option<Cache>;
+
+    fn on_finish(mut self) -> Result<Self::Target> {
+        let defaults = self.definitions.templates.templates.get(&("Cache".to_owned(), "FbxCache".to_owned())).map(|t| &t.properties);
+        let file_format = self.properties.get_or_default(defaults, "CacheFileType").and_then(|p| p.value.get_i64()).and_then(CacheFileFormat::from_i64).unwrap_or_default();
+        let filename = self.properties.get_or_default(defaults, "CacheFileAbsolutePath").and_then(|p| p.value.get_string().cloned());
+        let relative_filename = self.properties.get_or_default(defaults, "CacheFile").and_then(|p| p.value.get_string().cloned());
+        if filename.is_none() && relative_filename.is_none() {
+            error!("Required property not found for `/Objects/Cache`");
+            return Ok(None);
+        }
+        Ok(Some(Cache {
+            id: self.obj_props.id,
+            name: self.obj_props.name.to_owned(),
+            file_format: file_format,
+            filename: filename.unwrap_or_default().into(),
+            relative_filename: relative_filename.unwrap_or_default().into(),
+        }))
+    }
+}
+
+impl<'a, R: Read> NodeLoader<R> for CacheLoader<'a> {
+    fn on_child_node(&mut self, reader: &mut EventReader<R>, node_info: RawNodeInfo) -> Result<()> {
+        let RawNodeInfo { name, properties } = node_info;
+        match name.as_ref() {
+            "Version" => {
+                match properties.iter().next().and_then(|p| p.get_i32()) {
+                    Some(100) => {},
+                    Some(v) => {
+                        warn!("Maybe unsupported version of `/Objects/Cache` node: ver={}", v);
+                    },
+                    None => {
+                        error!("Invalid proprety at `/Objects/Cache/Version`: type error");
+                    },
+                }
+                try!(ignore_current_node(reader));
+            },
+            "Properties70" => {
+                self.properties = Some(try!(GenericPropertiesLoader::new(70).load(reader)));
+            },
+            _ => {
+                warn!("Unknown node: `/Objects/Cache/{}`", name);
+                try!(ignore_current_node(reader));
+            },
+        }
+        Ok(())
+    }
+}
@@ -11,6 +11,7 @@ use objects::properties::ObjectProperties;
 #[derive(Debug, Clone)]
 pub struct BlendShapeChannel {
     pub id: i64,
+    pub name: String,
     pub deform_percent: f64,
     pub full_weights: Vec<f32>,
 }
@@ -41,6 +42,7 @@ impl<'a> NodeLoaderCommon for BlendShapeChannelLoader<'a> {
         ) {
             Ok(Some(BlendShapeChannel {
                 id: self.obj_props.id,
+                name: self.obj_props.name.to_owned(),
                 // Default value is 0.
                 // See [Help: FbxBlendShapeChannel Class
                 // Reference](http://help.autodesk.com/view/FBX/2016/ENU/?guid=__cpp_ref_class_fbx_blend_shape_channel_html#a81e8c6b125b60687b414e3aa8f2bfc7a)
@@ -4,8 +4,10 @@ use std::io::Read;
 use fbx_binary_reader::EventReader;
 use definitions::Definitions;
 use error::Result;
-use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, ignore_current_node};
+use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, as_matrix, ignore_current_node};
+use objects::geometry::mesh::ControlPointRemap;
 use objects::properties::ObjectProperties;
+use Real;
 
 
 #[derive(Debug, Clone)]
@@ -15,8 +17,69 @@ pub struct Cluster {
     pub user_data: String,
     pub indices: Vec<u32>,
     pub weights: Vec<f32>,
-    pub transform: [[f32; 4]; 4],
-    pub transform_link: [[f32; 4]; 4],
+    pub transform: [[Real; 4]; 4],
+    pub transform_link: [[Real; 4]; 4],
+    /// Global matrix of the associate model at the bind time (used with
+    /// `ClusterLinkMode::Additive`).
+    pub transform_associate_model: Option<[[Real; 4]; 4]>,
+    pub link_mode: ClusterLinkMode,
+}
+
+impl Cluster {
+    /// Updates control points of the target mesh in accordance with the map.
+    ///
+    /// Influences on removed control points are dropped, and if some control points are merged
+    /// into one, the first influence is kept.
+    pub fn remap_control_points(&mut self, remap: &ControlPointRemap) {
+        let mut seen = vec![false; remap.num_new()];
+        let mut indices = Vec::with_capacity(self.indices.len());
+        let mut weights = Vec::with_capacity(self.weights.len());
+        for (&old, &weight) in self.indices.iter().zip(self.weights.iter()) {
+            if let Some(new) = remap.new_index(old as usize) {
+                if !seen[new] {
+                    seen[new] = true;
+                    indices.push(new as u32);
+                    weights.push(weight);
+                }
+            }
+        }
+        self.indices = indices;
+        self.weights = weights;
+    }
+}
+
+/// Link mode of clusters, which specifies how weights of clusters are combined.
+///
+/// See [Help: FbxCluster Class
+/// Reference](http://help.autodesk.com/view/FBX/2016/ENU/?guid=__cpp_ref_class_fbx_cluster_html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClusterLinkMode {
+    /// Weights are normalized so that they sum to 1.
+    Normalize,
+    /// Deformations of clusters are applied one after another, each blended with the identity
+    /// by its weight.
+    /// Deformations of clusters with associate models are relative to the associate models.
+    Additive,
+    /// Weights are not normalized, and the remainder (`1 - sum`) is the weight of the
+    /// undeformed position.
+    TotalOne,
+}
+
+impl ClusterLinkMode {
+    pub fn from_str<T: AsRef<str>>(val: T) -> Option<Self> {
+        match val.as_ref() {
+            "Normalize" => Some(ClusterLinkMode::Normalize),
+            "Additive" => Some(ClusterLinkMode::Additive),
+            "TotalOne" | "Total1" => Some(ClusterLinkMode::TotalOne),
+            _ => None,
+        }
+    }
+}
+
+impl Default for ClusterLinkMode {
+    fn default() -> Self {
+        ClusterLinkMode::Normalize
+    }
 }
 
 #[derive(Debug)]
@@ -25,8 +88,10 @@ pub struct ClusterLoader<'a> {
     user_data: Option<(String, String)>,
     indices: Option<Vec<u32>>,
     weights: Option<Vec<f32>>,
-    transform: Option<[[f32; 4]; 4]>,
-    transform_link: Option<[[f32; 4]; 4]>,
+    transform: Option<[[Real; 4]; 4]>,
+    transform_link: Option<[[Real; 4]; 4]>,
+    transform_associate_model: Option<[[Real; 4]; 4]>,
+    link_mode: Option<ClusterLinkMode>,
 }
 
 impl<'a> ClusterLoader<'a> {
@@ -38,6 +103,8 @@ impl<'a> ClusterLoader<'a> {
             weights: None,
             transform: None,
             transform_link: None,
+            transform_associate_model: None,
+            link_mode: None,
         }
     }
 }
@@ -64,6 +131,8 @@ impl<'a> NodeLoaderCommon for ClusterLoader<'a> {
                 weights: self.weights.unwrap_or_default(),
                 transform: transform,
                 transform_link: transform_link,
+                transform_associate_model: self.transform_associate_model,
+                link_mode: self.link_mode.unwrap_or_default(),
             }))
         } else {
             error!("Required property not found for `/Objects/Deformer(Cluster)`");
@@ -102,24 +171,22 @@ impl<'a, R: Read> NodeLoader<R> for ClusterLoader<'a> {
                 self.weights = properties.iter().next().and_then(|p| p.into_vec_f32().ok());
             },
             "Transform" => {
-                self.transform = properties.iter().next().and_then(|p| p.as_vec_f32().into_iter().find(|v| v.len() >= 16).map(|v| {
-                    [
-                        [v[0], v[1], v[2], v[3]],
-                        [v[4], v[5], v[6], v[7]],
-                        [v[8], v[9], v[10], v[11]],
-                        [v[12], v[13], v[14], v[15]],
-                    ]
-                }));
+                self.transform = properties.iter().next().and_then(|p| as_matrix(&p));
             },
             "TransformLink" => {
-                self.transform_link = properties.iter().next().and_then(|p| p.as_vec_f32().into_iter().find(|v| v.len() >= 16).map(|v| {
-                    [
-                        [v[0], v[1], v[2], v[3]],
-                        [v[4], v[5], v[6], v[7]],
-                        [v[8], v[9], v[10], v[11]],
-                        [v[12], v[13], v[14], v[15]],
-                    ]
-                }));
+                self.transform_link = properties.iter().next().and_then(|p| as_matrix(&p));
+            },
+            "TransformAssociateModel" => {
+                self.transform_associate_model = properties.iter().next().and_then(|p| as_matrix(&p));
+            },
+            "Mode" => {
+                self.link_mode = properties.iter().next().and_then(|p| p.get_string()).and_then(|v| {
+                    let mode = ClusterLinkMode::from_str(v);
+                    if mode.is_none() {
+                        error!("Invalid value at `/Objects/Deformer(Cluster)/Mode`: {}", v);
+                    }
+                    mode
+                });
             },
             _ => {
                 warn!("Unknown node: `/Objects/Deformer(Cluster)/{}`", name);
@@ -2,8 +2,9 @@
 
 pub use self::blend_shape::BlendShape;
 pub use self::blend_shape_channel::BlendShapeChannel;
-pub use self::cluster::Cluster;
+pub use self::cluster::{Cluster, ClusterLinkMode};
 pub use self::skin::{Skin, SkinningType};
+pub use self::vertex_cache::VertexCacheDeformer;
 
 use std::io::Read;
 use fbx_binary_reader::EventReader;
@@ -15,11 +16,13 @@ use self::blend_shape::BlendShapeLoader;
 use self::blend_shape_channel::BlendShapeChannelLoader;
 use self::cluster::ClusterLoader;
 use self::skin::SkinLoader;
+use self::vertex_cache::VertexCacheDeformerLoader;
 
 mod blend_shape;
 mod blend_shape_channel;
 mod cluster;
 mod skin;
+mod vertex_cache;
 
 
 #[derive(Debug, Clone)]
@@ -28,6 +31,7 @@ pub enum Deformer {
     BlendShapeChannel(BlendShapeChannel),
     Cluster(Cluster),
     Skin(Skin),
+    VertexCacheDeformer(VertexCacheDeformer),
 }
 
 #[derive(Debug)]
@@ -36,6 +40,7 @@ pub enum DeformerLoader<'a> {
     BlendShapeChannel(BlendShapeChannelLoader<'a>),
     Cluster(ClusterLoader<'a>),
     Skin(SkinLoader<'a>),
+    VertexCacheDeformer(VertexCacheDeformerLoader<'a>),
 }
 
 impl<'a> DeformerLoader<'a> {
@@ -44,6 +49,7 @@ impl<'a> DeformerLoader<'a> {
             "Deformer" => match obj_props.subclass {
                 "BlendShape" => Some(DeformerLoader::BlendShape(BlendShapeLoader::new(definitions, obj_props))),
                 "Skin" => Some(DeformerLoader::Skin(SkinLoader::new(definitions, obj_props))),
+                "VertexCacheDeformer" => Some(DeformerLoader::VertexCacheDeformer(VertexCacheDeformerLoader::new(definitions, obj_props))),
                 v => {
                     warn!("Unknown subclass ({}) for `/Objects/Deformer(class={})", v, obj_props.class);
                     None
@@ -74,6 +80,7 @@ impl<'a> NodeLoaderCommon for DeformerLoader<'a> {
             DeformerLoader::BlendShapeChannel(loader) => try!(loader.on_finish()).map(Deformer::BlendShapeChannel),
             DeformerLoader::Cluster(loader) => try!(loader.on_finish()).map(Deformer::Cluster),
             DeformerLoader::Skin(loader) => try!(loader.on_finish()).map(Deformer::Skin),
+            DeformerLoader::VertexCacheDeformer(loader) => try!(loader.on_finish()).map(Deformer::VertexCacheDeformer),
         })
     }
 }
@@ -85,6 +92,7 @@ impl<'a, R: Read> NodeLoader<R> for DeformerLoader<'a> {
             DeformerLoader::BlendShapeChannel(ref mut loader) => loader.on_child_node(reader, node_info),
             DeformerLoader::Cluster(ref mut loader) => loader.on_child_node(reader, node_info),
             DeformerLoader::Skin(ref mut loader) => loader.on_child_node(reader, node_info),
+            DeformerLoader::VertexCacheDeformer(ref mut loader) => loader.on_child_node(reader, node_info),
         }
     }
 }
@@ -5,6 +5,7 @@ use fbx_binary_reader::EventReader;
 use definitions::Definitions;
 use error::Result;
 use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, ignore_current_node};
+use objects::geometry::mesh::ControlPointRemap;
 use objects::properties::ObjectProperties;
 
 
@@ -13,6 +14,47 @@ pub struct Skin {
     pub id: i64,
     pub link_deform_accuracy: f64,
     pub skinning_type: SkinningType,
+    /// Control points which have blend weights.
+    pub blend_weight_indices: Vec<u32>,
+    /// Blend weights of control points for `SkinningType::Blend` (`0` for linear and `1` for
+    /// dual quaternion skinning).
+    pub blend_weights: Vec<f32>,
+}
+
+impl Skin {
+    /// Returns blend weights of all control points.
+    ///
+    /// Control points without blend weights have `0`.
+    pub fn control_point_blend_weights(&self, num_control_points: usize) -> Vec<f32> {
+        let mut weights = vec![0.0; num_control_points];
+        for (&cp, &weight) in self.blend_weight_indices.iter().zip(self.blend_weights.iter()) {
+            if let Some(w) = weights.get_mut(cp as usize) {
+                *w = weight;
+            }
+        }
+        weights
+    }
+
+    /// Updates control points of the target mesh in accordance with the map.
+    ///
+    /// Blend weights of removed control points are dropped, and if some control points are
+    /// merged into one, the first blend weight is kept.
+    pub fn remap_control_points(&mut self, remap: &ControlPointRemap) {
+        let mut seen = vec![false; remap.num_new()];
+        let mut indices = Vec::with_capacity(self.blend_weight_indices.len());
+        let mut weights = Vec::with_capacity(self.blend_weights.len());
+        for (&old, &weight) in self.blend_weight_indices.iter().zip(self.blend_weights.iter()) {
+            if let Some(new) = remap.new_index(old as usize) {
+                if !seen[new] {
+                    seen[new] = true;
+                    indices.push(new as u32);
+                    weights.push(weight);
+                }
+            }
+        }
+        self.blend_weight_indices = indices;
+        self.blend_weights = weights;
+    }
 }
 
 #[derive(Debug)]
@@ -20,6 +62,8 @@ pub struct SkinLoader<'a> {
     obj_props: &'a ObjectProperties<'a>,
     link_deform_accuracy: Option<f64>,
     skinning_type: Option<SkinningType>,
+    blend_weight_indices: Option<Vec<u32>>,
+    blend_weights: Option<Vec<f32>>,
 }
 
 impl<'a> SkinLoader<'a> {
@@ -28,6 +72,8 @@ impl<'a> SkinLoader<'a> {
             obj_props: obj_props,
             link_deform_accuracy: None,
             skinning_type: None,
+            blend_weight_indices: None,
+            blend_weights: None,
         }
     }
 }
@@ -36,6 +82,10 @@ impl<'a> NodeLoaderCommon for SkinLoader<'a> {
     type Target = Option<Skin>;
 
     fn on_finish(self) -> Result<Self::Target> {
+        if self.blend_weight_indices.as_ref().map_or(0, |v| v.len()) != self.blend_weights.as_ref().map_or(0, |v| v.len()) {
+            error!("Inconsistent data at `/Objects/Deformer(Skin)` node: Number of elements in `Indexes` and `BlendWeights` not matched");
+            return Ok(None);
+        }
         if_all_some!{(
             link_deform_accuracy=self.link_deform_accuracy,
             skinning_type=self.skinning_type,
@@ -44,6 +94,8 @@ impl<'a> NodeLoaderCommon for SkinLoader<'a> {
                 id: self.obj_props.id,
                 link_deform_accuracy: link_deform_accuracy,
                 skinning_type: skinning_type,
+                blend_weight_indices: self.blend_weight_indices.unwrap_or_default(),
+                blend_weights: self.blend_weights.unwrap_or_default(),
             }))
         } else {
             error!("Required property not found for `/Objects/Deformer(Skin)`");
@@ -73,6 +125,12 @@ impl<'a, R: Read> NodeLoader<R> for SkinLoader<'a> {
             "SkinningType" => {
                 self.skinning_type = properties.iter().next().and_then(|p| p.get_string()).and_then(SkinningType::from_str);
             },
+            "Indexes" => {
+                self.blend_weight_indices = properties.iter().next().and_then(|p| p.extract_vec_i32().ok().map(|v| v.into_iter().map(|v| v as u32).collect()));
+            },
+            "BlendWeights" => {
+                self.blend_weights = properties.iter().next().and_then(|p| p.into_vec_f32().ok());
+            },
             _ => {
                 warn!("Unknown node: `/Objects/Deformer(Skin)/{}`", name);
             },
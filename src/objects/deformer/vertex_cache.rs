@@ -0,0 +1,86 @@
+//! Contains `/Objects/Deformer(VertexCacheDeformer)` node-related stuff.
+
+use std::io::Read;
+use fbx_binary_reader::EventReader;
+use definitions::Definitions;
+use error::Result;
+use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, ignore_current_node};
+use objects::properties::ObjectProperties;
+use property::{GenericProperties, GenericPropertiesLoader, OptionalProperties};
+
+
+/// Deformer which replaces control points of a mesh with positions from a point cache.
+///
+/// The cache is connected to the deformer (see `FbxScene::vertex_cache_deformer_cache()`).
+#[derive(Debug, Clone)]
+pub struct VertexCacheDeformer {
+    pub id: i64,
+    pub name: String,
+    pub active: bool,
+    /// Name of the channel in the cache file (for Maya caches with several channels).
+    pub channel: String,
+    pub cache_set: String,
+}
+
+#[derive(Debug)]
+pub struct VertexCacheDeformerLoader<'a> {
+    definitions: &'a Definitions,
+    obj_props: &'a ObjectProperties<'a>,
+    properties: Option<GenericProperties>,
+}
+
+impl<'a> VertexCacheDeformerLoader<'a> {
+    pub fn new(definitions: &'a Definitions, obj_props: &'a ObjectProperties<'a>) -> Self {
+        VertexCacheDeformerLoader {
+            definitions: definitions,
+            obj_props: obj_props,
+            properties: None,
+        }
+    }
+}
+
+impl<'a> NodeLoaderCommon for VertexCacheDeformerLoader<'a> {
+    type Target = Option<VertexCacheDeformer>;
+
+    fn on_finish(mut self) -> Result<Self::Target> {
+        let defaults = self.definitions.templates.templates.get(&("Deformer".to_owned(), "FbxVertexCacheDeformer".to_owned())).map(|t| &t.properties);
+        let active = self.properties.get_or_default(defaults, "Active").and_then(|p| p.value.get_i64()).map_or(true, |v| v != 0);
+        let channel = self.properties.get_or_default(defaults, "Channel").and_then(|p| p.value.get_string().cloned()).unwrap_or_default();
+        let cache_set = self.properties.get_or_default(defaults, "CacheSet").and_then(|p| p.value.get_string().cloned()).unwrap_or_default();
+        Ok(Some(VertexCacheDeformer {
+            id: self.obj_props.id,
+            name: self.obj_props.name.to_owned(),
+            active: active,
+            channel: channel,
+            cache_set: cache_set,
+        }))
+    }
+}
+
+impl<'a, R: Read> NodeLoader<R> for VertexCacheDeformerLoader<'a> {
+    fn on_child_node(&mut self, reader: &mut EventReader<R>, node_info: RawNodeInfo) -> Result<()> {
+        let RawNodeInfo { name, properties } = node_info;
+        match name.as_ref() {
+            "Version" => {
+                match properties.iter().next().and_then(|p| p.get_i32()) {
+                    Some(100) => {},
+                    Some(v) => {
+                        warn!("Maybe unsupported version of `/Objects/Deformer(VertexCacheDeformer)` node: ver={}", v);
+                    },
+                    None => {
+                        error!("Invalid proprety at `/Objects/Deformer(VertexCacheDeformer)/Version`: type error");
+                    },
+                }
+                try!(ignore_current_node(reader));
+            },
+            "Properties70" => {
+                self.properties = Some(try!(GenericPropertiesLoader::new(70).load(reader)));
+            },
+            _ => {
+                warn!("Unknown node: `/Objects/Deformer(VertexCacheDeformer)/{}`", name);
+                try!(ignore_current_node(reader));
+            },
+        }
+        Ok(())
+    }
+}
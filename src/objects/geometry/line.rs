@@ -0,0 +1,182 @@
+//! Contains `/Objects/Geometry(Line)` node-related stuff.
+
+use std::io::Read;
+use fbx_binary_reader::EventReader;
+use definitions::Definitions;
+use error::Result;
+use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, as_vec_real, ignore_current_node};
+use objects::properties::ObjectProperties;
+use Real;
+
+
+/// Line geometry, which consists of line strips.
+#[derive(Debug, Clone)]
+pub struct LineGeometry {
+    pub id: i64,
+    pub name: String,
+    pub points: Vec<[Real; 3]>,
+    /// Indices of points.
+    ///
+    /// The last point of each strip is stored as `!index` (negative value), like
+    /// `PolygonVertexIndex` of meshes. If no index is negative, all points form one strip.
+    pub points_index: Vec<i32>,
+}
+
+impl LineGeometry {
+    /// Returns the number of line strips.
+    pub fn num_strips(&self) -> usize {
+        self.strips().count()
+    }
+
+    /// Returns an iterator of line strips (point indices of each strip).
+    pub fn strips(&self) -> LineStrips {
+        LineStrips {
+            points_index: &self.points_index,
+        }
+    }
+
+    /// Returns an iterator of line segments (pairs of point indices).
+    ///
+    /// Indices are not checked against `points`.
+    pub fn segments(&self) -> LineSegments {
+        LineSegments {
+            points_index: &self.points_index,
+            current: 0,
+        }
+    }
+
+    /// Returns an iterator of line segments as pairs of positions.
+    ///
+    /// Segments with out-of-range indices are skipped.
+    pub fn segment_positions<'a>(&'a self) -> Box<Iterator<Item = ([Real; 3], [Real; 3])> + 'a> {
+        Box::new(self.segments().filter_map(move |(p1, p2)| {
+            match (self.points.get(p1 as usize), self.points.get(p2 as usize)) {
+                (Some(&p1), Some(&p2)) => Some((p1, p2)),
+                _ => None,
+            }
+        }))
+    }
+}
+
+/// Decodes a point index (of the end of a strip or not).
+fn decode_index(index: i32) -> u32 {
+    if index < 0 { !index as u32 } else { index as u32 }
+}
+
+/// Iterator of line strips, created by `LineGeometry::strips()`.
+#[derive(Debug, Clone)]
+pub struct LineStrips<'a> {
+    points_index: &'a [i32],
+}
+
+impl<'a> Iterator for LineStrips<'a> {
+    type Item = Vec<u32>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.points_index.is_empty() {
+            return None;
+        }
+        let len = self.points_index.iter().position(|&i| i < 0).map_or(self.points_index.len(), |end| end + 1);
+        let (strip, rest) = self.points_index.split_at(len);
+        self.points_index = rest;
+        Some(strip.iter().map(|&i| decode_index(i)).collect())
+    }
+}
+
+/// Iterator of line segments, created by `LineGeometry::segments()`.
+#[derive(Debug, Clone)]
+pub struct LineSegments<'a> {
+    points_index: &'a [i32],
+    current: usize,
+}
+
+impl<'a> Iterator for LineSegments<'a> {
+    type Item = (u32, u32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.current + 1 < self.points_index.len() {
+            let index = self.points_index[self.current];
+            self.current += 1;
+            if index >= 0 {
+                return Some((index as u32, decode_index(self.points_index[self.current])));
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug)]
+pub struct LineLoader<'a> {
+    obj_props: &'a ObjectProperties<'a>,
+    points: Option<Vec<[Real; 3]>>,
+    points_index: Option<Vec<i32>>,
+}
+
+impl<'a> LineLoader<'a> {
+    pub fn new(_definitions: &'a Definitions, obj_props: &'a ObjectProperties<'a>) -> Self {
+        LineLoader {
+            obj_props: obj_props,
+            points: None,
+            points_index: None,
+        }
+    }
+}
+
+impl<'a> NodeLoaderCommon for LineLoader<'a> {
+    type Target = Option<LineGeometry>;
+
+    fn on_finish(self) -> Result<Self::Target> {
+        if_all_some!{(
+            points=self.points,
+        ) {
+            // Without `PointsIndex`, all points form one strip.
+            let points_index = self.points_index.unwrap_or_else(|| (0..points.len() as i32).collect());
+            Ok(Some(LineGeometry {
+                id: self.obj_props.id,
+                name: self.obj_props.name.to_owned(),
+                points: points,
+                points_index: points_index,
+            }))
+        } else {
+            error!("Required property not found for `/Objects/Geometry(Line)`");
+            Ok(None)
+        }}
+    }
+}
+
+impl<'a, R: Read> NodeLoader<R> for LineLoader<'a> {
+    fn on_child_node(&mut self, reader: &mut EventReader<R>, node_info: RawNodeInfo) -> Result<()> {
+        let RawNodeInfo { name, properties } = node_info;
+        match name.as_ref() {
+            "Type" | "GeometryVersion" | "Properties70" => {},
+            "LineVersion" => {
+                match properties.iter().next().and_then(|p| p.get_i32()) {
+                    Some(100) => {},
+                    Some(v) => {
+                        warn!("Maybe unsupported version of `/Objects/Geometry(Line)` node: ver={}", v);
+                    },
+                    None => {
+                        error!("Invalid proprety at `/Objects/Geometry(Line)/LineVersion`: type error");
+                    },
+                }
+            },
+            "Points" => {
+                self.points = properties.iter().next()
+                    .and_then(|p| as_vec_real(&p)
+                        .into_iter().find(|vec| vec.len() > 0) // Prevent vec.chunks() from panicking.
+                        .map(|vec| {
+                            let len = vec.len() / 3;
+                            vec.chunks(3).take(len).map(|v| [v[0], v[1], v[2]]).collect()
+                        }));
+            },
+            "PointsIndex" => {
+                self.points_index = properties.iter().next().and_then(|p| p.extract_vec_i32().ok());
+            },
+            _ => {
+                warn!("Unknown node: `/Objects/Geometry(Line)/{}`", name);
+            },
+        }
+        try!(ignore_current_node(reader));
+        Ok(())
+    }
+}
@@ -0,0 +1,355 @@
+//! Contains cleanup passes of meshes.
+
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+use fnv::FnvHasher;
+use math::{sanitize_values, vec3_length, vec3_sub};
+use super::{Mesh, VertexIndex, MappingMode, ReferenceMode, LayerElement};
+use super::edges::update_polygons_keeping_edges;
+use Real;
+
+
+/// Options for `Mesh::cleanup()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CleanupOptions {
+    /// Replace non-finite values of control points and layer elements with zero.
+    pub sanitize_non_finite: bool,
+    /// Weld control points within the epsilon.
+    pub weld_epsilon: Option<Real>,
+    /// Remove degenerate and zero-area polygons.
+    pub remove_degenerate_polygons: bool,
+    /// Remove control points not used by any polygon.
+    pub remove_unused_control_points: bool,
+}
+
+impl Default for CleanupOptions {
+    fn default() -> Self {
+        CleanupOptions {
+            sanitize_non_finite: true,
+            weld_epsilon: None,
+            remove_degenerate_polygons: true,
+            remove_unused_control_points: true,
+        }
+    }
+}
+
+/// Map from source control points to new control points, returned by cleanup passes.
+///
+/// Objects which refer to control points of the mesh (such as `Cluster` and `Shape`) should be
+/// updated with this (see `FbxScene::apply_control_point_remap()`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlPointRemap {
+    /// New control point of each source control point.
+    old_to_new: Vec<Option<u32>>,
+    /// The first source control point of each new control point.
+    new_to_old: Vec<u32>,
+}
+
+impl ControlPointRemap {
+    /// Creates an identity map for the given number of control points.
+    pub fn identity(num_control_points: usize) -> Self {
+        ControlPointRemap {
+            old_to_new: (0..num_control_points as u32).map(Some).collect(),
+            new_to_old: (0..num_control_points as u32).collect(),
+        }
+    }
+
+    /// Creates a map from new control points of each source control point.
+    ///
+    /// New control points should be contiguous (i.e. each of `0..num_new` is used).
+    pub fn from_old_to_new(old_to_new: Vec<Option<u32>>) -> Self {
+        let num_new = old_to_new.iter().filter_map(|&v| v).max().map_or(0, |v| v as usize + 1);
+        let mut new_to_old = vec![::std::u32::MAX; num_new];
+        for (old, new) in old_to_new.iter().enumerate() {
+            if let Some(new) = *new {
+                if new_to_old[new as usize] == ::std::u32::MAX {
+                    new_to_old[new as usize] = old as u32;
+                }
+            }
+        }
+        ControlPointRemap {
+            old_to_new: old_to_new,
+            new_to_old: new_to_old,
+        }
+    }
+
+    /// Returns the number of source control points.
+    pub fn num_old(&self) -> usize {
+        self.old_to_new.len()
+    }
+
+    /// Returns the number of new control points.
+    pub fn num_new(&self) -> usize {
+        self.new_to_old.len()
+    }
+
+    /// Returns the new control point of the given source control point, or `None` if the
+    /// control point is removed.
+    pub fn new_index(&self, old: usize) -> Option<usize> {
+        self.old_to_new.get(old).and_then(|&v| v).map(|v| v as usize)
+    }
+
+    /// Returns the (first) source control point of the given new control point.
+    pub fn old_index(&self, new: usize) -> Option<usize> {
+        self.new_to_old.get(new).map(|&v| v as usize)
+    }
+
+    /// Returns whether the map changes nothing.
+    pub fn is_identity(&self) -> bool {
+        self.old_to_new.len() == self.new_to_old.len() && self.old_to_new.iter().enumerate().all(|(old, &new)| new == Some(old as u32))
+    }
+
+    /// Returns the map which applies `self` and then `next`.
+    pub fn then(&self, next: &ControlPointRemap) -> ControlPointRemap {
+        ControlPointRemap::from_old_to_new(self.old_to_new.iter().map(|&new| new.and_then(|new| next.new_index(new as usize)).map(|v| v as u32)).collect())
+    }
+
+    /// Returns the new index of the control point referred by polygons, or `None` if the
+    /// control point is removed.
+    ///
+    /// Control points out of range stay out of range.
+    fn remap_referred(&self, old: usize) -> Option<usize> {
+        if old >= self.num_old() {
+            Some(old - self.num_old() + self.num_new())
+        } else {
+            self.new_index(old)
+        }
+    }
+}
+
+impl Mesh {
+    /// Runs the cleanup passes enabled in the options, in the order of sanitization, welding,
+    /// degenerate polygon removal, and unused control point removal.
+    ///
+    /// Returns the map of control points which composes all passes.
+    pub fn cleanup(&mut self, options: &CleanupOptions) -> ControlPointRemap {
+        let mut remap = ControlPointRemap::identity(self.vertices.len());
+        if options.sanitize_non_finite {
+            self.sanitize_non_finite();
+        }
+        if let Some(epsilon) = options.weld_epsilon {
+            remap = remap.then(&self.weld_control_points(epsilon));
+        }
+        if options.remove_degenerate_polygons {
+            self.remove_degenerate_polygons();
+        }
+        if options.remove_unused_control_points {
+            remap = remap.then(&self.remove_unused_control_points());
+        }
+        remap
+    }
+
+    /// Replaces non-finite values of control points, normals, UVs, tangents, binormals and
+    /// creases with zero.
+    ///
+    /// Returns the number of replaced values.
+    pub fn sanitize_non_finite(&mut self) -> usize {
+        let mut count = sanitize_values(&mut self.vertices);
+        for le in &mut self.layer_element_normals {
+            count += le.data.as_mut().map_or(0, |data| sanitize_values(data));
+        }
+        for le in &mut self.layer_element_uvs {
+            count += le.data.as_mut().map_or(0, |data| sanitize_values(data));
+        }
+        for le in &mut self.layer_element_tangents {
+            count += le.data.as_mut().map_or(0, |data| sanitize_values(data));
+        }
+        for le in &mut self.layer_element_binormals {
+            count += le.data.as_mut().map_or(0, |data| sanitize_values(data));
+        }
+        for le in self.layer_element_edge_creases.iter_mut().chain(self.layer_element_vertex_creases.iter_mut()) {
+            for v in le.data.iter_mut().flat_map(|data| data.iter_mut()) {
+                if !v.is_finite() {
+                    *v = 0.0;
+                    count += 1;
+                }
+            }
+        }
+        if count > 0 {
+            warn!("Replaced {} non-finite values with zero (mesh id={})", count, self.id);
+        }
+        count
+    }
+
+    /// Welds control points within the epsilon (distance).
+    ///
+    /// Welded control points are replaced with the first one of them, and `ByControlPoint`
+    /// layer elements keep the values of the first one.
+    /// Non-finite control points are not welded.
+    pub fn weld_control_points(&mut self, epsilon: Real) -> ControlPointRemap {
+        let epsilon = epsilon.max(0.0);
+        // Cell size of the grid. Control points within the epsilon are in the same or adjacent
+        // cells.
+        let cell_size = if epsilon > 0.0 { epsilon } else { 1.0 };
+        let cell_of = |p: [Real; 3]| -> [i64; 3] {
+            [(p[0] / cell_size).floor() as i64, (p[1] / cell_size).floor() as i64, (p[2] / cell_size).floor() as i64]
+        };
+        let mut grid: HashMap<[i64; 3], Vec<u32>, BuildHasherDefault<FnvHasher>> = Default::default();
+        let mut old_to_new = Vec::with_capacity(self.vertices.len());
+        let mut num_new = 0u32;
+        for (old, &p) in self.vertices.iter().enumerate() {
+            if !p.iter().all(|v| v.is_finite()) {
+                old_to_new.push(Some(num_new));
+                num_new += 1;
+                continue;
+            }
+            let cell = cell_of(p);
+            let mut found = None;
+            'search: for dx in -1..2 {
+                for dy in -1..2 {
+                    for dz in -1..2 {
+                        if let Some(candidates) = grid.get(&[cell[0] + dx, cell[1] + dy, cell[2] + dz]) {
+                            if let Some(&rep) = candidates.iter().find(|&&rep| vec3_length(vec3_sub(self.vertices[rep as usize], p)) <= epsilon) {
+                                found = Some(rep);
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+            match found {
+                Some(rep) => {
+                    let new = old_to_new[rep as usize];
+                    old_to_new.push(new);
+                },
+                None => {
+                    grid.entry(cell).or_insert_with(Vec::new).push(old as u32);
+                    old_to_new.push(Some(num_new));
+                    num_new += 1;
+                },
+            }
+        }
+        let remap = ControlPointRemap::from_old_to_new(old_to_new);
+        if !remap.is_identity() {
+            self.apply_control_point_remap(&remap);
+        }
+        remap
+    }
+
+    /// Removes degenerate polygons (see `Mesh::is_degenerate_polygon()`).
+    ///
+    /// Returns the number of removed polygons.
+    pub fn remove_degenerate_polygons(&mut self) -> usize {
+        let kept = (0..self.polygon_structure.num_polygons()).filter(|&poly| !self.is_degenerate_polygon(poly)).collect::<Vec<_>>();
+        let num_removed = self.retain_polygons(&kept);
+        if num_removed > 0 {
+            warn!("Removed {} degenerate polygons (mesh id={})", num_removed, self.id);
+        }
+        num_removed
+    }
+
+    /// Keeps only the given polygons, and updates edges and layer elements.
+    ///
+    /// Polygon vertices not in any polygon are also removed.
+    /// Returns the number of removed polygons.
+    fn retain_polygons(&mut self, kept: &[usize]) -> usize {
+        let num_polygons = self.polygon_structure.num_polygons();
+        let mut pvi_map = Vec::new();
+        let mut poly_map = Vec::new();
+        for &poly in kept {
+            pvi_map.extend(self.polygon_structure.polygon_range(poly).unwrap().map(|pvi| pvi as u32));
+            poly_map.push(poly as u32);
+        }
+        if pvi_map.len() == self.num_polygon_vertices() {
+            // Nothing to remove.
+            return 0;
+        }
+        let new_index = match self.polygon_vertex_index {
+            VertexIndex::NotTriangulated(ref vec) => {
+                VertexIndex::NotTriangulated(kept.iter().flat_map(|&poly| {
+                    let range = self.polygon_structure.polygon_range(poly).unwrap();
+                    let last = range.end - 1;
+                    range.map(move |pvi| match vec[pvi] {
+                        i if i < 0 => if pvi == last { i } else { !i },
+                        i => if pvi == last { !i } else { i },
+                    })
+                }).collect())
+            },
+            VertexIndex::Triangulated(ref vec) => {
+                VertexIndex::Triangulated(pvi_map.iter().map(|&pvi| vec[pvi as usize]).collect())
+            },
+        };
+        update_polygons_keeping_edges(self, Some, |mesh| mesh.polygon_vertex_index = new_index);
+        self.remap_polygon_layer_elements(&pvi_map, &poly_map, 0);
+        num_polygons - kept.len()
+    }
+
+    /// Removes control points which are not used by any polygon.
+    pub fn remove_unused_control_points(&mut self) -> ControlPointRemap {
+        let mut used = vec![false; self.vertices.len()];
+        for pvi in 0..self.num_polygon_vertices() {
+            if let Some(v) = used.get_mut(self.control_point_of_polygon_vertex(pvi)) {
+                *v = true;
+            }
+        }
+        let mut num_new = 0;
+        let remap = ControlPointRemap::from_old_to_new(used.iter().map(|&used| if used {
+            num_new += 1;
+            Some(num_new - 1)
+        } else {
+            None
+        }).collect());
+        if !remap.is_identity() {
+            self.apply_control_point_remap(&remap);
+        }
+        remap
+    }
+
+    /// Replaces control points of the mesh in accordance with the map.
+    ///
+    /// Polygons referring to removed control points are removed.
+    fn apply_control_point_remap(&mut self, remap: &ControlPointRemap) {
+        if (0..self.num_polygon_vertices()).any(|pvi| remap.remap_referred(self.control_point_of_polygon_vertex(pvi)).is_none()) {
+            let kept = (0..self.polygon_structure.num_polygons()).filter(|&poly| {
+                self.polygon_structure.polygon_range(poly).unwrap().all(|pvi| remap.remap_referred(self.control_point_of_polygon_vertex(pvi)).is_some())
+            }).collect::<Vec<_>>();
+            let num_removed = self.retain_polygons(&kept);
+            warn!("Removed {} polygons referring to removed control points (mesh id={})", num_removed, self.id);
+        }
+        let remap_referred = |old: usize| match remap.remap_referred(old) {
+            Some(new) => new,
+            None => unreachable!("Polygons referring to removed control points should have been removed"),
+        };
+        self.vertices = (0..remap.num_new()).filter_map(|new| remap.old_index(new).and_then(|old| self.vertices.get(old).cloned())).collect();
+        let new_index = match self.polygon_vertex_index {
+            VertexIndex::NotTriangulated(ref vec) => VertexIndex::NotTriangulated(vec.iter().map(|&i| {
+                if i < 0 {
+                    !(remap_referred(!i as usize) as i32)
+                } else {
+                    remap_referred(i as usize) as i32
+                }
+            }).collect()),
+            VertexIndex::Triangulated(ref vec) => VertexIndex::Triangulated(vec.iter().map(|&i| remap_referred(i as usize) as u32).collect()),
+        };
+        update_polygons_keeping_edges(self, |cp| remap.new_index(cp), |mesh| mesh.polygon_vertex_index = new_index);
+        remap_control_point_layer_elements(&mut self.layer_element_materials, remap);
+        remap_control_point_layer_elements(&mut self.layer_element_normals, remap);
+        remap_control_point_layer_elements(&mut self.layer_element_uvs, remap);
+        remap_control_point_layer_elements(&mut self.layer_element_tangents, remap);
+        remap_control_point_layer_elements(&mut self.layer_element_binormals, remap);
+        remap_control_point_layer_elements(&mut self.layer_element_smoothing, remap);
+        remap_control_point_layer_elements(&mut self.layer_element_polygon_groups, remap);
+        remap_control_point_layer_elements(&mut self.layer_element_visibility, remap);
+        remap_control_point_layer_elements(&mut self.layer_element_edge_creases, remap);
+        remap_control_point_layer_elements(&mut self.layer_element_vertex_creases, remap);
+    }
+}
+
+fn remap_control_point_layer_elements<'a, T, I>(layer_elements: I, remap: &ControlPointRemap)
+    where T: 'a + Copy,
+          I: 'a + IntoIterator<Item = &'a mut LayerElement<T>>,
+{
+    for le in layer_elements.into_iter() {
+        if le.mapping_mode != MappingMode::ByControlPoint {
+            continue;
+        }
+        match le.reference_mode {
+            ReferenceMode::Direct => if let Some(ref mut data) = le.data {
+                *data = (0..remap.num_new()).filter_map(|new| remap.old_index(new).and_then(|old| data.get(old).cloned())).collect();
+            },
+            ReferenceMode::IndexToDirect(ref mut indices) => {
+                *indices = (0..remap.num_new()).filter_map(|new| remap.old_index(new).and_then(|old| indices.get(old).cloned())).collect();
+            },
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! Contains edge-related stuff of meshes.
+
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+use fnv::FnvHasher;
+use super::Mesh;
+
+
+type EdgeMap = HashMap<(usize, usize), u32, BuildHasherDefault<FnvHasher>>;
+
+impl Mesh {
+    /// Returns the next polygon vertex of the given polygon vertex in the same polygon.
+    ///
+    /// Returns the given polygon vertex itself if it is not in any polygon.
+    pub fn next_polygon_vertex(&self, pvi: usize) -> usize {
+        match self.polygon_structure.polygon_of_polygon_vertex(pvi).and_then(|poly| self.polygon_structure.polygon_range(poly)) {
+            // The end of the polygon. Go back to the beginning of the polygon.
+            Some(range) => if pvi + 1 == range.end { range.start } else { pvi + 1 },
+            None => pvi,
+        }
+    }
+
+    /// Returns control points at both ends of the given edge.
+    ///
+    /// Returns `None` if the edge or its polygon vertex is out of range.
+    pub fn edge_control_points(&self, edge: usize) -> Option<(usize, usize)> {
+        let pvi = match self.edges.get(edge) {
+            Some(&pvi) if (pvi as usize) < self.num_polygon_vertices() => pvi as usize,
+            _ => return None,
+        };
+        let next_pvi = self.next_polygon_vertex(pvi);
+        Some((self.control_point_of_polygon_vertex(pvi), self.control_point_of_polygon_vertex(next_pvi)))
+    }
+
+    /// Returns the edge from the given polygon vertex to the next polygon vertex in the same
+    /// polygon.
+    ///
+    /// Returns `None` if the edge is not in `self.edges`.
+    pub fn edge_of_polygon_vertex(&self, pvi: usize) -> Option<usize> {
+        self.polygon_vertex_edges.get(pvi).and_then(|&e| e).map(|e| e as usize)
+    }
+
+    /// Returns the edge of each polygon vertex (the edge from the polygon vertex to the next one).
+    ///
+    /// This is derived from `self.edges`, and should be recomputed by
+    /// `Mesh::rebuild_polygon_vertex_edges()` after `edges` or `polygon_vertex_index` is
+    /// modified manually.
+    pub fn polygon_vertex_edges(&self) -> &[Option<u32>] {
+        &self.polygon_vertex_edges
+    }
+
+    /// Recomputes the edge of each polygon vertex from `self.edges`.
+    ///
+    /// This should be called after `edges` or `polygon_vertex_index` is modified manually.
+    pub fn rebuild_polygon_vertex_edges(&mut self) {
+        let edge_map = edge_map(self);
+        self.polygon_vertex_edges = (0..self.num_polygon_vertices()).map(|pvi| {
+            let key = edge_key(self.control_point_of_polygon_vertex(pvi), self.control_point_of_polygon_vertex(self.next_polygon_vertex(pvi)));
+            edge_map.get(&key).cloned()
+        }).collect();
+    }
+}
+
+/// Returns the map from control point pairs (edge keys) to edges.
+fn edge_map(mesh: &Mesh) -> EdgeMap {
+    let mut edge_map: EdgeMap = Default::default();
+    for edge in 0..mesh.edges.len() {
+        if let Some((cp1, cp2)) = mesh.edge_control_points(edge) {
+            edge_map.entry(edge_key(cp1, cp2)).or_insert(edge as u32);
+        }
+    }
+    edge_map
+}
+
+/// Updates `mesh.edges` and `mesh.polygon_vertex_edges` for the triangulated polygon vertices.
+///
+/// This should be called before `mesh.polygon_vertex_index` is replaced with the triangulated
+/// one, and returns the number of the new (diagonal) edges appended to `mesh.edges`.
+///
+/// Existing edges keep their indices. An existing edge which doesn't appear in the triangulated
+/// polygons (for example, an edge of a dropped degenerate polygon) gets `u32::MAX` as its polygon
+/// vertex.
+pub fn triangulate_edges(mesh: &mut Mesh, tri_vertex_index: &[u32]) -> usize {
+    let mut edge_map = edge_map(mesh);
+    let num_src_edges = mesh.edges.len();
+    let mut edges = vec![::std::u32::MAX; num_src_edges];
+    let mut polygon_vertex_edges = Vec::with_capacity(tri_vertex_index.len());
+    for (pvi, &cp) in tri_vertex_index.iter().enumerate() {
+        let next_pvi = if pvi % 3 == 2 { pvi - 2 } else { pvi + 1 };
+        let key = edge_key(cp as usize, tri_vertex_index[next_pvi] as usize);
+        let edge = *edge_map.entry(key).or_insert_with(|| {
+            // A new diagonal edge.
+            edges.push(pvi as u32);
+            (edges.len() - 1) as u32
+        });
+        if edges[edge as usize] == ::std::u32::MAX {
+            edges[edge as usize] = pvi as u32;
+        }
+        polygon_vertex_edges.push(Some(edge));
+    }
+    mesh.edges = edges;
+    mesh.polygon_vertex_edges = polygon_vertex_edges;
+    mesh.edges.len() - num_src_edges
+}
+
+/// Updates polygons of the mesh by `update`, keeping indices of existing edges.
+///
+/// `remap_control_point` maps the control points before the update to the ones after the update.
+/// Edges are reassigned to the new polygon vertices with the same control points.
+/// An edge which doesn't appear in the new polygons gets `u32::MAX` as its polygon vertex, and
+/// only the first one of edges which become the same is kept.
+pub fn update_polygons_keeping_edges<F, G>(mesh: &mut Mesh, remap_control_point: F, update: G)
+    where F: Fn(usize) -> Option<usize>,
+          G: FnOnce(&mut Mesh),
+{
+    let mut edge_map: EdgeMap = Default::default();
+    for edge in 0..mesh.edges.len() {
+        if let Some((cp1, cp2)) = mesh.edge_control_points(edge) {
+            if let (Some(cp1), Some(cp2)) = (remap_control_point(cp1), remap_control_point(cp2)) {
+                edge_map.entry(edge_key(cp1, cp2)).or_insert(edge as u32);
+            }
+        }
+    }
+    update(mesh);
+    mesh.rebuild_polygon_structure();
+    let mut edges = vec![::std::u32::MAX; mesh.edges.len()];
+    for pvi in 0..mesh.num_polygon_vertices() {
+        let key = edge_key(mesh.control_point_of_polygon_vertex(pvi), mesh.control_point_of_polygon_vertex(mesh.next_polygon_vertex(pvi)));
+        if let Some(&edge) = edge_map.get(&key) {
+            if edges[edge as usize] == ::std::u32::MAX {
+                edges[edge as usize] = pvi as u32;
+            }
+        }
+    }
+    mesh.edges = edges;
+    mesh.rebuild_polygon_vertex_edges();
+}
+
+/// Returns the edge key, the pair of control points with the smaller one first.
+pub fn edge_key(cp1: usize, cp2: usize) -> (usize, usize) {
+    if cp1 < cp2 {
+        (cp1, cp2)
+    } else {
+        (cp2, cp1)
+    }
+}
@@ -0,0 +1,211 @@
+//! Contains flattening of meshes into indexed triangle lists.
+
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+use fnv::FnvHasher;
+use error::{MeshErrorKind, MeshResult};
+use utils;
+use super::Mesh;
+use Real;
+
+
+/// Mesh flattened into an indexed triangle list, created by `Mesh::flatten()`.
+///
+/// Each vertex has its own set of attributes, and polygon vertices with the same control point
+/// and the same attributes share a vertex.
+#[derive(Debug, Clone)]
+pub struct FlattenedMesh {
+    /// Positions of vertices.
+    pub positions: Vec<[Real; 3]>,
+    /// Normals of vertices (from the first normal layer element with data).
+    pub normals: Option<Vec<[f32; 3]>>,
+    /// UVs of vertices for each UV set with data (in the order of `Mesh::layer_element_uvs`).
+    pub uvs: Vec<(String, Vec<[f32; 2]>)>,
+    /// Tangents of vertices (from the first tangent layer element with data).
+    pub tangents: Option<Vec<[f32; 4]>>,
+    /// Binormals of vertices (from the first binormal layer element with data).
+    pub binormals: Option<Vec<[f32; 4]>>,
+    /// Source control point of each vertex.
+    pub control_points: Vec<u32>,
+    /// Vertex indices of triangles.
+    pub indices: Vec<u32>,
+    /// Material index of each triangle (from the first material layer element).
+    pub materials: Option<Vec<u32>>,
+}
+
+/// ACMR (average cache miss ratio) before and after optimization, returned by
+/// `FlattenedMesh::optimize_vertex_cache()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VertexCacheReport {
+    pub acmr_before: f32,
+    pub acmr_after: f32,
+}
+
+impl Mesh {
+    /// Flattens the triangulated mesh into an indexed triangle list.
+    ///
+    /// Returns an error if the mesh is not triangulated, a polygon vertex refers to a nonexistent
+    /// control point, or layer elements are inconsistent with the mesh.
+    pub fn flatten(&self) -> MeshResult<FlattenedMesh> {
+        let tri_indices = try!(self.try_triangulated_index_list());
+        let normals = self.layer_element_normals.iter().find(|le| le.has_data());
+        let uvs = self.layer_element_uvs.iter().filter(|le| le.has_data()).collect::<Vec<_>>();
+        let tangents = self.layer_element_tangents.iter().find(|le| le.has_data());
+        let binormals = self.layer_element_binormals.iter().find(|le| le.has_data());
+
+        let mut flattened = FlattenedMesh {
+            positions: Vec::new(),
+            normals: normals.map(|_| Vec::new()),
+            uvs: uvs.iter().map(|le| (le.name.clone(), Vec::new())).collect(),
+            tangents: tangents.map(|_| Vec::new()),
+            binormals: binormals.map(|_| Vec::new()),
+            control_points: Vec::new(),
+            indices: Vec::with_capacity(tri_indices.len()),
+            materials: None,
+        };
+        // Vertices are looked up by the hash of the control point and bits of all attributes, and
+        // vertices with the same hash are chained and compared exactly.
+        let mut buckets: HashMap<u64, u32, BuildHasherDefault<FnvHasher>> = Default::default();
+        // Next vertex with the same hash of each vertex.
+        let mut next_in_bucket = Vec::new();
+        let mut uv_values = Vec::with_capacity(uvs.len());
+        for (pvi, &cp) in tri_indices.iter().enumerate() {
+            let position = match self.vertices.get(cp as usize) {
+                Some(&v) => v,
+                None => return Err(self.error(MeshErrorKind::ControlPointOutOfRange(cp as usize))),
+            };
+            let normal = match normals {
+                Some(le) => Some(try!(le.try_element_of_polygon_vertex(self, pvi))),
+                None => None,
+            };
+            uv_values.clear();
+            for le in &uvs {
+                uv_values.push(try!(le.try_element_of_polygon_vertex(self, pvi)));
+            }
+            let tangent = match tangents {
+                Some(le) => Some(try!(le.try_element_of_polygon_vertex(self, pvi))),
+                None => None,
+            };
+            let binormal = match binormals {
+                Some(le) => Some(try!(le.try_element_of_polygon_vertex(self, pvi))),
+                None => None,
+            };
+            let hash = {
+                let mut hasher = FnvHasher::default();
+                cp.hash(&mut hasher);
+                let values = normal.iter().flat_map(|v| v.iter())
+                    .chain(uv_values.iter().flat_map(|v| v.iter()))
+                    .chain(tangent.iter().flat_map(|v| v.iter()))
+                    .chain(binormal.iter().flat_map(|v| v.iter()));
+                for v in values {
+                    v.to_bits().hash(&mut hasher);
+                }
+                hasher.finish()
+            };
+
+            let mut candidate = buckets.get(&hash).cloned();
+            while let Some(v) = candidate {
+                if flattened.is_same_vertex(v as usize, cp, normal, &uv_values, tangent, binormal) {
+                    break;
+                }
+                candidate = next_in_bucket[v as usize];
+            }
+            let index = if let Some(index) = candidate {
+                index
+            } else {
+                let index = flattened.positions.len() as u32;
+                next_in_bucket.push(buckets.insert(hash, index));
+                flattened.positions.push(position);
+                flattened.control_points.push(cp);
+                if let (Some(normals), Some(v)) = (flattened.normals.as_mut(), normal) {
+                    normals.push(v);
+                }
+                for (&mut (_, ref mut uvs), &v) in flattened.uvs.iter_mut().zip(&uv_values) {
+                    uvs.push(v);
+                }
+                if let (Some(tangents), Some(v)) = (flattened.tangents.as_mut(), tangent) {
+                    tangents.push(v);
+                }
+                if let (Some(binormals), Some(v)) = (flattened.binormals.as_mut(), binormal) {
+                    binormals.push(v);
+                }
+                index
+            };
+            flattened.indices.push(index);
+        }
+        if let Some(le) = self.layer_element_materials.first() {
+            let mut materials = Vec::with_capacity(tri_indices.len() / 3);
+            for pvi in (0..tri_indices.len() / 3).map(|tri| tri * 3) {
+                materials.push(try!(le.try_element_index_of_polygon_vertex(self, pvi)) as u32);
+            }
+            flattened.materials = Some(materials);
+        }
+        Ok(flattened)
+    }
+}
+
+impl FlattenedMesh {
+    /// Returns the number of vertices.
+    pub fn num_vertices(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Returns whether the vertex has the given control point and the same bits of attributes.
+    fn is_same_vertex(&self, vertex: usize, cp: u32, normal: Option<[f32; 3]>, uvs: &[[f32; 2]], tangent: Option<[f32; 4]>, binormal: Option<[f32; 4]>) -> bool {
+        fn same_bits(a: &[f32], b: &[f32]) -> bool {
+            a.iter().zip(b).all(|(a, b)| a.to_bits() == b.to_bits())
+        }
+        fn same_attribute<T: AsRef<[f32]>>(values: Option<&Vec<T>>, vertex: usize, value: Option<T>) -> bool {
+            match (values, value) {
+                (Some(values), Some(value)) => same_bits(values[vertex].as_ref(), value.as_ref()),
+                _ => true,
+            }
+        }
+        self.control_points[vertex] == cp
+            && same_attribute(self.normals.as_ref(), vertex, normal)
+            && self.uvs.iter().zip(uvs).all(|(&(_, ref values), uv)| same_bits(&values[vertex], uv))
+            && same_attribute(self.tangents.as_ref(), vertex, tangent)
+            && same_attribute(self.binormals.as_ref(), vertex, binormal)
+    }
+
+    /// Reorders triangles for the post-transform vertex cache, and then reorders vertices for
+    /// vertex fetch.
+    ///
+    /// Per-triangle materials and all per-vertex attributes are kept in sync.
+    /// See `utils::optimize_vertex_cache()` and `utils::optimize_vertex_fetch()`.
+    pub fn optimize_vertex_cache(&mut self, cache_size: usize) -> VertexCacheReport {
+        let acmr_before = utils::acmr(&self.indices, cache_size);
+        let num_vertices = self.num_vertices();
+        let tri_order = utils::optimize_vertex_cache(&mut self.indices, num_vertices, cache_size);
+        if let Some(ref mut materials) = self.materials {
+            *materials = utils::reorder(materials, &tri_order);
+        }
+        self.optimize_vertex_fetch();
+        VertexCacheReport {
+            acmr_before: acmr_before,
+            acmr_after: utils::acmr(&self.indices, cache_size),
+        }
+    }
+
+    /// Reorders vertices in the order of the first use by triangles.
+    ///
+    /// All per-vertex attributes are kept in sync.
+    pub fn optimize_vertex_fetch(&mut self) {
+        let num_vertices = self.num_vertices();
+        let new_to_old = utils::optimize_vertex_fetch(&mut self.indices, num_vertices);
+        self.positions = utils::reorder(&self.positions, &new_to_old);
+        self.control_points = utils::reorder(&self.control_points, &new_to_old);
+        if let Some(ref mut normals) = self.normals {
+            *normals = utils::reorder(normals, &new_to_old);
+        }
+        for &mut (_, ref mut uvs) in &mut self.uvs {
+            *uvs = utils::reorder(uvs, &new_to_old);
+        }
+        if let Some(ref mut tangents) = self.tangents {
+            *tangents = utils::reorder(tangents, &new_to_old);
+        }
+        if let Some(ref mut binormals) = self.binormals {
+            *binormals = utils::reorder(binormals, &new_to_old);
+        }
+    }
+}
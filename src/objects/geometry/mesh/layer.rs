@@ -10,6 +10,13 @@ pub struct Layer {
     pub material: Vec<i32>,
     pub normal: Vec<i32>,
     pub uv: Vec<i32>,
+    pub tangent: Vec<i32>,
+    pub binormal: Vec<i32>,
+    pub smoothing: Vec<i32>,
+    pub polygon_group: Vec<i32>,
+    pub visibility: Vec<i32>,
+    pub edge_crease: Vec<i32>,
+    pub vertex_crease: Vec<i32>,
     //pub color: Vec<i32>, // LayerElementColor is unsupported. see `MeshLoader::on_node_child()`.
 }
 
@@ -19,6 +26,13 @@ pub struct LayerLoader {
     material: Vec<i32>,
     normal: Vec<i32>,
     uv: Vec<i32>,
+    tangent: Vec<i32>,
+    binormal: Vec<i32>,
+    smoothing: Vec<i32>,
+    polygon_group: Vec<i32>,
+    visibility: Vec<i32>,
+    edge_crease: Vec<i32>,
+    vertex_crease: Vec<i32>,
 }
 
 impl LayerLoader {
@@ -28,6 +42,13 @@ impl LayerLoader {
             material: Default::default(),
             normal: Default::default(),
             uv: Default::default(),
+            tangent: Default::default(),
+            binormal: Default::default(),
+            smoothing: Default::default(),
+            polygon_group: Default::default(),
+            visibility: Default::default(),
+            edge_crease: Default::default(),
+            vertex_crease: Default::default(),
         }
     }
 
@@ -50,6 +71,13 @@ impl NodeLoaderCommon for LayerLoader {
             normal: self.normal,
             uv: self.uv,
             material: self.material,
+            tangent: self.tangent,
+            binormal: self.binormal,
+            smoothing: self.smoothing,
+            polygon_group: self.polygon_group,
+            visibility: self.visibility,
+            edge_crease: self.edge_crease,
+            vertex_crease: self.vertex_crease,
         }))
     }
 }
@@ -75,6 +103,13 @@ impl<R: Read> NodeLoader<R> for LayerLoader {
                     "LayerElementMaterial" => self.material.push(typed_index),
                     "LayerElementNormal" => self.normal.push(typed_index),
                     "LayerElementUV" => self.uv.push(typed_index),
+                    "LayerElementTangent" => self.tangent.push(typed_index),
+                    "LayerElementBinormal" => self.binormal.push(typed_index),
+                    "LayerElementSmoothing" => self.smoothing.push(typed_index),
+                    "LayerElementPolygonGroup" => self.polygon_group.push(typed_index),
+                    "LayerElementVisibility" => self.visibility.push(typed_index),
+                    "LayerElementEdgeCrease" => self.edge_crease.push(typed_index),
+                    "LayerElementVertexCrease" => self.vertex_crease.push(typed_index),
                     val => {
                         error!("Unsupported layer element type: `{}`", val);
                     },
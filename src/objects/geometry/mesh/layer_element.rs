@@ -2,7 +2,7 @@
 
 use std::io::Read;
 use fbx_binary_reader::{EventReader, DelayedProperties};
-use error::Result;
+use error::{MeshError, MeshErrorKind, MeshResult, Result};
 use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, ignore_current_node};
 use super::{Mesh, VertexIndex};
 
@@ -106,7 +106,22 @@ impl<T: Copy> LayerElement<T> {
     }
 
     /// Get an element index corresponding to the given polygon vertex index (index of vertex index).
+    ///
+    /// # Panics
+    /// The function panics if the mapping mode is unsupported or the index is out of range.
+    /// See `LayerElement::try_element_index_of_polygon_vertex()` for non-panicking version.
     pub fn element_index_of_polygon_vertex(&self, mesh: &Mesh, pvi: usize) -> usize {
+        self.try_element_index_of_polygon_vertex(mesh, pvi).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Get an element index corresponding to the given polygon vertex index (index of vertex index).
+    ///
+    /// Unlike `LayerElement::element_index_of_polygon_vertex()`, this returns an error for
+    /// unsupported mapping mode or out-of-range index.
+    pub fn try_element_index_of_polygon_vertex(&self, mesh: &Mesh, pvi: usize) -> MeshResult<usize> {
+        if pvi >= mesh.num_polygon_vertices() {
+            return Err(self.error(mesh, MeshErrorKind::PolygonVertexOutOfRange(pvi)));
+        }
         let unmapped_index = match self.mapping_mode {
             MappingMode::ByControlPoint => match mesh.polygon_vertex_index {
                 VertexIndex::NotTriangulated(ref vec) => match vec[pvi] {
@@ -116,21 +131,28 @@ impl<T: Copy> LayerElement<T> {
                 VertexIndex::Triangulated(ref vec) => vec[pvi] as usize,
             },
             MappingMode::ByPolygonVertex => pvi,
-            MappingMode::ByPolygon => match mesh.polygon_vertex_index {
-                VertexIndex::NotTriangulated(ref vec) => {
-                    warn!("Mesh should be triangulated before `LayerElement::element_index_of_polygon_vertex()` called, or it does O(N) operation");
-                    vec.iter().take(pvi).filter(|&&v| v < 0).count()
-                },
-                VertexIndex::Triangulated(_) => pvi / 3,
+            MappingMode::ByPolygon => if let Some(poly) = mesh.polygon_structure.polygon_of_polygon_vertex(pvi) {
+                poly
+            } else {
+                return Err(self.error(mesh, MeshErrorKind::PolygonNotFound(pvi)));
+            },
+            MappingMode::ByEdge => if let Some(edge) = mesh.edge_of_polygon_vertex(pvi) {
+                edge
+            } else {
+                return Err(self.error(mesh, MeshErrorKind::EdgeNotFound(pvi)));
             },
             MappingMode::AllSame => 0,
             mode => {
-                panic!("Unsupported mapping mode: `{:?}`", mode);
+                return Err(self.error(mesh, MeshErrorKind::UnsupportedMappingMode(mode)));
             },
         } as usize;
         match self.reference_mode {
-            ReferenceMode::Direct => unmapped_index,
-            ReferenceMode::IndexToDirect(ref vec) => vec[unmapped_index] as usize,
+            ReferenceMode::Direct => Ok(unmapped_index),
+            ReferenceMode::IndexToDirect(ref vec) => if let Some(&index) = vec.get(unmapped_index) {
+                Ok(index as usize)
+            } else {
+                Err(self.error(mesh, MeshErrorKind::IndexOutOfRange(unmapped_index)))
+            },
         }
     }
 
@@ -138,8 +160,46 @@ impl<T: Copy> LayerElement<T> {
     ///
     /// # Panics
     /// The function panics `self.has_data() == false` (i.e. `self.data` is `None`).
+    /// See `LayerElement::try_element_of_polygon_vertex()` for non-panicking version.
     pub fn element_of_polygon_vertex(&self, mesh: &Mesh, pvi: usize) -> T {
-        self.data.as_ref().expect("`LayerElement::element_of_polygon_vertex()` is called for the layer element with no data")[self.element_index_of_polygon_vertex(mesh, pvi)]
+        self.try_element_of_polygon_vertex(mesh, pvi).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Get an element corresponding to the given polygon vertex index (index of vertex index).
+    ///
+    /// Unlike `LayerElement::element_of_polygon_vertex()`, this returns an error for the layer
+    /// element with no data or out-of-range index.
+    pub fn try_element_of_polygon_vertex(&self, mesh: &Mesh, pvi: usize) -> MeshResult<T> {
+        let index = try!(self.try_element_index_of_polygon_vertex(mesh, pvi));
+        match self.data {
+            Some(ref data) => data.get(index).cloned().ok_or_else(|| self.error(mesh, MeshErrorKind::ElementOutOfRange(index))),
+            None => Err(self.error(mesh, MeshErrorKind::NoData)),
+        }
+    }
+
+    /// Returns the element for the given mapped index (index of control point, polygon, edge,
+    /// etc. in accordance with the mapping mode).
+    ///
+    /// Returns `None` if the layer element has no data or the index is out of range.
+    pub fn mapped_element(&self, index: usize) -> Option<T> {
+        let data = if let Some(ref data) = self.data {
+            data
+        } else {
+            return None;
+        };
+        match self.reference_mode {
+            ReferenceMode::Direct => data.get(index).cloned(),
+            ReferenceMode::IndexToDirect(ref indices) => indices.get(index).and_then(|&i| data.get(i as usize)).cloned(),
+        }
+    }
+
+    /// Creates an error for the layer element of the given mesh.
+    fn error(&self, mesh: &Mesh, kind: MeshErrorKind) -> MeshError {
+        MeshError {
+            mesh: mesh.id,
+            layer_element: Some((self.channel, self.name.clone())),
+            kind: kind,
+        }
     }
 }
 
@@ -151,6 +211,11 @@ enum ReferenceModeType {
 
 pub trait LoadAsLayerElementElement: Copy {
     fn node_properties_to_elements_array(properties: &DelayedProperties) -> Option<Vec<Self>>;
+
+    /// Merges the values of `*W` node (such as `TangentsW`) into the elements.
+    ///
+    /// Elements which have no `w` component ignore the values.
+    fn apply_w(_elements: &mut [Self], _w: &[f32]) {}
 }
 
 impl LoadAsLayerElementElement for () {
@@ -159,6 +224,27 @@ impl LoadAsLayerElementElement for () {
     }
 }
 
+impl LoadAsLayerElementElement for i32 {
+    fn node_properties_to_elements_array(properties: &DelayedProperties) -> Option<Vec<i32>> {
+        properties.iter().next().and_then(|p| p.extract_vec_i32().ok())
+    }
+}
+
+impl LoadAsLayerElementElement for bool {
+    fn node_properties_to_elements_array(properties: &DelayedProperties) -> Option<Vec<bool>> {
+        properties.iter().next().and_then(|p| p.extract_vec_i32()
+            .map(|vec| vec.into_iter().map(|v| v != 0).collect())
+            .or_else(|p| p.as_vec_f32().map(|vec| vec.iter().map(|&v| v != 0.0).collect()).ok_or(()))
+            .ok())
+    }
+}
+
+impl LoadAsLayerElementElement for f32 {
+    fn node_properties_to_elements_array(properties: &DelayedProperties) -> Option<Vec<f32>> {
+        properties.iter().next().and_then(|p| p.as_vec_f32().map(|vec| vec.into_owned()))
+    }
+}
+
 impl LoadAsLayerElementElement for [f32; 2] {
     fn node_properties_to_elements_array(properties: &DelayedProperties) -> Option<Vec<[f32; 2]>> {
         properties.iter().next().and_then(|p| p.as_vec_f32()
@@ -181,16 +267,44 @@ impl LoadAsLayerElementElement for [f32; 3] {
     }
 }
 
+/// Vector with `w` component, such as tangent and binormal.
+///
+/// `xyz` is loaded from the data node (such as `Tangents`), and `w` is loaded from the `*W` node
+/// (such as `TangentsW`).
+/// `w` is the handedness sign of the tangent space, and it is `1.0` when the `*W` node doesn't
+/// exist.
+impl LoadAsLayerElementElement for [f32; 4] {
+    fn node_properties_to_elements_array(properties: &DelayedProperties) -> Option<Vec<[f32; 4]>> {
+        properties.iter().next().and_then(|p| p.as_vec_f32()
+            .into_iter().find(|v| v.len() > 0) // Prevent `slice::chunks()` from panicking.
+            .map(|vec| {
+                let len = vec.len() / 3;
+                vec.chunks(3).take(len).map(|e| [e[0], e[1], e[2], 1.0]).collect()
+            }))
+    }
+
+    fn apply_w(elements: &mut [[f32; 4]], w: &[f32]) {
+        if elements.len() != w.len() {
+            warn!("Number of `w` components (={}) is different from number of elements (={}) in `/Objects/Geometry(Mesh)/LayerElement*`", w.len(), elements.len());
+        }
+        for (elem, &w) in elements.iter_mut().zip(w) {
+            elem[3] = w;
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct LayerElementLoader<'a, T: LoadAsLayerElementElement> {
     data_node_name: &'a str,
     index_node_name: &'a str,
+    w_node_name: Option<&'a str>,
     channel: i32,
     name: Option<String>,
     mapping_mode: Option<MappingMode>,
     reference_mode: Option<ReferenceModeType>,
     data: Option<Vec<T>>,
     index: Option<Vec<u32>>,
+    w: Option<Vec<f32>>,
 }
 
 impl<'a, T: LoadAsLayerElementElement> LayerElementLoader<'a, T> {
@@ -207,14 +321,22 @@ impl<'a, T: LoadAsLayerElementElement> LayerElementLoader<'a, T> {
         LayerElementLoader {
             data_node_name: data_node_name,
             index_node_name: index_node_name,
+            w_node_name: None,
             channel: channel,
             name: None,
             mapping_mode: None,
             reference_mode: None,
             data: None,
             index: None,
+            w: None,
         }
     }
+
+    /// Sets the name of the node which has `w` components of the elements (such as `TangentsW`).
+    pub fn w_node_name(mut self, w_node_name: &'a str) -> Self {
+        self.w_node_name = Some(w_node_name);
+        self
+    }
 }
 
 impl<'a, T: LoadAsLayerElementElement> NodeLoaderCommon for LayerElementLoader<'a, T> {
@@ -222,6 +344,9 @@ impl<'a, T: LoadAsLayerElementElement> NodeLoaderCommon for LayerElementLoader<'
 
     fn on_finish(mut self) -> Result<Self::Target> {
         let index = self.index.take();
+        if let (Some(data), Some(w)) = (self.data.as_mut(), self.w.as_ref()) {
+            T::apply_w(data, w);
+        }
         if_all_some!{(
             name=self.name,
             mapping_mode=self.mapping_mode,
@@ -282,6 +407,9 @@ impl<'a, T: LoadAsLayerElementElement, R: Read> NodeLoader<R> for LayerElementLo
             _ if name == self.index_node_name => {
                 self.index = properties.iter().next().and_then(|p| p.extract_vec_i32().ok().map(|v| v.into_iter().map(|v| v as u32).collect()));
             },
+            _ if Some(name.as_ref()) == self.w_node_name => {
+                self.w = properties.iter().next().and_then(|p| p.into_vec_f32().ok());
+            },
             "NormalsW" => {}, // TODO: `NormalsW` may have euclidean norms of normals.
             _ => {
                 warn!("Unknown node: `/Objects/Geometry(Mesh)/LayerElement*/{}`", name);
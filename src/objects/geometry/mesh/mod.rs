@@ -1,19 +1,37 @@
 //! Contains `/Objects/Geometry(Mesh)` node-related stuff.
 
+pub use self::cleanup::{CleanupOptions, ControlPointRemap};
+pub use self::flatten::{FlattenedMesh, VertexCacheReport};
 pub use self::layer::Layer;
 pub use self::layer_element::{MappingMode, ReferenceMode, LayerElement};
+pub use self::normals::NormalGenerationOptions;
+pub use self::polygons::{PolygonStructure, Polygons, Polygon, PolygonControlPoints};
+pub use self::smoothing::SmoothingLookup;
+pub use self::stats::{Aabb, BoundingSphere};
 
 use std::io::Read;
+use std::ops::Range;
 use fbx_binary_reader::EventReader;
 use definitions::Definitions;
-use error::Result;
-use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, ignore_current_node};
+use error::{MeshError, MeshErrorKind, MeshResult, Result};
+use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, as_vec_real, ignore_current_node};
 use objects::properties::ObjectProperties;
 use self::layer::LayerLoader;
 use self::layer_element::LayerElementLoader;
+use Real;
 
+mod cleanup;
+mod edges;
+mod flatten;
 mod layer;
 mod layer_element;
+mod normals;
+mod polygons;
+mod smoothing;
+mod stats;
+mod subdivision;
+mod tangents;
+mod uv_sets;
 
 
 #[derive(Debug, Clone)]
@@ -32,34 +50,100 @@ struct TriangulationInfo {
 pub struct Mesh {
     pub id: i64,
     pub name: String,
-    pub vertices: Vec<[f32; 3]>,
+    pub vertices: Vec<[Real; 3]>,
+    /// Polygon vertex index.
+    ///
+    /// `Mesh::rebuild_polygon_structure()` and `Mesh::rebuild_polygon_vertex_edges()` should be
+    /// called after this is modified manually.
     pub polygon_vertex_index: VertexIndex,
+    /// Polygon structure of `polygon_vertex_index` (see `Mesh::polygon_structure()`).
+    polygon_structure: PolygonStructure,
+    /// Edges of the mesh.
+    ///
+    /// Each value is a polygon vertex index (index of `polygon_vertex_index`), and the edge is
+    /// from the polygon vertex to the next polygon vertex in the same polygon.
+    /// `Mesh::rebuild_polygon_vertex_edges()` should be called after this is modified manually.
+    pub edges: Vec<u32>,
+    /// Edge of each polygon vertex (see `Mesh::polygon_vertex_edges()`).
+    polygon_vertex_edges: Vec<Option<u32>>,
     pub layer_element_materials: Vec<LayerElement<()>>,
     pub layer_element_normals: Vec<LayerElement<[f32; 3]>>,
     pub layer_element_uvs: Vec<LayerElement<[f32; 2]>>,
+    pub layer_element_tangents: Vec<LayerElement<[f32; 4]>>,
+    pub layer_element_binormals: Vec<LayerElement<[f32; 4]>>,
+    /// Smoothing groups (`ByPolygon`) or smooth flags of edges (`ByEdge`, `0` for hard edges).
+    pub layer_element_smoothing: Vec<LayerElement<i32>>,
+    /// Polygon groups (usually `ByPolygon`).
+    pub layer_element_polygon_groups: Vec<LayerElement<i32>>,
+    /// Visibility flags (`ByPolygon` or `ByEdge`, `false` for hidden ones).
+    pub layer_element_visibility: Vec<LayerElement<bool>>,
+    /// Crease weights of edges (`ByEdge`, `0.0` to `1.0` for fully creased ones).
+    pub layer_element_edge_creases: Vec<LayerElement<f32>>,
+    /// Crease weights of control points (`ByControlPoint`, `0.0` to `1.0` for fully creased ones).
+    pub layer_element_vertex_creases: Vec<LayerElement<f32>>,
     pub layers: Vec<Layer>,
+    /// Smoothness level of subdivision display (`Smoothness`).
+    pub smoothness: Option<i32>,
+    /// Subdivision levels for preview (`PreviewDivisionLevels`).
+    pub preview_division_levels: Option<i32>,
+    /// Subdivision levels for rendering (`RenderDivisionLevels`).
+    pub render_division_levels: Option<i32>,
 }
 
 impl Mesh {
+    /// Creates a mesh without layer elements and layers.
+    pub fn new(id: i64, name: String, vertices: Vec<[Real; 3]>, polygon_vertex_index: VertexIndex, edges: Vec<u32>) -> Self {
+        let mut mesh = Mesh {
+            id: id,
+            name: name,
+            vertices: vertices,
+            polygon_vertex_index: polygon_vertex_index,
+            polygon_structure: Default::default(),
+            edges: edges,
+            polygon_vertex_edges: Vec::new(),
+            layer_element_materials: Vec::new(),
+            layer_element_normals: Vec::new(),
+            layer_element_uvs: Vec::new(),
+            layer_element_tangents: Vec::new(),
+            layer_element_binormals: Vec::new(),
+            layer_element_smoothing: Vec::new(),
+            layer_element_polygon_groups: Vec::new(),
+            layer_element_visibility: Vec::new(),
+            layer_element_edge_creases: Vec::new(),
+            layer_element_vertex_creases: Vec::new(),
+            layers: Vec::new(),
+            smoothness: None,
+            preview_division_levels: None,
+            render_division_levels: None,
+        };
+        mesh.rebuild_polygon_structure();
+        mesh.rebuild_polygon_vertex_edges();
+        mesh
+    }
+
     /// Triangulates all polygons in the mesh with the given triangulation function.
     ///
     /// This function modifies `polygon_vertex_index` and layer elements, but doesn't change
     /// `vertices`.
     pub fn triangulate<F>(&mut self, triangulator: F)
-        where F: Fn(&[[f32; 3]], &[u32], &mut Vec<u32>) -> u32
+        where F: Fn(&[[Real; 3]], &[u32], &mut Vec<u32>) -> u32
     {
         // Triangulate and update layer elements only when the vertex index (polygon vertices) is
         // not yet triangulated.
         if let Some(result) = self.triangulate_polygon_index(triangulator) {
+            // Update edges before polygon vertices are replaced, because the source edges refer
+            // to the source polygon vertices.
+            let num_new_edges = edges::triangulate_edges(self, &result.tri_vertex_index);
             self.polygon_vertex_index = VertexIndex::Triangulated(result.tri_vertex_index);
+            self.rebuild_polygon_structure();
             // Update layer elements in accordance with updated polygon vertices
             // `tri_vertex_index`.
-            self.apply_triangulation_to_layer_elements(&result.tri_pvi_to_src_pvi, &result.tri_poly_to_src_poly);
+            self.remap_polygon_layer_elements(&result.tri_pvi_to_src_pvi, &result.tri_poly_to_src_poly, num_new_edges);
         }
     }
 
     fn triangulate_polygon_index<F>(&self, triangulator: F) -> Option<TriangulationInfo>
-        where F: Fn(&[[f32; 3]], &[u32], &mut Vec<u32>) -> u32
+        where F: Fn(&[[Real; 3]], &[u32], &mut Vec<u32>) -> u32
     {
         // Triangulate and update layer elements only when the vertex index (polygon vertices) is
         // not yet triangulated.
@@ -149,10 +233,25 @@ impl Mesh {
         })
     }
 
-    fn apply_triangulation_to_layer_elements(&mut self, tri_pvi_to_src_pvi: &Vec<u32>, tri_poly_to_src_poly: &Vec<u32>) {
-        update_layer_elements(&mut self.layer_element_materials, tri_pvi_to_src_pvi, tri_poly_to_src_poly);
-        update_layer_elements(&mut self.layer_element_normals, tri_pvi_to_src_pvi, tri_poly_to_src_poly);
-        update_layer_elements(&mut self.layer_element_uvs, tri_pvi_to_src_pvi, tri_poly_to_src_poly);
+    /// Updates layer elements for new polygons (for example, triangulated polygons), using maps
+    /// from new polygon vertices and new polygons to the source ones.
+    ///
+    /// Values of `ByEdge` layer elements for new diagonal edges are defaults which keep the
+    /// appearance of the source polygons (for example, diagonal edges are smooth).
+    fn remap_polygon_layer_elements(&mut self, tri_pvi_to_src_pvi: &Vec<u32>, tri_poly_to_src_poly: &Vec<u32>, num_new_edges: usize) {
+        update_layer_elements(&mut self.layer_element_materials, tri_pvi_to_src_pvi, tri_poly_to_src_poly, num_new_edges, ());
+        update_layer_elements(&mut self.layer_element_normals, tri_pvi_to_src_pvi, tri_poly_to_src_poly, num_new_edges, [0.0; 3]);
+        update_layer_elements(&mut self.layer_element_uvs, tri_pvi_to_src_pvi, tri_poly_to_src_poly, num_new_edges, [0.0; 2]);
+        update_layer_elements(&mut self.layer_element_tangents, tri_pvi_to_src_pvi, tri_poly_to_src_poly, num_new_edges, [0.0; 4]);
+        update_layer_elements(&mut self.layer_element_binormals, tri_pvi_to_src_pvi, tri_poly_to_src_poly, num_new_edges, [0.0; 4]);
+        // Diagonal edges are smooth.
+        update_layer_elements(&mut self.layer_element_smoothing, tri_pvi_to_src_pvi, tri_poly_to_src_poly, num_new_edges, 1);
+        update_layer_elements(&mut self.layer_element_polygon_groups, tri_pvi_to_src_pvi, tri_poly_to_src_poly, num_new_edges, 0);
+        // Diagonal edges are invisible.
+        update_layer_elements(&mut self.layer_element_visibility, tri_pvi_to_src_pvi, tri_poly_to_src_poly, num_new_edges, false);
+        // Diagonal edges are not creased.
+        update_layer_elements(&mut self.layer_element_edge_creases, tri_pvi_to_src_pvi, tri_poly_to_src_poly, num_new_edges, 0.0);
+        update_layer_elements(&mut self.layer_element_vertex_creases, tri_pvi_to_src_pvi, tri_poly_to_src_poly, num_new_edges, 0.0);
     }
 
     /// Returns "polygon vertex" (control point index) list of triangulated polygon.
@@ -160,15 +259,108 @@ impl Mesh {
     /// # Panics
     /// This function should be called on `triangulate()`d mesh.
     /// If the mesh is not yet triangulated, this function panics.
+    /// See `Mesh::try_triangulated_index_list()` for non-panicking version.
     pub fn triangulated_index_list(&self) -> &Vec<u32> {
         match self.polygon_vertex_index {
             VertexIndex::Triangulated(ref pvi) => &pvi,
             _ => panic!("`Mesh::get_expanded_triangles_list()` called on not triangulated mesh"),
         }
     }
+
+    /// Returns "polygon vertex" (control point index) list of triangulated polygon.
+    ///
+    /// Returns an error if the mesh is not yet triangulated.
+    pub fn try_triangulated_index_list(&self) -> MeshResult<&Vec<u32>> {
+        match self.polygon_vertex_index {
+            VertexIndex::Triangulated(ref pvi) => Ok(&pvi),
+            _ => Err(MeshError {
+                mesh: self.id,
+                layer_element: None,
+                kind: MeshErrorKind::NotTriangulated,
+            }),
+        }
+    }
+
+    /// Returns the number of polygon vertices.
+    pub fn num_polygon_vertices(&self) -> usize {
+        match self.polygon_vertex_index {
+            VertexIndex::NotTriangulated(ref vec) => vec.len(),
+            VertexIndex::Triangulated(ref vec) => vec.len(),
+        }
+    }
+
+    /// Returns the control point index of the given polygon vertex.
+    fn control_point_of_polygon_vertex(&self, pvi: usize) -> usize {
+        match self.polygon_vertex_index {
+            VertexIndex::NotTriangulated(ref vec) => match vec[pvi] {
+                i if i < 0 => !i as usize,
+                i => i as usize,
+            },
+            VertexIndex::Triangulated(ref vec) => vec[pvi] as usize,
+        }
+    }
+
+    /// Returns the control point index of the given polygon vertex.
+    ///
+    /// Returns an error if the polygon vertex or the control point is out of range.
+    pub fn try_control_point_of_polygon_vertex(&self, pvi: usize) -> MeshResult<usize> {
+        if pvi >= self.num_polygon_vertices() {
+            return Err(self.error(MeshErrorKind::PolygonVertexOutOfRange(pvi)));
+        }
+        let cp = self.control_point_of_polygon_vertex(pvi);
+        if cp >= self.vertices.len() {
+            return Err(self.error(MeshErrorKind::ControlPointOutOfRange(cp)));
+        }
+        Ok(cp)
+    }
+
+    /// Returns the position of the control point of the given polygon vertex.
+    ///
+    /// Returns an error if the polygon vertex or the control point is out of range.
+    pub fn try_position_of_polygon_vertex(&self, pvi: usize) -> MeshResult<[Real; 3]> {
+        self.try_control_point_of_polygon_vertex(pvi).map(|cp| self.vertices[cp])
+    }
+
+    /// Creates an error for the mesh.
+    fn error(&self, kind: MeshErrorKind) -> MeshError {
+        MeshError {
+            mesh: self.id,
+            layer_element: None,
+            kind: kind,
+        }
+    }
+
+    /// Creates an error for the layer element of the mesh.
+    fn layer_element_error<T: Copy>(&self, layer_element: &LayerElement<T>, kind: MeshErrorKind) -> MeshError {
+        MeshError {
+            mesh: self.id,
+            layer_element: Some((layer_element.channel, layer_element.name.clone())),
+            kind: kind,
+        }
+    }
+
+    /// Returns ranges of polygon vertices for each polygon.
+    fn polygon_vertex_ranges(&self) -> Vec<Range<usize>> {
+        self.polygons().map(|polygon| polygon.polygon_vertices()).collect()
+    }
+
+    /// Returns the polygon structure of `self.polygon_vertex_index`.
+    ///
+    /// This is kept up to date by methods of the mesh, and should be recomputed by
+    /// `Mesh::rebuild_polygon_structure()` after `polygon_vertex_index` is modified manually.
+    pub fn polygon_structure(&self) -> &PolygonStructure {
+        &self.polygon_structure
+    }
+
+    /// Recomputes the polygon structure from `self.polygon_vertex_index`.
+    ///
+    /// This should be called after `polygon_vertex_index` is modified manually.
+    pub fn rebuild_polygon_structure(&mut self) {
+        self.polygon_structure = PolygonStructure::from_vertex_index(&self.polygon_vertex_index);
+    }
 }
 
-fn update_layer_elements<'a, T, I>(layer_elements: I, tri_pvi_to_src_pvi: &Vec<u32>, tri_poly_to_src_poly: &Vec<u32>)
+fn update_layer_elements<'a, T, I>(layer_elements: I, tri_pvi_to_src_pvi: &Vec<u32>, tri_poly_to_src_poly: &Vec<u32>, num_new_edges: usize, new_edge_value: T)
     where T: 'a + Copy,
           I: 'a + IntoIterator<Item = &'a mut LayerElement<T>>,
 {
@@ -178,12 +370,25 @@ fn update_layer_elements<'a, T, I>(layer_elements: I, tri_pvi_to_src_pvi: &Vec<u
             MappingMode::None |
             // ByControlPoint: Control point is not changed.
             MappingMode::ByControlPoint |
-            // ByEdge: Edge-related feature is not supported by current `fbx_load` crate.
-            MappingMode::ByEdge |
             // AllSame: No dependency on polygons.
             MappingMode::AllSame => {
                 // Do nothing.
             },
+            MappingMode::ByEdge => {
+                // Source edges keep their indices, and new diagonal edges are appended.
+                if let Some(ref mut data) = le.data {
+                    match le.reference_mode {
+                        ReferenceMode::Direct => {
+                            data.extend(::std::iter::repeat(new_edge_value).take(num_new_edges));
+                        },
+                        ReferenceMode::IndexToDirect(ref mut indices) => if num_new_edges > 0 {
+                            data.push(new_edge_value);
+                            let new_index = (data.len() - 1) as u32;
+                            indices.extend(::std::iter::repeat(new_index).take(num_new_edges));
+                        },
+                    }
+                }
+            },
             MappingMode::ByPolygonVertex => {
                 // NOTE: Update can be more effective by changing reference mode from `Direct`
                 //       to `IndexToDirect`, but this function doesn't do it (because the modes
@@ -221,12 +426,23 @@ fn update_layer_elements<'a, T, I>(layer_elements: I, tri_pvi_to_src_pvi: &Vec<u
 pub struct MeshLoader<'a> {
     //definitions: &'a Definitions,
     obj_props: &'a ObjectProperties<'a>,
-    vertices: Option<Vec<[f32; 3]>>,
+    vertices: Option<Vec<[Real; 3]>>,
     polygon_vertex_index: Option<Vec<i32>>,
+    edges: Option<Vec<u32>>,
     layer_element_materials: Vec<LayerElement<()>>,
     layer_element_normals: Vec<LayerElement<[f32; 3]>>,
     layer_element_uvs: Vec<LayerElement<[f32; 2]>>,
+    layer_element_tangents: Vec<LayerElement<[f32; 4]>>,
+    layer_element_binormals: Vec<LayerElement<[f32; 4]>>,
+    layer_element_smoothing: Vec<LayerElement<i32>>,
+    layer_element_polygon_groups: Vec<LayerElement<i32>>,
+    layer_element_visibility: Vec<LayerElement<bool>>,
+    layer_element_edge_creases: Vec<LayerElement<f32>>,
+    layer_element_vertex_creases: Vec<LayerElement<f32>>,
     layers: Vec<Layer>,
+    smoothness: Option<i32>,
+    preview_division_levels: Option<i32>,
+    render_division_levels: Option<i32>,
 }
 
 impl<'a> MeshLoader<'a> {
@@ -236,10 +452,21 @@ impl<'a> MeshLoader<'a> {
             obj_props: obj_props,
             vertices: None,
             polygon_vertex_index: None,
+            edges: None,
             layer_element_materials: Default::default(),
             layer_element_normals: Default::default(),
             layer_element_uvs: Default::default(),
+            layer_element_tangents: Default::default(),
+            layer_element_binormals: Default::default(),
+            layer_element_smoothing: Default::default(),
+            layer_element_polygon_groups: Default::default(),
+            layer_element_visibility: Default::default(),
+            layer_element_edge_creases: Default::default(),
+            layer_element_vertex_creases: Default::default(),
             layers: Default::default(),
+            smoothness: None,
+            preview_division_levels: None,
+            render_division_levels: None,
         }
     }
 }
@@ -252,16 +479,32 @@ impl<'a> NodeLoaderCommon for MeshLoader<'a> {
             vertices=self.vertices,
             polygon_vertex_index=self.polygon_vertex_index,
         ) {
-            Ok(Some(Mesh {
+            let mut mesh = Mesh {
                 id: self.obj_props.id,
                 name: self.obj_props.name.to_owned(),
                 vertices: vertices,
                 polygon_vertex_index: VertexIndex::NotTriangulated(polygon_vertex_index),
+                polygon_structure: Default::default(),
+                edges: self.edges.unwrap_or_default(),
+                polygon_vertex_edges: vec![],
                 layer_element_materials: self.layer_element_materials,
                 layer_element_normals: self.layer_element_normals,
                 layer_element_uvs: self.layer_element_uvs,
+                layer_element_tangents: self.layer_element_tangents,
+                layer_element_binormals: self.layer_element_binormals,
+                layer_element_smoothing: self.layer_element_smoothing,
+                layer_element_polygon_groups: self.layer_element_polygon_groups,
+                layer_element_visibility: self.layer_element_visibility,
+                layer_element_edge_creases: self.layer_element_edge_creases,
+                layer_element_vertex_creases: self.layer_element_vertex_creases,
                 layers: self.layers,
-            }))
+                smoothness: self.smoothness,
+                preview_division_levels: self.preview_division_levels,
+                render_division_levels: self.render_division_levels,
+            };
+            mesh.rebuild_polygon_structure();
+            mesh.rebuild_polygon_vertex_edges();
+            Ok(Some(mesh))
         } else {
             error!("Required property not found for `/Objects/Geometry(Mesh)`");
             Ok(None)
@@ -275,7 +518,7 @@ impl<'a, R: Read> NodeLoader<R> for MeshLoader<'a> {
         match name.as_ref() {
             "Vertices" => {
                 self.vertices = properties.iter().next()
-                    .and_then(|p| p.as_vec_f32()
+                    .and_then(|p| as_vec_real(&p)
                         .into_iter().find(|vec| vec.len() > 0) // Prevent vec.chunks() from panicking.
                         .map(|vec| {
                             let len = vec.len() / 3;
@@ -320,6 +563,20 @@ impl<'a, R: Read> NodeLoader<R> for MeshLoader<'a> {
             } else {
                 try!(ignore_current_node(reader));
             },
+            "LayerElementTangent" => if let Some(loader) = LayerElementLoader::<[f32; 4]>::from_node_properties(&properties, "Tangents", "TangentsIndex") {
+                if let Some(layer_elem) = try!(loader.w_node_name("TangentsW").load(reader)) {
+                    self.layer_element_tangents.push(layer_elem);
+                }
+            } else {
+                try!(ignore_current_node(reader));
+            },
+            "LayerElementBinormal" => if let Some(loader) = LayerElementLoader::<[f32; 4]>::from_node_properties(&properties, "Binormals", "BinormalsIndex") {
+                if let Some(layer_elem) = try!(loader.w_node_name("BinormalsW").load(reader)) {
+                    self.layer_element_binormals.push(layer_elem);
+                }
+            } else {
+                try!(ignore_current_node(reader));
+            },
             "Layer" => if let Some(loader) = LayerLoader::from_node_properties(&properties) {
                 if let Some(layer) = try!(loader.load(reader)) {
                     self.layers.push(layer);
@@ -327,7 +584,55 @@ impl<'a, R: Read> NodeLoader<R> for MeshLoader<'a> {
             } else {
                 try!(ignore_current_node(reader));
             },
+            "LayerElementSmoothing" => if let Some(loader) = LayerElementLoader::<i32>::from_node_properties(&properties, "Smoothing", "SmoothingIndex") {
+                if let Some(layer_elem) = try!(loader.load(reader)) {
+                    self.layer_element_smoothing.push(layer_elem);
+                }
+            } else {
+                try!(ignore_current_node(reader));
+            },
+            "LayerElementPolygonGroup" => if let Some(loader) = LayerElementLoader::<i32>::from_node_properties(&properties, "PolygonGroup", "PolygonGroupIndex") {
+                if let Some(layer_elem) = try!(loader.load(reader)) {
+                    self.layer_element_polygon_groups.push(layer_elem);
+                }
+            } else {
+                try!(ignore_current_node(reader));
+            },
+            "LayerElementVisibility" => if let Some(loader) = LayerElementLoader::<bool>::from_node_properties(&properties, "Visibility", "VisibilityIndex") {
+                if let Some(layer_elem) = try!(loader.load(reader)) {
+                    self.layer_element_visibility.push(layer_elem);
+                }
+            } else {
+                try!(ignore_current_node(reader));
+            },
+            "LayerElementEdgeCrease" => if let Some(loader) = LayerElementLoader::<f32>::from_node_properties(&properties, "EdgeCrease", "EdgeCreaseIndex") {
+                if let Some(layer_elem) = try!(loader.load(reader)) {
+                    self.layer_element_edge_creases.push(layer_elem);
+                }
+            } else {
+                try!(ignore_current_node(reader));
+            },
+            "LayerElementVertexCrease" => if let Some(loader) = LayerElementLoader::<f32>::from_node_properties(&properties, "VertexCrease", "VertexCreaseIndex") {
+                if let Some(layer_elem) = try!(loader.load(reader)) {
+                    self.layer_element_vertex_creases.push(layer_elem);
+                }
+            } else {
+                try!(ignore_current_node(reader));
+            },
+            "Smoothness" => {
+                self.smoothness = properties.iter().next().and_then(|p| p.get_i32());
+                try!(ignore_current_node(reader));
+            },
+            "PreviewDivisionLevels" => {
+                self.preview_division_levels = properties.iter().next().and_then(|p| p.get_i32());
+                try!(ignore_current_node(reader));
+            },
+            "RenderDivisionLevels" => {
+                self.render_division_levels = properties.iter().next().and_then(|p| p.get_i32());
+                try!(ignore_current_node(reader));
+            },
             "Edges" => {
+                self.edges = properties.iter().next().and_then(|p| p.extract_vec_i32().ok()).map(|v| v.into_iter().map(|v| v as u32).collect());
                 try!(ignore_current_node(reader));
             },
             _ => {
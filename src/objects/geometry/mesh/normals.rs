@@ -0,0 +1,171 @@
+//! Contains normal generation for meshes.
+
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+use std::ops::Range;
+use fnv::FnvHasher;
+use error::MeshResult;
+use math::{vec3_add, vec3_angle, vec3_normalize, vec3_scale, vec3_sub, vec3_to_f32, polygon_normal};
+use super::{Mesh, MappingMode, ReferenceMode, LayerElement, SmoothingLookup};
+use Real;
+
+
+/// Options for `Mesh::generate_normals()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NormalGenerationOptions {
+    /// If `false`, each polygon vertex gets the normal of its polygon (flat shading).
+    pub smooth: bool,
+    /// Maximum angle (in radians) between two adjacent polygons to be smoothed.
+    ///
+    /// If `None`, adjacent polygons are smoothed regardless of the angle between them.
+    pub angle_threshold: Option<f32>,
+    /// If `true`, hard edges given by `LayerElementSmoothing` are not smoothed.
+    pub respect_smoothing: bool,
+}
+
+impl Default for NormalGenerationOptions {
+    fn default() -> Self {
+        NormalGenerationOptions {
+            smooth: true,
+            angle_threshold: None,
+            respect_smoothing: true,
+        }
+    }
+}
+
+impl Mesh {
+    /// Computes normals of the mesh.
+    ///
+    /// The result is a new layer element with `ByPolygonVertex` mapping and `Direct` reference,
+    /// and it is not added to `self.layer_element_normals`.
+    ///
+    /// Smooth normals are weighted by the angle of each polygon at the corner, and polygon
+    /// vertices sharing the same control point are smoothed only when they are connected by
+    /// smooth edges (see `NormalGenerationOptions::angle_threshold` and
+    /// `NormalGenerationOptions::respect_smoothing`).
+    ///
+    /// Returns an error if a polygon vertex refers to a nonexistent control point.
+    pub fn generate_normals(&self, options: &NormalGenerationOptions) -> MeshResult<LayerElement<[f32; 3]>> {
+        let polygons = self.polygon_vertex_ranges();
+        let num_pv = self.num_polygon_vertices();
+        let positions = try!((0..num_pv).map(|pvi| self.try_position_of_polygon_vertex(pvi)).collect::<MeshResult<Vec<_>>>());
+        // Normalized normal of each polygon.
+        let polygon_normals = polygons.iter().map(|range| {
+            vec3_normalize(polygon_normal(range.clone().map(|pvi| positions[pvi])))
+        }).collect::<Vec<_>>();
+
+        let mut normals = vec![[0.0; 3]; num_pv];
+        if !options.smooth {
+            for (poly, range) in polygons.iter().enumerate() {
+                for pvi in range.clone() {
+                    normals[pvi] = vec3_to_f32(polygon_normals[poly]);
+                }
+            }
+        } else {
+            let smoothing = if options.respect_smoothing {
+                self.smoothing_lookup()
+            } else {
+                SmoothingLookup::None
+            };
+            let mut groups = CornerGroups::new(num_pv);
+            self.merge_smooth_corners(&polygons, &mut groups, |poly1, poly2, edge| {
+                smoothing.is_smooth(poly1, poly2, edge)
+                    && options.angle_threshold.map_or(true, |threshold| vec3_angle(polygon_normals[poly1], polygon_normals[poly2]) <= threshold as Real)
+            });
+            // Accumulate angle-weighted polygon normals for each group.
+            let mut sums = vec![[0.0; 3]; num_pv];
+            for (poly, range) in polygons.iter().enumerate() {
+                for pvi in range.clone() {
+                    let weight = corner_angle(&positions, range, pvi);
+                    let root = groups.find(pvi);
+                    sums[root] = vec3_add(sums[root], vec3_scale(polygon_normals[poly], weight));
+                }
+            }
+            for (pvi, normal) in normals.iter_mut().enumerate() {
+                *normal = vec3_to_f32(vec3_normalize(sums[groups.find(pvi)]));
+            }
+        }
+
+        Ok(LayerElement {
+            channel: self.layer_element_normals.len() as i32,
+            name: String::new(),
+            mapping_mode: MappingMode::ByPolygonVertex,
+            reference_mode: ReferenceMode::Direct,
+            data: Some(normals),
+        })
+    }
+
+    /// Merges groups of polygon vertices which share a control point and an edge whose adjacent
+    /// polygons are smooth (`is_smooth(poly1, poly2, edge)` returns `true`).
+    fn merge_smooth_corners<F>(&self, polygons: &[Range<usize>], groups: &mut CornerGroups, is_smooth: F)
+        where F: Fn(usize, usize, (usize, usize)) -> bool
+    {
+        // Polygon vertex pairs for each (undirected) edge, keyed by sorted control points.
+        let mut edges: HashMap<(usize, usize), Vec<(usize, usize, usize)>, BuildHasherDefault<FnvHasher>> = Default::default();
+        for (poly, range) in polygons.iter().enumerate() {
+            for pvi in range.clone() {
+                let next_pvi = if pvi + 1 == range.end { range.start } else { pvi + 1 };
+                let cp = self.control_point_of_polygon_vertex(pvi);
+                let next_cp = self.control_point_of_polygon_vertex(next_pvi);
+                let key = if cp < next_cp { (cp, next_cp) } else { (next_cp, cp) };
+                edges.entry(key).or_insert_with(Vec::new).push((poly, pvi, next_pvi));
+            }
+        }
+        for (key, sides) in edges {
+            for (i, &(poly1, pvi1, next_pvi1)) in sides.iter().enumerate() {
+                for &(poly2, pvi2, next_pvi2) in &sides[i + 1..] {
+                    if poly1 == poly2 || !is_smooth(poly1, poly2, key) {
+                        continue;
+                    }
+                    // Merge the corners at the same control point.
+                    if self.control_point_of_polygon_vertex(pvi1) == self.control_point_of_polygon_vertex(pvi2) {
+                        groups.union(pvi1, pvi2);
+                        groups.union(next_pvi1, next_pvi2);
+                    } else {
+                        groups.union(pvi1, next_pvi2);
+                        groups.union(next_pvi1, pvi2);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the angle of the polygon at the given polygon vertex.
+///
+/// `positions` are positions of all polygon vertices.
+fn corner_angle(positions: &[[Real; 3]], polygon: &Range<usize>, pvi: usize) -> Real {
+    let prev_pvi = if pvi == polygon.start { polygon.end - 1 } else { pvi - 1 };
+    let next_pvi = if pvi + 1 == polygon.end { polygon.start } else { pvi + 1 };
+    let pos = positions[pvi];
+    vec3_angle(vec3_sub(positions[prev_pvi], pos), vec3_sub(positions[next_pvi], pos))
+}
+
+/// Disjoint sets of polygon vertices.
+pub struct CornerGroups {
+    parents: Vec<usize>,
+}
+
+impl CornerGroups {
+    pub fn new(len: usize) -> Self {
+        CornerGroups {
+            parents: (0..len).collect(),
+        }
+    }
+
+    pub fn find(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+        i
+    }
+
+    pub fn union(&mut self, i: usize, j: usize) {
+        let root_i = self.find(i);
+        let root_j = self.find(j);
+        if root_i != root_j {
+            self.parents[root_j] = root_i;
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! Contains polygon-related stuff of meshes.
+
+use std::ops::Range;
+use error::{MeshErrorKind, MeshResult};
+use super::{Mesh, VertexIndex, LayerElement};
+use Real;
+
+
+/// Polygon structure of a mesh.
+///
+/// This is computed once from the polygon vertex index, and makes polygon-related queries O(1)
+/// even for not triangulated meshes.
+#[derive(Debug, Clone, Default)]
+pub struct PolygonStructure {
+    /// Polygon vertex index at the beginning of each polygon, followed by the end of the last
+    /// polygon.
+    starts: Vec<u32>,
+    /// Polygon index of each polygon vertex, or `u32::MAX` for the polygon vertex not in any
+    /// polygon (i.e. trailing polygon vertices without terminating negative index).
+    polygon_of_polygon_vertex: Vec<u32>,
+}
+
+impl PolygonStructure {
+    /// Computes polygon structure from the given polygon vertex index.
+    pub fn from_vertex_index(vertex_index: &VertexIndex) -> Self {
+        match *vertex_index {
+            VertexIndex::NotTriangulated(ref vec) => {
+                let mut starts = vec![0];
+                let mut polygon_of_polygon_vertex = Vec::with_capacity(vec.len());
+                for (pvi, &pv) in vec.iter().enumerate() {
+                    polygon_of_polygon_vertex.push((starts.len() - 1) as u32);
+                    if pv < 0 {
+                        starts.push((pvi + 1) as u32);
+                    }
+                }
+                let end = *starts.last().unwrap() as usize;
+                if end != vec.len() {
+                    warn!("Polygon vertex index didn't end with negtive number");
+                    for v in &mut polygon_of_polygon_vertex[end..] {
+                        *v = ::std::u32::MAX;
+                    }
+                }
+                PolygonStructure {
+                    starts: starts,
+                    polygon_of_polygon_vertex: polygon_of_polygon_vertex,
+                }
+            },
+            VertexIndex::Triangulated(ref vec) => {
+                let num_polygons = vec.len() / 3;
+                PolygonStructure {
+                    starts: (0..num_polygons as u32 + 1).map(|i| i * 3).collect(),
+                    polygon_of_polygon_vertex: (0..num_polygons as u32 * 3).map(|i| i / 3).chain(::std::iter::repeat(::std::u32::MAX).take(vec.len() % 3)).collect(),
+                }
+            },
+        }
+    }
+
+    /// Returns the number of polygons.
+    pub fn num_polygons(&self) -> usize {
+        self.starts.len().saturating_sub(1)
+    }
+
+    /// Returns polygon start offsets (polygon vertex index at the beginning of each polygon).
+    pub fn polygon_starts(&self) -> &[u32] {
+        let len = self.num_polygons();
+        &self.starts[..len]
+    }
+
+    /// Returns the range of polygon vertices of the given polygon.
+    pub fn polygon_range(&self, polygon: usize) -> Option<Range<usize>> {
+        if polygon < self.num_polygons() {
+            Some(self.starts[polygon] as usize..self.starts[polygon + 1] as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of polygon vertices of the given polygon.
+    pub fn polygon_size(&self, polygon: usize) -> Option<usize> {
+        self.polygon_range(polygon).map(|range| range.len())
+    }
+
+    /// Returns an iterator of the number of polygon vertices of each polygon.
+    pub fn polygon_sizes<'a>(&'a self) -> Box<Iterator<Item = usize> + 'a> {
+        Box::new(self.starts.windows(2).map(|w| (w[1] - w[0]) as usize))
+    }
+
+    /// Returns the polygon which has the given polygon vertex.
+    pub fn polygon_of_polygon_vertex(&self, pvi: usize) -> Option<usize> {
+        match self.polygon_of_polygon_vertex.get(pvi) {
+            Some(&poly) if poly != ::std::u32::MAX => Some(poly as usize),
+            _ => None,
+        }
+    }
+}
+
+impl Mesh {
+    /// Returns the number of polygons.
+    pub fn num_polygons(&self) -> usize {
+        self.polygon_structure.num_polygons()
+    }
+
+    /// Returns the polygon of the given index.
+    ///
+    /// Returns `None` if the polygon is out of range of the polygon vertex index (i.e. the
+    /// polygon structure is not rebuilt after `polygon_vertex_index` is modified).
+    pub fn polygon(&self, index: usize) -> Option<Polygon> {
+        let num_pv = self.num_polygon_vertices();
+        self.polygon_structure.polygon_range(index).filter(|range| range.end <= num_pv).map(|range| Polygon {
+            mesh: self,
+            index: index,
+            polygon_vertices: range,
+        })
+    }
+
+    /// Returns an iterator of polygons.
+    ///
+    /// This works for both triangulated and not triangulated meshes.
+    pub fn polygons(&self) -> Polygons {
+        Polygons {
+            mesh: self,
+            next: 0,
+        }
+    }
+}
+
+/// Iterator of polygons of a mesh, created by `Mesh::polygons()`.
+#[derive(Debug, Clone)]
+pub struct Polygons<'a> {
+    mesh: &'a Mesh,
+    next: usize,
+}
+
+impl<'a> Iterator for Polygons<'a> {
+    type Item = Polygon<'a>;
+
+    fn next(&mut self) -> Option<Polygon<'a>> {
+        let mesh = self.mesh;
+        let polygon = mesh.polygon(self.next);
+        if polygon.is_some() {
+            self.next += 1;
+        }
+        polygon
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.mesh.num_polygons().saturating_sub(self.next);
+        (len, Some(len))
+    }
+}
+
+impl<'a> ExactSizeIterator for Polygons<'a> {}
+
+/// Polygon of a mesh.
+#[derive(Debug, Clone)]
+pub struct Polygon<'a> {
+    mesh: &'a Mesh,
+    index: usize,
+    polygon_vertices: Range<usize>,
+}
+
+impl<'a> Polygon<'a> {
+    /// Returns the mesh which has the polygon.
+    pub fn mesh(&self) -> &'a Mesh {
+        self.mesh
+    }
+
+    /// Returns the polygon index.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the range of polygon vertices (indices of `Mesh::polygon_vertex_index`).
+    pub fn polygon_vertices(&self) -> Range<usize> {
+        self.polygon_vertices.clone()
+    }
+
+    /// Returns the number of vertices (corners) of the polygon.
+    pub fn num_vertices(&self) -> usize {
+        self.polygon_vertices.len()
+    }
+
+    /// Returns the control point index of the given corner.
+    ///
+    /// Returns an error if the corner is out of range of the polygon, or the control point is out
+    /// of range of the mesh vertices.
+    pub fn control_point(&self, corner: usize) -> MeshResult<usize> {
+        let pvi = self.polygon_vertices.start + corner;
+        if corner < self.num_vertices() {
+            self.mesh.try_control_point_of_polygon_vertex(pvi)
+        } else {
+            Err(self.mesh.error(MeshErrorKind::PolygonVertexOutOfRange(pvi)))
+        }
+    }
+
+    /// Returns an iterator of control point indices of the corners.
+    ///
+    /// See `Polygon::control_point()` for errors.
+    pub fn control_points(&self) -> PolygonControlPoints<'a> {
+        PolygonControlPoints {
+            mesh: self.mesh,
+            polygon_vertices: self.polygon_vertices.clone(),
+        }
+    }
+
+    /// Returns positions of the corners.
+    ///
+    /// Returns an error if a corner refers to a nonexistent control point.
+    pub fn positions(&self) -> MeshResult<Vec<[Real; 3]>> {
+        self.polygon_vertices().map(|pvi| self.mesh.try_position_of_polygon_vertex(pvi)).collect()
+    }
+
+    /// Returns elements of the given layer element for each corner.
+    pub fn elements<T: Copy>(&self, layer_element: &LayerElement<T>) -> MeshResult<Vec<T>> {
+        self.polygon_vertices().map(|pvi| layer_element.try_element_of_polygon_vertex(self.mesh, pvi)).collect()
+    }
+
+    /// Returns element indices of the given layer element for each corner.
+    ///
+    /// This is useful for layer elements without data, such as `LayerElementMaterial`.
+    pub fn element_indices<T: Copy>(&self, layer_element: &LayerElement<T>) -> MeshResult<Vec<usize>> {
+        self.polygon_vertices().map(|pvi| layer_element.try_element_index_of_polygon_vertex(self.mesh, pvi)).collect()
+    }
+
+    /// Returns normals of the corners from the first normal layer element with data.
+    ///
+    /// Returns `None` if the mesh has no normals.
+    pub fn normals(&self) -> Option<MeshResult<Vec<[f32; 3]>>> {
+        self.mesh.layer_element_normals.iter().find(|le| le.has_data()).map(|le| self.elements(le))
+    }
+
+    /// Returns UVs of the corners from the UV layer element of the given channel.
+    ///
+    /// Returns `None` if the mesh has no UVs of the channel.
+    pub fn uvs(&self, channel: i32) -> Option<MeshResult<Vec<[f32; 2]>>> {
+        self.mesh.layer_element_uvs.iter().find(|le| le.channel == channel && le.has_data()).map(|le| self.elements(le))
+    }
+
+    /// Returns the material index of the polygon from the first material layer element.
+    ///
+    /// Returns `None` if the mesh has no material layer element.
+    pub fn material(&self) -> Option<MeshResult<usize>> {
+        let pvi = self.polygon_vertices.start;
+        self.mesh.layer_element_materials.first().map(|le| le.try_element_index_of_polygon_vertex(self.mesh, pvi))
+    }
+}
+
+/// Iterator of control point indices of a polygon, created by `Polygon::control_points()`.
+#[derive(Debug, Clone)]
+pub struct PolygonControlPoints<'a> {
+    mesh: &'a Mesh,
+    polygon_vertices: Range<usize>,
+}
+
+impl<'a> Iterator for PolygonControlPoints<'a> {
+    type Item = MeshResult<usize>;
+
+    fn next(&mut self) -> Option<MeshResult<usize>> {
+        self.polygon_vertices.next().map(|pvi| self.mesh.try_control_point_of_polygon_vertex(pvi))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.polygon_vertices.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for PolygonControlPoints<'a> {}
@@ -0,0 +1,131 @@
+//! Contains queries for smoothing information (`LayerElementSmoothing` and `Edges`) of meshes.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::BuildHasherDefault;
+use fnv::FnvHasher;
+use super::{Mesh, MappingMode, LayerElement};
+use super::edges::edge_key;
+
+
+impl Mesh {
+    /// Returns the smoothing layer element used to determine hard edges.
+    ///
+    /// This is the first smoothing layer element which has data.
+    pub fn smoothing(&self) -> Option<&LayerElement<i32>> {
+        self.layer_element_smoothing.iter().find(|le| le.has_data())
+    }
+
+    /// Returns whether the given edge is hard.
+    ///
+    /// Returns `None` if the mesh has no smoothing information or the edge is out of range.
+    ///
+    /// For `ByPolygon` smoothing (smoothing groups), an edge is hard when the polygons sharing it
+    /// have no smoothing group in common.
+    /// This creates a lookup for each call, so use `Mesh::smoothing_lookup()` (or
+    /// `Mesh::hard_edges()`) to query many edges.
+    pub fn is_hard_edge(&self, edge: usize) -> Option<bool> {
+        self.smoothing_lookup().is_hard_edge(self, edge)
+    }
+
+    /// Returns hard flags for all edges in `self.edges`.
+    ///
+    /// Returns `None` if the mesh has no smoothing information.
+    pub fn hard_edges(&self) -> Option<Vec<bool>> {
+        let lookup = self.smoothing_lookup();
+        if let SmoothingLookup::None = lookup {
+            return None;
+        }
+        Some((0..self.edges.len()).map(|edge| lookup.is_hard_edge(self, edge).unwrap_or(false)).collect())
+    }
+
+    /// Creates a lookup to test whether adjacent polygons are smoothed.
+    pub fn smoothing_lookup(&self) -> SmoothingLookup {
+        let smoothing = if let Some(v) = self.smoothing() {
+            v
+        } else {
+            return SmoothingLookup::None;
+        };
+        match smoothing.mapping_mode {
+            MappingMode::ByPolygon => {
+                let groups = polygon_smoothing_groups(self, smoothing);
+                // Collect smoothing groups of polygons around each edge.
+                let mut groups_of_edge: HashMap<(usize, usize), Vec<i32>, BuildHasherDefault<FnvHasher>> = Default::default();
+                for (poly, range) in self.polygon_vertex_ranges().into_iter().enumerate() {
+                    for pvi in range {
+                        let key = edge_key(self.control_point_of_polygon_vertex(pvi), self.control_point_of_polygon_vertex(self.next_polygon_vertex(pvi)));
+                        groups_of_edge.entry(key).or_insert_with(Vec::new).push(groups[poly]);
+                    }
+                }
+                let hard_edges = groups_of_edge.into_iter()
+                    .filter(|&(_, ref groups)| any_pair_without_common_group(groups))
+                    .map(|(key, _)| key)
+                    .collect();
+                SmoothingLookup::Groups(groups, hard_edges)
+            },
+            MappingMode::ByEdge => {
+                let mut hard_edges: HashSet<(usize, usize), BuildHasherDefault<FnvHasher>> = Default::default();
+                for edge in 0..self.edges.len() {
+                    if smoothing.mapped_element(edge) == Some(0) {
+                        if let Some((cp1, cp2)) = self.edge_control_points(edge) {
+                            hard_edges.insert(edge_key(cp1, cp2));
+                        }
+                    }
+                }
+                SmoothingLookup::HardEdges(hard_edges)
+            },
+            mode => {
+                warn!("Unsupported mapping mode for `LayerElementSmoothing`: `{:?}`", mode);
+                SmoothingLookup::None
+            },
+        }
+    }
+}
+
+/// Lookup to test whether adjacent polygons are smoothed, created by `Mesh::smoothing_lookup()`.
+#[derive(Debug, Clone)]
+pub enum SmoothingLookup {
+    /// No smoothing information.
+    None,
+    /// Smoothing groups (bit flags) of each polygon, and hard edges between polygons without
+    /// smoothing groups in common.
+    Groups(Vec<i32>, HashSet<(usize, usize), BuildHasherDefault<FnvHasher>>),
+    /// Hard edges, represented by pairs of control points (the smaller one first).
+    HardEdges(HashSet<(usize, usize), BuildHasherDefault<FnvHasher>>),
+}
+
+impl SmoothingLookup {
+    /// Returns whether the polygons `poly1` and `poly2` are smoothed at the edge between
+    /// control points `edge`.
+    pub fn is_smooth(&self, poly1: usize, poly2: usize, edge: (usize, usize)) -> bool {
+        match *self {
+            SmoothingLookup::None => true,
+            SmoothingLookup::Groups(ref groups, _) => match (groups.get(poly1), groups.get(poly2)) {
+                (Some(&g1), Some(&g2)) => g1 & g2 != 0,
+                _ => true,
+            },
+            SmoothingLookup::HardEdges(ref hard_edges) => !hard_edges.contains(&edge_key(edge.0, edge.1)),
+        }
+    }
+
+    /// Returns whether the given edge (index of `mesh.edges`) is hard.
+    ///
+    /// `mesh` should be the mesh the lookup is created from.
+    /// Returns `None` if there is no smoothing information or the edge is out of range.
+    pub fn is_hard_edge(&self, mesh: &Mesh, edge: usize) -> Option<bool> {
+        let hard_edges = match *self {
+            SmoothingLookup::None => return None,
+            SmoothingLookup::Groups(_, ref hard_edges) | SmoothingLookup::HardEdges(ref hard_edges) => hard_edges,
+        };
+        mesh.edge_control_points(edge).map(|(cp1, cp2)| hard_edges.contains(&edge_key(cp1, cp2)))
+    }
+}
+
+/// Returns whether any pair of the given smoothing groups has no group in common.
+fn any_pair_without_common_group(groups: &[i32]) -> bool {
+    groups.iter().enumerate().any(|(i, &g1)| groups[i + 1..].iter().any(|&g2| g1 & g2 == 0))
+}
+
+/// Returns smoothing groups for each polygon.
+fn polygon_smoothing_groups(mesh: &Mesh, smoothing: &LayerElement<i32>) -> Vec<i32> {
+    (0..mesh.polygon_structure.num_polygons()).map(|poly| smoothing.mapped_element(poly).unwrap_or(0)).collect()
+}
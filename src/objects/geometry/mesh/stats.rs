@@ -0,0 +1,165 @@
+//! Contains bounding volumes and statistics of meshes.
+
+use std::collections::BTreeMap;
+use math::{mat4_transform_point, polygon_normal, vec3_length, vec3_sub};
+use super::Mesh;
+use Real;
+
+
+/// Axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: [Real; 3],
+    pub max: [Real; 3],
+}
+
+impl Aabb {
+    /// Creates the bounding box of the given points.
+    ///
+    /// Non-finite points are ignored.
+    /// Returns `None` if there are no finite points.
+    pub fn from_points<I: IntoIterator<Item = [Real; 3]>>(points: I) -> Option<Self> {
+        points.into_iter()
+            .filter(|p| p.iter().all(|v| v.is_finite()))
+            .fold(None, |aabb: Option<Aabb>, p| Some(match aabb {
+                Some(aabb) => aabb.extended(p),
+                None => Aabb { min: p, max: p },
+            }))
+    }
+
+    /// Returns the bounding box extended to contain the given point.
+    pub fn extended(&self, p: [Real; 3]) -> Self {
+        Aabb {
+            min: [self.min[0].min(p[0]), self.min[1].min(p[1]), self.min[2].min(p[2])],
+            max: [self.max[0].max(p[0]), self.max[1].max(p[1]), self.max[2].max(p[2])],
+        }
+    }
+
+    /// Returns the bounding box containing both bounding boxes.
+    pub fn union(&self, other: &Aabb) -> Self {
+        self.extended(other.min).extended(other.max)
+    }
+
+    /// Returns the center of the bounding box.
+    pub fn center(&self) -> [Real; 3] {
+        [(self.min[0] + self.max[0]) * 0.5, (self.min[1] + self.max[1]) * 0.5, (self.min[2] + self.max[2]) * 0.5]
+    }
+
+    /// Returns the size of the bounding box.
+    pub fn size(&self) -> [Real; 3] {
+        vec3_sub(self.max, self.min)
+    }
+
+    /// Returns the corners of the bounding box.
+    pub fn corners(&self) -> [[Real; 3]; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            [min[0], min[1], min[2]],
+            [max[0], min[1], min[2]],
+            [min[0], max[1], min[2]],
+            [max[0], max[1], min[2]],
+            [min[0], min[1], max[2]],
+            [max[0], min[1], max[2]],
+            [min[0], max[1], max[2]],
+            [max[0], max[1], max[2]],
+        ]
+    }
+
+    /// Returns the bounding box of this bounding box transformed by the given matrix.
+    pub fn transformed(&self, matrix: &[[Real; 4]; 4]) -> Option<Self> {
+        Aabb::from_points(self.corners().iter().map(|&p| mat4_transform_point(matrix, p)))
+    }
+}
+
+/// Bounding sphere.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: [Real; 3],
+    pub radius: Real,
+}
+
+impl Mesh {
+    /// Returns the axis-aligned bounding box of the control points.
+    ///
+    /// Returns `None` if the mesh has no finite control points.
+    pub fn aabb(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices.iter().cloned())
+    }
+
+    /// Returns the bounding sphere of the control points.
+    ///
+    /// The center of the sphere is the center of the AABB, so the sphere is not always minimal.
+    /// Returns `None` if the mesh has no finite control points.
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        self.aabb().map(|aabb| {
+            let center = aabb.center();
+            let radius = self.vertices.iter()
+                .filter(|p| p.iter().all(|v| v.is_finite()))
+                .map(|&p| vec3_length(vec3_sub(p, center)))
+                .fold(0.0, Real::max);
+            BoundingSphere {
+                center: center,
+                radius: radius,
+            }
+        })
+    }
+
+    /// Returns the number of polygons for each number of polygon vertices.
+    pub fn polygon_counts_by_arity(&self) -> BTreeMap<usize, usize> {
+        let mut counts = BTreeMap::new();
+        for size in self.polygon_structure.polygon_sizes() {
+            *counts.entry(size).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Returns the number of triangles after triangulation.
+    ///
+    /// Each polygon with `n` vertices is counted as `n - 2` triangles, and polygons with less
+    /// than 3 vertices are not counted.
+    pub fn num_triangles(&self) -> usize {
+        self.polygon_structure.polygon_sizes().map(|size| size.saturating_sub(2)).sum()
+    }
+
+    /// Returns whether the given polygon is degenerate.
+    ///
+    /// A polygon is degenerate if it has less than 3 vertices, uses the same control point more
+    /// than once consecutively, refers to a control point out of range, or has zero area.
+    pub fn is_degenerate_polygon(&self, polygon: usize) -> bool {
+        let range = match self.polygon_structure.polygon_range(polygon) {
+            Some(range) => range,
+            None => return true,
+        };
+        if range.len() < 3 {
+            return true;
+        }
+        let cps = range.map(|pvi| self.control_point_of_polygon_vertex(pvi)).collect::<Vec<_>>();
+        if cps.iter().any(|&cp| cp >= self.vertices.len()) {
+            return true;
+        }
+        if (0..cps.len()).any(|i| cps[i] == cps[(i + 1) % cps.len()]) {
+            return true;
+        }
+        let positions = cps.iter().map(|&cp| self.vertices[cp]).collect::<Vec<_>>();
+        let normal = polygon_normal(positions.iter().cloned());
+        !(vec3_length(normal) > 0.0)
+    }
+
+    /// Returns the number of degenerate polygons.
+    ///
+    /// See `Mesh::is_degenerate_polygon()`.
+    pub fn num_degenerate_polygons(&self) -> usize {
+        (0..self.polygon_structure.num_polygons()).filter(|&poly| self.is_degenerate_polygon(poly)).count()
+    }
+
+    /// Returns control points which are not used by any polygon.
+    pub fn unused_control_points(&self) -> Vec<usize> {
+        let mut used = vec![false; self.vertices.len()];
+        for pvi in 0..self.num_polygon_vertices() {
+            if let Some(v) = used.get_mut(self.control_point_of_polygon_vertex(pvi)) {
+                *v = true;
+            }
+        }
+        used.iter().enumerate().filter(|&(_, &used)| !used).map(|(cp, _)| cp).collect()
+    }
+}
@@ -0,0 +1,492 @@
+//! Contains Catmull-Clark subdivision of meshes.
+
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+use std::ops::Range;
+use fnv::FnvHasher;
+use math::{vec3_add, vec3_normalize, vec3_scale, vec3_to_f32, vec3_to_real};
+use super::{Mesh, VertexIndex, MappingMode, ReferenceMode, LayerElement};
+use super::edges::edge_key;
+use Real;
+
+
+type EdgeIndexMap = HashMap<(usize, usize), usize, BuildHasherDefault<FnvHasher>>;
+
+/// Sharpness of fully creased edges and vertices (crease `1.0`).
+///
+/// This is the maximum crease level of Maya, which is also treated as infinitely sharp by
+/// OpenSubdiv.
+const MAX_SHARPNESS: f32 = 10.0;
+
+/// Converts the crease value (`0.0..1.0`) of FBX into the sharpness.
+fn crease_to_sharpness(crease: f32) -> f32 {
+    crease.max(0.0).min(1.0) * MAX_SHARPNESS
+}
+
+/// Converts the sharpness into the crease value (`0.0..1.0`) of FBX.
+fn sharpness_to_crease(sharpness: f32) -> f32 {
+    (sharpness / MAX_SHARPNESS).max(0.0).min(1.0)
+}
+
+impl Mesh {
+    /// Applies Catmull-Clark subdivision and returns the subdivided mesh.
+    ///
+    /// If `levels` is `None`, `self.render_division_levels` (or `0` if it is not loaded) is used.
+    ///
+    /// Edge creases (`ByEdge`) and vertex creases (`ByControlPoint`) are respected as
+    /// sharpness: crease values of FBX are normalized to `0.0..1.0` and they are scaled to
+    /// sharpness `0.0..10.0` (the range of Maya crease levels), an edge or a vertex with the
+    /// sharpness `s` stays sharp for `s` levels, and a fractional sharpness blends smooth and
+    /// sharp rules.
+    /// Creases of the result are the remaining sharpness converted back into `0.0..1.0`.
+    /// Boundary and non-manifold edges are always sharp.
+    ///
+    /// Normals, UVs, tangents and binormals are linearly interpolated on each polygon (face-varying
+    /// linear interpolation) and become `ByPolygonVertex` layer elements. Interpolated normals
+    /// are normalized, but regenerating normals (see `Mesh::generate_normals()`) gives smoother
+    /// results.
+    /// `ByPolygon` (and `AllSame`) materials, smoothing groups, polygon groups and visibility are
+    /// inherited by subdivided polygons, and layer elements with other mapping modes are
+    /// dropped.
+    ///
+    /// The result is not triangulated even if `self` is triangulated. Polygons with less than 3
+    /// vertices are dropped.
+    pub fn subdivide(&self, levels: Option<u32>) -> Mesh {
+        let levels = levels.unwrap_or_else(|| self.render_division_levels.unwrap_or(0).max(0) as u32);
+        let mut mesh = self.clone();
+        for _ in 0..levels {
+            mesh = subdivide_once(&mesh);
+        }
+        mesh
+    }
+}
+
+/// Values which can be interpolated.
+trait Interpolate: Copy {
+    fn average(values: &[Self]) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn average(values: &[f32]) -> f32 {
+        values.iter().fold(0.0, |sum, &v| sum + v) / values.len() as f32
+    }
+}
+
+macro_rules! impl_interpolate_for_array {
+    ($len:expr) => {
+        impl Interpolate for [f32; $len] {
+            fn average(values: &[[f32; $len]]) -> [f32; $len] {
+                let mut sum = [0.0; $len];
+                for v in values {
+                    for i in 0..$len {
+                        sum[i] += v[i];
+                    }
+                }
+                for s in &mut sum {
+                    *s /= values.len() as f32;
+                }
+                sum
+            }
+        }
+    }
+}
+impl_interpolate_for_array!(2);
+impl_interpolate_for_array!(3);
+impl_interpolate_for_array!(4);
+
+/// Topology of the source mesh for a subdivision step.
+struct Topology {
+    /// Ranges of polygon vertices of polygons with 3 or more vertices.
+    polygons: Vec<Range<usize>>,
+    /// Source polygon index of each polygon in `polygons`.
+    polygon_indices: Vec<usize>,
+    /// Control points of each polygon vertex.
+    control_points: Vec<usize>,
+    /// Edge (index of `edge_ends`) from each polygon vertex to the next one.
+    pv_edges: Vec<usize>,
+    /// Control points at both ends of each edge.
+    edge_ends: Vec<(usize, usize)>,
+    /// Polygons (indices of `polygons`) around each edge.
+    edge_faces: Vec<Vec<usize>>,
+    /// Sharpness of each edge (infinity for boundary and non-manifold edges).
+    edge_sharpness: Vec<f32>,
+}
+
+impl Topology {
+    fn new(mesh: &Mesh) -> Self {
+        let num_pv = mesh.num_polygon_vertices();
+        let control_points = (0..num_pv).map(|pvi| mesh.control_point_of_polygon_vertex(pvi)).collect::<Vec<_>>();
+        let mut polygons = Vec::new();
+        let mut polygon_indices = Vec::new();
+        for poly in 0..mesh.polygon_structure.num_polygons() {
+            let range = mesh.polygon_structure.polygon_range(poly).unwrap();
+            if range.len() < 3 || range.clone().any(|pvi| control_points[pvi] >= mesh.vertices.len()) {
+                warn!("Polygon {} is dropped by subdivision (mesh id={})", poly, mesh.id);
+                continue;
+            }
+            polygons.push(range);
+            polygon_indices.push(poly);
+        }
+
+        let mut edge_map: EdgeIndexMap = Default::default();
+        let mut edge_ends = Vec::new();
+        let mut edge_faces: Vec<Vec<usize>> = Vec::new();
+        let mut pv_edges = vec![::std::usize::MAX; num_pv];
+        for (face, range) in polygons.iter().enumerate() {
+            for pvi in range.clone() {
+                let next = if pvi + 1 == range.end { range.start } else { pvi + 1 };
+                let (cp1, cp2) = (control_points[pvi], control_points[next]);
+                let edge = *edge_map.entry(edge_key(cp1, cp2)).or_insert_with(|| {
+                    edge_ends.push((cp1, cp2));
+                    edge_faces.push(Vec::new());
+                    edge_ends.len() - 1
+                });
+                edge_faces[edge].push(face);
+                pv_edges[pvi] = edge;
+            }
+        }
+
+        // Sharpness from edge creases.
+        let mut edge_sharpness = edge_faces.iter().map(|faces| if faces.len() == 2 { 0.0 } else { ::std::f32::INFINITY }).collect::<Vec<f32>>();
+        if let Some(creases) = mesh.layer_element_edge_creases.iter().find(|le| le.has_data()) {
+            if creases.mapping_mode == MappingMode::ByEdge {
+                for src_edge in 0..mesh.edges.len() {
+                    if let (Some((cp1, cp2)), Some(crease)) = (mesh.edge_control_points(src_edge), creases.mapped_element(src_edge)) {
+                        if let Some(&edge) = edge_map.get(&edge_key(cp1, cp2)) {
+                            let sharpness = crease_to_sharpness(crease);
+                            if sharpness > edge_sharpness[edge] {
+                                edge_sharpness[edge] = sharpness;
+                            }
+                        }
+                    }
+                }
+            } else {
+                warn!("Unsupported mapping mode for `LayerElementEdgeCrease`: `{:?}`", creases.mapping_mode);
+            }
+        }
+
+        Topology {
+            polygons: polygons,
+            polygon_indices: polygon_indices,
+            control_points: control_points,
+            pv_edges: pv_edges,
+            edge_ends: edge_ends,
+            edge_faces: edge_faces,
+            edge_sharpness: edge_sharpness,
+        }
+    }
+}
+
+/// Returns sharpness of each control point from vertex creases.
+fn vertex_sharpness(mesh: &Mesh) -> Vec<f32> {
+    let mut sharpness = vec![0.0; mesh.vertices.len()];
+    if let Some(creases) = mesh.layer_element_vertex_creases.iter().find(|le| le.has_data()) {
+        if creases.mapping_mode == MappingMode::ByControlPoint {
+            for (cp, s) in sharpness.iter_mut().enumerate() {
+                *s = creases.mapped_element(cp).map_or(0.0, crease_to_sharpness);
+            }
+        } else {
+            warn!("Unsupported mapping mode for `LayerElementVertexCrease`: `{:?}`", creases.mapping_mode);
+        }
+    }
+    sharpness
+}
+
+fn lerp(v1: [Real; 3], v2: [Real; 3], t: f32) -> [Real; 3] {
+    let t = t.max(0.0).min(1.0) as Real;
+    vec3_add(vec3_scale(v1, 1.0 - t), vec3_scale(v2, t))
+}
+
+fn average_points<I: IntoIterator<Item = [Real; 3]>>(points: I) -> [Real; 3] {
+    let (sum, count) = points.into_iter().fold(([0.0; 3], 0), |(sum, count), p| (vec3_add(sum, p), count + 1));
+    if count == 0 {
+        sum
+    } else {
+        vec3_scale(sum, 1.0 / count as Real)
+    }
+}
+
+/// Applies one level of Catmull-Clark subdivision.
+fn subdivide_once(mesh: &Mesh) -> Mesh {
+    let topo = Topology::new(mesh);
+    let src_vertex_sharpness = vertex_sharpness(mesh);
+    let num_cp = mesh.vertices.len();
+    let num_edges = topo.edge_ends.len();
+
+    // Face points.
+    let face_points = topo.polygons.iter().map(|range| average_points(range.clone().map(|pvi| mesh.vertices[topo.control_points[pvi]]))).collect::<Vec<_>>();
+
+    // Edge points.
+    let edge_points = (0..num_edges).map(|edge| {
+        let (cp1, cp2) = topo.edge_ends[edge];
+        let midpoint = average_points(vec![mesh.vertices[cp1], mesh.vertices[cp2]]);
+        let faces = &topo.edge_faces[edge];
+        if faces.len() != 2 {
+            return midpoint;
+        }
+        let smooth = average_points(vec![mesh.vertices[cp1], mesh.vertices[cp2], face_points[faces[0]], face_points[faces[1]]]);
+        lerp(smooth, midpoint, topo.edge_sharpness[edge])
+    }).collect::<Vec<_>>();
+
+    // Vertex points.
+    let mut cp_edges = vec![Vec::new(); num_cp];
+    for (edge, &(cp1, cp2)) in topo.edge_ends.iter().enumerate() {
+        cp_edges[cp1].push(edge);
+        if cp2 != cp1 {
+            cp_edges[cp2].push(edge);
+        }
+    }
+    let mut cp_faces = vec![Vec::new(); num_cp];
+    for (face, range) in topo.polygons.iter().enumerate() {
+        for pvi in range.clone() {
+            cp_faces[topo.control_points[pvi]].push(face);
+        }
+    }
+    let vertex_points = (0..num_cp).map(|cp| {
+        let p = mesh.vertices[cp];
+        let edges = &cp_edges[cp];
+        let faces = &cp_faces[cp];
+        if edges.is_empty() || faces.is_empty() {
+            return p;
+        }
+        let n = edges.len() as Real;
+        let face_avg = average_points(faces.iter().map(|&f| face_points[f]));
+        let edge_mid_avg = average_points(edges.iter().map(|&e| {
+            let (cp1, cp2) = topo.edge_ends[e];
+            average_points(vec![mesh.vertices[cp1], mesh.vertices[cp2]])
+        }));
+        let smooth = vec3_scale(vec3_add(vec3_add(face_avg, vec3_scale(edge_mid_avg, 2.0)), vec3_scale(p, n - 3.0)), 1.0 / n);
+        let sharp_edges = edges.iter().cloned().filter(|&e| topo.edge_sharpness[e] > 0.0).collect::<Vec<_>>();
+        let edge_sharpness = if sharp_edges.is_empty() {
+            0.0
+        } else {
+            sharp_edges.iter().map(|&e| topo.edge_sharpness[e].min(1.0)).sum::<f32>() / sharp_edges.len() as f32
+        };
+        let result = match sharp_edges.len() {
+            0 | 1 => smooth,
+            2 => {
+                let other = |e: usize| {
+                    let (cp1, cp2) = topo.edge_ends[e];
+                    mesh.vertices[if cp1 == cp && cp2 != cp { cp2 } else { cp1 }]
+                };
+                let crease = vec3_scale(vec3_add(vec3_scale(p, 6.0), vec3_add(other(sharp_edges[0]), other(sharp_edges[1]))), 1.0 / 8.0);
+                lerp(smooth, crease, edge_sharpness)
+            },
+            _ => lerp(smooth, p, edge_sharpness),
+        };
+        lerp(result, p, src_vertex_sharpness[cp])
+    }).collect::<Vec<_>>();
+
+    // New control points: vertex points, edge points, and face points.
+    let edge_point_base = num_cp;
+    let face_point_base = num_cp + num_edges;
+    let mut vertices = vertex_points;
+    vertices.extend(edge_points);
+    vertices.extend(face_points);
+
+    // New polygons: a quad for each corner of source polygons.
+    // Corner `i` of the quad is (vertex point, edge point to the next corner, face point, edge
+    // point from the previous corner).
+    let mut polygon_vertex_index = Vec::new();
+    // Source polygon vertex (of the corner) for each new polygon vertex, and the kind of the
+    // new polygon vertex.
+    let mut new_pv_sources = Vec::new();
+    let mut new_poly_to_src_poly = Vec::new();
+    let mut new_edges: Vec<u32> = Vec::new();
+    let mut new_edge_map: EdgeIndexMap = Default::default();
+    let mut new_edge_sharpness = Vec::new();
+    for (face, range) in topo.polygons.iter().enumerate() {
+        for pvi in range.clone() {
+            let prev = if pvi == range.start { range.end - 1 } else { pvi - 1 };
+            let quad = [
+                topo.control_points[pvi],
+                edge_point_base + topo.pv_edges[pvi],
+                face_point_base + face,
+                edge_point_base + topo.pv_edges[prev],
+            ];
+            // Sharpness of edges of the quad.
+            let child_sharpness = [
+                (topo.edge_sharpness[topo.pv_edges[pvi]] - 1.0).max(0.0),
+                0.0,
+                0.0,
+                (topo.edge_sharpness[topo.pv_edges[prev]] - 1.0).max(0.0),
+            ];
+            let quad_start = polygon_vertex_index.len();
+            for i in 0..4 {
+                polygon_vertex_index.push(if i == 3 { !(quad[i] as i32) } else { quad[i] as i32 });
+                new_pv_sources.push((face, pvi, prev, i));
+                let key = edge_key(quad[i], quad[(i + 1) % 4]);
+                if !new_edge_map.contains_key(&key) {
+                    new_edge_map.insert(key, new_edges.len());
+                    new_edges.push((quad_start + i) as u32);
+                    // Boundary and non-manifold edges are sharp without creases.
+                    new_edge_sharpness.push(if child_sharpness[i].is_finite() { child_sharpness[i] } else { 0.0 });
+                }
+            }
+            new_poly_to_src_poly.push(topo.polygon_indices[face]);
+        }
+    }
+
+    let mut result = Mesh {
+        id: mesh.id,
+        name: mesh.name.clone(),
+        vertices: vertices,
+        polygon_vertex_index: VertexIndex::NotTriangulated(polygon_vertex_index),
+        polygon_structure: Default::default(),
+        edges: new_edges,
+        polygon_vertex_edges: Vec::new(),
+        layer_element_materials: inherit_by_polygon(&mesh.layer_element_materials, &new_poly_to_src_poly, "LayerElementMaterial"),
+        layer_element_normals: interpolate_face_varying(mesh, &mesh.layer_element_normals, &topo, &new_pv_sources).into_iter().map(|mut le| {
+            if let Some(ref mut data) = le.data {
+                for n in data.iter_mut() {
+                    *n = vec3_to_f32(vec3_normalize(vec3_to_real(*n)));
+                }
+            }
+            le
+        }).collect(),
+        layer_element_uvs: interpolate_face_varying(mesh, &mesh.layer_element_uvs, &topo, &new_pv_sources),
+        layer_element_tangents: interpolate_face_varying(mesh, &mesh.layer_element_tangents, &topo, &new_pv_sources),
+        layer_element_binormals: interpolate_face_varying(mesh, &mesh.layer_element_binormals, &topo, &new_pv_sources),
+        layer_element_smoothing: inherit_by_polygon(&mesh.layer_element_smoothing, &new_poly_to_src_poly, "LayerElementSmoothing"),
+        layer_element_polygon_groups: inherit_by_polygon(&mesh.layer_element_polygon_groups, &new_poly_to_src_poly, "LayerElementPolygonGroup"),
+        layer_element_visibility: inherit_by_polygon(&mesh.layer_element_visibility, &new_poly_to_src_poly, "LayerElementVisibility"),
+        layer_element_edge_creases: mesh.layer_element_edge_creases.iter().find(|le| le.has_data()).map(|le| LayerElement {
+            channel: le.channel,
+            name: le.name.clone(),
+            mapping_mode: MappingMode::ByEdge,
+            reference_mode: ReferenceMode::Direct,
+            data: Some(new_edge_sharpness.iter().map(|&s| sharpness_to_crease(s)).collect()),
+        }).into_iter().collect(),
+        layer_element_vertex_creases: mesh.layer_element_vertex_creases.iter().find(|le| le.has_data()).map(|le| LayerElement {
+            channel: le.channel,
+            name: le.name.clone(),
+            mapping_mode: MappingMode::ByControlPoint,
+            reference_mode: ReferenceMode::Direct,
+            data: Some(src_vertex_sharpness.iter().map(|&s| sharpness_to_crease(s - 1.0)).chain(::std::iter::repeat(0.0).take(num_edges + topo.polygons.len())).collect()),
+        }).into_iter().collect(),
+        layers: mesh.layers.clone(),
+        smoothness: mesh.smoothness,
+        preview_division_levels: mesh.preview_division_levels,
+        render_division_levels: mesh.render_division_levels,
+    };
+    result.rebuild_polygon_structure();
+    result.rebuild_polygon_vertex_edges();
+    result
+}
+
+/// Interpolates face-varying values of layer elements for new polygon vertices.
+///
+/// `new_pv_sources` is the list of (face, source polygon vertex of the corner, previous
+/// source polygon vertex, corner kind of the new polygon vertex) of each new polygon vertex.
+fn interpolate_face_varying<T: Interpolate>(mesh: &Mesh, layer_elements: &[LayerElement<T>], topo: &Topology, new_pv_sources: &[(usize, usize, usize, usize)]) -> Vec<LayerElement<T>> {
+    layer_elements.iter().filter_map(|le| {
+        if !le.has_data() {
+            return None;
+        }
+        let corner_values = match topo.polygons.iter().flat_map(|range| range.clone()).map(|pvi| le.try_element_of_polygon_vertex(mesh, pvi).map(|v| (pvi, v))).collect::<::error::MeshResult<HashMap<_, _>>>() {
+            Ok(v) => v,
+            Err(err) => {
+                warn!("Layer element is dropped by subdivision: {}", err);
+                return None;
+            },
+        };
+        let face_values = topo.polygons.iter().map(|range| T::average(&range.clone().map(|pvi| corner_values[&pvi]).collect::<Vec<_>>())).collect::<Vec<_>>();
+        let data = new_pv_sources.iter().map(|&(face, pvi, prev, kind)| {
+            let range = &topo.polygons[face];
+            let next = if pvi + 1 == range.end { range.start } else { pvi + 1 };
+            match kind {
+                0 => corner_values[&pvi],
+                1 => T::average(&[corner_values[&pvi], corner_values[&next]]),
+                2 => face_values[face],
+                _ => T::average(&[corner_values[&prev], corner_values[&pvi]]),
+            }
+        }).collect();
+        Some(LayerElement {
+            channel: le.channel,
+            name: le.name.clone(),
+            mapping_mode: MappingMode::ByPolygonVertex,
+            reference_mode: ReferenceMode::Direct,
+            data: Some(data),
+        })
+    }).collect()
+}
+
+/// Copies `ByPolygon` and `AllSame` layer elements for new polygons.
+fn inherit_by_polygon<T: Copy>(layer_elements: &[LayerElement<T>], new_poly_to_src_poly: &[usize], type_name: &str) -> Vec<LayerElement<T>> {
+    layer_elements.iter().filter_map(|le| {
+        match le.mapping_mode {
+            MappingMode::AllSame => Some(le.clone()),
+            MappingMode::ByPolygon => {
+                let (data, reference_mode) = match le.reference_mode {
+                    ReferenceMode::Direct => (le.data.as_ref().map(|data| new_poly_to_src_poly.iter().filter_map(|&poly| data.get(poly).cloned()).collect()), ReferenceMode::Direct),
+                    ReferenceMode::IndexToDirect(ref indices) => (le.data.clone(), ReferenceMode::IndexToDirect(new_poly_to_src_poly.iter().filter_map(|&poly| indices.get(poly).cloned()).collect())),
+                };
+                Some(LayerElement {
+                    channel: le.channel,
+                    name: le.name.clone(),
+                    mapping_mode: MappingMode::ByPolygon,
+                    reference_mode: reference_mode,
+                    data: data,
+                })
+            },
+            mode => {
+                warn!("`{}` with mapping mode `{:?}` is dropped by subdivision", type_name, mode);
+                None
+            },
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use objects::geometry::mesh::{Mesh, MappingMode, ReferenceMode, LayerElement, VertexIndex};
+
+    /// Returns a unit cube with all edges, and the crease value on the first edge.
+    fn cube(crease: f32) -> Mesh {
+        let vertices = vec![
+            [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0], [1.0, 0.0, 1.0], [1.0, 1.0, 1.0], [0.0, 1.0, 1.0],
+        ];
+        let faces = [[0, 3, 2, 1], [4, 5, 6, 7], [0, 1, 5, 4], [1, 2, 6, 5], [2, 3, 7, 6], [3, 0, 4, 7]];
+        let mut polygon_vertex_index = Vec::new();
+        let mut edges = Vec::new();
+        let mut keys = Vec::new();
+        for face in &faces {
+            for i in 0..4 {
+                let key = (face[i].min(face[(i + 1) % 4]), face[i].max(face[(i + 1) % 4]));
+                if !keys.contains(&key) {
+                    keys.push(key);
+                    edges.push(polygon_vertex_index.len() as u32);
+                }
+                polygon_vertex_index.push(if i == 3 { !face[i] } else { face[i] });
+            }
+        }
+        let mut mesh = Mesh::new(0, String::new(), vertices, VertexIndex::NotTriangulated(polygon_vertex_index), edges);
+        let mut creases = vec![0.0; mesh.edges.len()];
+        creases[0] = crease;
+        mesh.layer_element_edge_creases.push(LayerElement {
+            channel: 0,
+            name: String::new(),
+            mapping_mode: MappingMode::ByEdge,
+            reference_mode: ReferenceMode::Direct,
+            data: Some(creases),
+        });
+        mesh
+    }
+
+    #[test]
+    fn subdivide_cube() {
+        let mesh = cube(0.0).subdivide(Some(1));
+        assert_eq!(mesh.vertices.len(), 26);
+        assert_eq!(mesh.num_polygons(), 24);
+        assert!(mesh.polygons().all(|polygon| polygon.polygon_vertices().len() == 4));
+    }
+
+    #[test]
+    fn creased_edge_stays_on_line() {
+        // The first edge is from the control point 0 to 3.
+        let midpoint = [0.0, 0.5, 0.0];
+        assert!(!cube(0.0).subdivide(Some(1)).vertices.contains(&midpoint));
+        assert!(cube(1.0).subdivide(Some(1)).vertices.contains(&midpoint));
+    }
+}
@@ -0,0 +1,580 @@
+//! Contains tangent generation for meshes.
+//!
+//! This is a port of MikkTSpace (`mikktspace.c` by Morten S. Mikkelsen), which is the de facto
+//! standard tangent space of normal map bakers.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+use fnv::FnvHasher;
+use error::{MeshErrorKind, MeshResult};
+use math::{vec3_add, vec3_cross, vec3_dot, vec3_length, vec3_scale, vec3_sub, vec3_to_real};
+use super::{Mesh, MappingMode, ReferenceMode, LayerElement};
+use Real;
+
+
+/// Cosine of the angular threshold of MikkTSpace (180 degrees by default).
+const THRESHOLD_COS: Real = -1.0;
+
+impl Mesh {
+    /// Computes tangents and binormals of the mesh from the given UV and normal layer elements.
+    ///
+    /// The computation is MikkTSpace: polygon vertices with the same position, normal and UV are
+    /// welded, quads are split along the shorter diagonal in UV space, triangles are grouped
+    /// around each vertex by connectivity and handedness, and tangents of the groups are
+    /// weighted by corner angles.
+    /// Polygons with more than four vertices are not accepted by MikkTSpace, and they are split
+    /// into triangle fans here; triangulate them beforehand as the baker does for exact results.
+    ///
+    /// The handedness sign is stored in `w` of tangents.
+    /// Binormals are `cross(normal, tangent) * sign` and their `w` is always `1.0`.
+    ///
+    /// The results are new layer elements with `ByPolygonVertex` mapping and `Direct` reference,
+    /// and they are not added to `self.layer_element_tangents` nor `self.layer_element_binormals`.
+    /// Returns an error if `uvs` or `normals` has no data or is inconsistent with the mesh, or a
+    /// polygon vertex refers to a nonexistent control point.
+    pub fn generate_tangents(&self, uvs: &LayerElement<[f32; 2]>, normals: &LayerElement<[f32; 3]>) -> MeshResult<(LayerElement<[f32; 4]>, LayerElement<[f32; 4]>)> {
+        if !uvs.has_data() {
+            return Err(self.layer_element_error(uvs, MeshErrorKind::NoData));
+        }
+        if !normals.has_data() {
+            return Err(self.layer_element_error(normals, MeshErrorKind::NoData));
+        }
+        let num_pv = self.num_polygon_vertices();
+        let pv_normals = try!((0..num_pv).map(|pvi| normals.try_element_of_polygon_vertex(self, pvi).map(|n| normalize_if_not_zero(vec3_to_real(n)))).collect::<MeshResult<Vec<_>>>());
+        let positions = try!((0..num_pv).map(|pvi| self.try_position_of_polygon_vertex(pvi)).collect::<MeshResult<Vec<_>>>());
+        let pv_uvs = try!((0..num_pv).map(|pvi| uvs.try_element_of_polygon_vertex(self, pvi).map(|uv| [uv[0] as Real, uv[1] as Real])).collect::<MeshResult<Vec<_>>>());
+
+        // Weld polygon vertices with the same position, normal and UV.
+        // Each polygon vertex is represented by the first polygon vertex with the same values.
+        let welded = {
+            let mut representatives: HashMap<_, usize, BuildHasherDefault<FnvHasher>> = Default::default();
+            (0..num_pv).map(|pvi| {
+                let (p, n, uv) = (positions[pvi], pv_normals[pvi], pv_uvs[pvi]);
+                let mut key = [0; 8];
+                for (bits, &v) in key.iter_mut().zip(&[p[0], p[1], p[2], n[0], n[1], n[2], uv[0], uv[1]]) {
+                    *bits = exact_bits(v as f64);
+                }
+                *representatives.entry(key).or_insert(pvi)
+            }).collect::<Vec<_>>()
+        };
+
+        let mut tris = Vec::new();
+        for range in self.polygon_vertex_ranges() {
+            let s = range.start;
+            match range.end - s {
+                0 | 1 | 2 => {},
+                4 => {
+                    let uv_dist_sq = |a: usize, b: usize| {
+                        let d = [pv_uvs[b][0] - pv_uvs[a][0], pv_uvs[b][1] - pv_uvs[a][1]];
+                        d[0] * d[0] + d[1] * d[1]
+                    };
+                    let pos_dist_sq = |a: usize, b: usize| {
+                        let d = vec3_sub(positions[b], positions[a]);
+                        vec3_dot(d, d)
+                    };
+                    let diagonal_is_02 = match uv_dist_sq(s, s + 2).partial_cmp(&uv_dist_sq(s + 1, s + 3)) {
+                        Some(Ordering::Less) => true,
+                        Some(Ordering::Greater) => false,
+                        _ => !(pos_dist_sq(s + 1, s + 3) < pos_dist_sq(s, s + 2)),
+                    };
+                    let corners = if diagonal_is_02 {
+                        [[s, s + 1, s + 2], [s, s + 2, s + 3]]
+                    } else {
+                        [[s, s + 1, s + 3], [s + 1, s + 2, s + 3]]
+                    };
+                    tris.push(TriInfo::new(corners[0], &welded, s, true));
+                    tris.push(TriInfo::new(corners[1], &welded, s, true));
+                },
+                _ => {
+                    for second in s + 1..range.end - 1 {
+                        tris.push(TriInfo::new([s, second, second + 1], &welded, s, false));
+                    }
+                },
+            }
+        }
+
+        // Mark degenerate triangles and quads with only one good triangle.
+        for tri in &mut tris {
+            let p = [positions[tri.corners[0]], positions[tri.corners[1]], positions[tri.corners[2]]];
+            tri.degenerate = p[0] == p[1] || p[0] == p[2] || p[1] == p[2];
+        }
+        let mut t = 0;
+        while t + 1 < tris.len() {
+            if tris[t].quad && tris[t].polygon == tris[t + 1].polygon {
+                if tris[t].degenerate != tris[t + 1].degenerate {
+                    tris[t].quad_one_degenerate = true;
+                    tris[t + 1].quad_one_degenerate = true;
+                }
+                t += 2;
+            } else {
+                t += 1;
+            }
+        }
+        // Move degenerate triangles to the end, in the same order as MikkTSpace does.
+        let num_good = tris.iter().filter(|tri| !tri.degenerate).count();
+        let mut next_good = 1;
+        for t in 0..num_good {
+            if !tris[t].degenerate {
+                next_good = ::std::cmp::max(next_good, t + 2);
+                continue;
+            }
+            while next_good < tris.len() && tris[next_good].degenerate {
+                next_good += 1;
+            }
+            if next_good >= tris.len() {
+                break;
+            }
+            tris.swap(t, next_good);
+            next_good += 1;
+        }
+
+        // Initialize tangents of triangles.
+        for tri in &mut tris[..num_good] {
+            tri.init_tangent(&positions, &pv_uvs);
+        }
+        // Force healthy quads to a fixed orientation.
+        let mut t = 0;
+        while t + 1 < num_good {
+            if tris[t].quad && tris[t].polygon == tris[t + 1].polygon {
+                if tris[t].orient_preserving != tris[t + 1].orient_preserving {
+                    let (src, dst) = if tris[t + 1].group_with_any || tris[t].tex_area >= tris[t + 1].tex_area {
+                        (t, t + 1)
+                    } else {
+                        (t + 1, t)
+                    };
+                    tris[dst].orient_preserving = tris[src].orient_preserving;
+                }
+                t += 2;
+            } else {
+                t += 1;
+            }
+        }
+
+        build_neighbors(&mut tris[..num_good]);
+        let groups = build_groups(&mut tris[..num_good]);
+
+        // Compute tangent spaces of polygon vertices from subgroups of each group.
+        let mut tspaces = vec![TSpace::default(); num_pv];
+        for group in &groups {
+            let normal = pv_normals[group.vertex];
+            let mut subgroups: Vec<(Vec<usize>, TSpace)> = Vec::new();
+            for &f in &group.tris {
+                let tri = &tris[f];
+                let os = normalize_if_not_zero(project(tri.os, normal));
+                let ot = normalize_if_not_zero(project(tri.ot, normal));
+                let mut members = group.tris.iter().cloned().filter(|&t| {
+                    let other = &tris[t];
+                    let os2 = normalize_if_not_zero(project(other.os, normal));
+                    let ot2 = normalize_if_not_zero(project(other.ot, normal));
+                    tri.group_with_any || other.group_with_any || tri.polygon == other.polygon
+                        || (vec3_dot(os, os2) > THRESHOLD_COS && vec3_dot(ot, ot2) > THRESHOLD_COS)
+                }).collect::<Vec<_>>();
+                members.sort();
+                let subgroup = match subgroups.iter().position(|&(ref m, _)| *m == members) {
+                    Some(v) => v,
+                    None => {
+                        let tspace = eval_tspace(&tris, &members, group.vertex, normal, &positions);
+                        subgroups.push((members, tspace));
+                        subgroups.len() - 1
+                    },
+                };
+                let tspace = subgroups[subgroup].1;
+                let out = &mut tspaces[tri.corners[tri.corner_of_vertex(group.vertex)]];
+                if out.counter == 1 {
+                    *out = average_tspace(out, &tspace);
+                    out.counter = 2;
+                } else {
+                    *out = tspace;
+                    out.counter = 1;
+                }
+                out.orient_preserving = group.orient_preserving;
+            }
+        }
+
+        // Degenerate triangles get tangent spaces of the same welded vertices in good triangles.
+        let mut good_corners: HashMap<usize, usize, BuildHasherDefault<FnvHasher>> = Default::default();
+        for tri in &tris[..num_good] {
+            for i in 0..3 {
+                good_corners.entry(tri.verts[i]).or_insert(tri.corners[i]);
+            }
+        }
+        for tri in tris[num_good..].iter().filter(|tri| !tri.quad_one_degenerate) {
+            for i in 0..3 {
+                if let Some(&src) = good_corners.get(&tri.verts[i]) {
+                    tspaces[tri.corners[i]] = tspaces[src];
+                }
+            }
+        }
+        // The missing corner of a quad with only one good triangle gets the tangent space of the
+        // corner at the same position.
+        for tri in tris[..num_good].iter().filter(|tri| tri.quad_one_degenerate) {
+            if let Some(missing) = (tri.polygon..tri.polygon + 4).find(|c| !tri.corners.contains(c)) {
+                if let Some(&src) = tri.corners.iter().find(|&&c| positions[c] == positions[missing]) {
+                    tspaces[missing] = tspaces[src];
+                }
+            }
+        }
+
+        let mut tangents = Vec::with_capacity(num_pv);
+        let mut binormals = Vec::with_capacity(num_pv);
+        for (tspace, &normal) in tspaces.iter().zip(&pv_normals) {
+            let sign = if tspace.orient_preserving { 1.0 } else { -1.0 };
+            let tangent = tspace.os;
+            let binormal = vec3_scale(vec3_cross(normal, tangent), sign);
+            tangents.push([tangent[0] as f32, tangent[1] as f32, tangent[2] as f32, sign as f32]);
+            binormals.push([binormal[0] as f32, binormal[1] as f32, binormal[2] as f32, 1.0]);
+        }
+
+        let tangents = LayerElement {
+            channel: self.layer_element_tangents.len() as i32,
+            name: uvs.name.clone(),
+            mapping_mode: MappingMode::ByPolygonVertex,
+            reference_mode: ReferenceMode::Direct,
+            data: Some(tangents),
+        };
+        let binormals = LayerElement {
+            channel: self.layer_element_binormals.len() as i32,
+            name: uvs.name.clone(),
+            mapping_mode: MappingMode::ByPolygonVertex,
+            reference_mode: ReferenceMode::Direct,
+            data: Some(binormals),
+        };
+        Ok((tangents, binormals))
+    }
+}
+
+/// Triangle of a polygon.
+#[derive(Debug, Clone)]
+struct TriInfo {
+    /// Polygon vertices of corners.
+    corners: [usize; 3],
+    /// Welded vertices (representative polygon vertices) of corners.
+    verts: [usize; 3],
+    /// The first polygon vertex of the source polygon.
+    polygon: usize,
+    /// Whether the source polygon is a quad.
+    quad: bool,
+    degenerate: bool,
+    quad_one_degenerate: bool,
+    /// Whether the triangle has no valid tangent and can join any group.
+    group_with_any: bool,
+    orient_preserving: bool,
+    /// Neighbor triangle of each edge (from the corner to the next corner).
+    neighbors: [Option<usize>; 3],
+    /// Group of each corner.
+    groups: [Option<usize>; 3],
+    os: [Real; 3],
+    ot: [Real; 3],
+    mag_s: Real,
+    mag_t: Real,
+    /// Area of the triangle in UV space.
+    tex_area: Real,
+}
+
+impl TriInfo {
+    fn new(corners: [usize; 3], welded: &[usize], polygon: usize, quad: bool) -> Self {
+        TriInfo {
+            corners: corners,
+            verts: [welded[corners[0]], welded[corners[1]], welded[corners[2]]],
+            polygon: polygon,
+            quad: quad,
+            degenerate: false,
+            quad_one_degenerate: false,
+            group_with_any: true,
+            orient_preserving: false,
+            neighbors: [None; 3],
+            groups: [None; 3],
+            os: [0.0; 3],
+            ot: [0.0; 3],
+            mag_s: 0.0,
+            mag_t: 0.0,
+            tex_area: 0.0,
+        }
+    }
+
+    /// Computes the tangent, bitangent, their magnitudes and the orientation of the triangle.
+    fn init_tangent(&mut self, positions: &[[Real; 3]], uvs: &[[Real; 2]]) {
+        let p = [positions[self.corners[0]], positions[self.corners[1]], positions[self.corners[2]]];
+        let t = [uvs[self.corners[0]], uvs[self.corners[1]], uvs[self.corners[2]]];
+        let (t21x, t21y) = (t[1][0] - t[0][0], t[1][1] - t[0][1]);
+        let (t31x, t31y) = (t[2][0] - t[0][0], t[2][1] - t[0][1]);
+        let d1 = vec3_sub(p[1], p[0]);
+        let d2 = vec3_sub(p[2], p[0]);
+        let signed_area = t21x * t31y - t21y * t31x;
+        let os = vec3_sub(vec3_scale(d1, t31y), vec3_scale(d2, t21y));
+        let ot = vec3_add(vec3_scale(d1, -t31x), vec3_scale(d2, t21x));
+        self.orient_preserving = signed_area > 0.0;
+        self.tex_area = signed_area.abs() * 0.5;
+        if not_zero(signed_area) {
+            let abs_area = signed_area.abs();
+            let len_os = vec3_length(os);
+            let len_ot = vec3_length(ot);
+            let sign = if self.orient_preserving { 1.0 } else { -1.0 };
+            self.os = if not_zero(len_os) { vec3_scale(os, sign / len_os) } else { os };
+            self.ot = if not_zero(len_ot) { vec3_scale(ot, sign / len_ot) } else { ot };
+            self.mag_s = len_os / abs_area;
+            self.mag_t = len_ot / abs_area;
+            if not_zero(self.mag_s) && not_zero(self.mag_t) {
+                self.group_with_any = false;
+            }
+        }
+    }
+
+    /// Returns the corner of the given welded vertex.
+    ///
+    /// # Panics
+    /// Panics if the triangle does not have the vertex.
+    fn corner_of_vertex(&self, vertex: usize) -> usize {
+        self.verts.iter().position(|&v| v == vertex).expect("Vertex should be in the triangle")
+    }
+}
+
+/// Tangent space of a polygon vertex.
+#[derive(Debug, Clone, Copy)]
+struct TSpace {
+    os: [Real; 3],
+    mag_s: Real,
+    ot: [Real; 3],
+    mag_t: Real,
+    /// Number of groups contributed to the tangent space.
+    counter: u8,
+    orient_preserving: bool,
+}
+
+impl Default for TSpace {
+    fn default() -> Self {
+        TSpace {
+            os: [1.0, 0.0, 0.0],
+            mag_s: 1.0,
+            ot: [0.0, 1.0, 0.0],
+            mag_t: 1.0,
+            counter: 0,
+            orient_preserving: true,
+        }
+    }
+}
+
+/// Triangles sharing a welded vertex, connected by edges and with the same orientation.
+#[derive(Debug, Clone)]
+struct Group {
+    vertex: usize,
+    orient_preserving: bool,
+    tris: Vec<usize>,
+}
+
+/// Finds neighbor triangles sharing edges with the opposite direction.
+fn build_neighbors(tris: &mut [TriInfo]) {
+    let mut edges = Vec::with_capacity(tris.len() * 3);
+    for (f, tri) in tris.iter().enumerate() {
+        for i in 0..3 {
+            let (v0, v1) = (tri.verts[i], tri.verts[(i + 1) % 3]);
+            edges.push((::std::cmp::min(v0, v1), ::std::cmp::max(v0, v1), f));
+        }
+    }
+    edges.sort();
+    for i in 0..edges.len() {
+        let (v0, v1, f) = edges[i];
+        let (a0, a1, edge_a) = triangle_edge(&tris[f].verts, v0, v1);
+        if tris[f].neighbors[edge_a].is_some() {
+            continue;
+        }
+        for &(w0, w1, t) in edges[i + 1..].iter().take_while(|&&(w0, w1, _)| (w0, w1) == (v0, v1)) {
+            let (b1, b0, edge_b) = triangle_edge(&tris[t].verts, w0, w1);
+            if a0 == b0 && a1 == b1 && tris[t].neighbors[edge_b].is_none() {
+                tris[f].neighbors[edge_a] = Some(t);
+                tris[t].neighbors[edge_b] = Some(f);
+                break;
+            }
+        }
+    }
+}
+
+/// Returns the directed edge of the triangle with the given (unordered) vertices and its index.
+fn triangle_edge(verts: &[usize; 3], v0: usize, v1: usize) -> (usize, usize, usize) {
+    if verts[0] == v0 || verts[0] == v1 {
+        if verts[1] == v0 || verts[1] == v1 {
+            (verts[0], verts[1], 0)
+        } else {
+            (verts[2], verts[0], 2)
+        }
+    } else {
+        (verts[1], verts[2], 1)
+    }
+}
+
+/// Groups corners of triangles.
+///
+/// Each group is seeded by a corner of a triangle with valid tangent, and grows through neighbor
+/// triangles sharing the vertex with the same orientation.
+fn build_groups(tris: &mut [TriInfo]) -> Vec<Group> {
+    let mut groups = Vec::new();
+    for f in 0..tris.len() {
+        for i in 0..3 {
+            if tris[f].group_with_any || tris[f].groups[i].is_some() {
+                continue;
+            }
+            let index = groups.len();
+            let mut group = Group {
+                vertex: tris[f].verts[i],
+                orient_preserving: tris[f].orient_preserving,
+                tris: vec![f],
+            };
+            tris[f].groups[i] = Some(index);
+            let mut stack = Vec::new();
+            stack.extend(tris[f].neighbors[(i + 2) % 3]);
+            stack.extend(tris[f].neighbors[i]);
+            // Depth-first, left neighbors first.
+            while let Some(t) = stack.pop() {
+                let tri = &mut tris[t];
+                let corner = match tri.verts.iter().position(|&v| v == group.vertex) {
+                    Some(v) => v,
+                    None => continue,
+                };
+                if tri.groups[corner].is_some() {
+                    continue;
+                }
+                // The first group to take a group-with-any triangle determines its orientation.
+                if tri.group_with_any && tri.groups.iter().all(Option::is_none) {
+                    tri.orient_preserving = group.orient_preserving;
+                }
+                if tri.orient_preserving != group.orient_preserving {
+                    continue;
+                }
+                group.tris.push(t);
+                tri.groups[corner] = Some(index);
+                stack.extend(tri.neighbors[(corner + 2) % 3]);
+                stack.extend(tri.neighbors[corner]);
+            }
+            groups.push(group);
+        }
+    }
+    groups
+}
+
+/// Computes the angle-weighted tangent space of the vertex from the given triangles.
+fn eval_tspace(tris: &[TriInfo], members: &[usize], vertex: usize, normal: [Real; 3], positions: &[[Real; 3]]) -> TSpace {
+    let mut result = TSpace {
+        os: [0.0; 3],
+        mag_s: 0.0,
+        ot: [0.0; 3],
+        mag_t: 0.0,
+        counter: 0,
+        orient_preserving: true,
+    };
+    let mut angle_sum = 0.0;
+    for tri in members.iter().map(|&f| &tris[f]).filter(|tri| !tri.group_with_any) {
+        let i = tri.corner_of_vertex(vertex);
+        let os = normalize_if_not_zero(project(tri.os, normal));
+        let ot = normalize_if_not_zero(project(tri.ot, normal));
+        let p0 = positions[tri.verts[(i + 2) % 3]];
+        let p1 = positions[tri.verts[i]];
+        let p2 = positions[tri.verts[(i + 1) % 3]];
+        let v1 = normalize_if_not_zero(project(vec3_sub(p0, p1), normal));
+        let v2 = normalize_if_not_zero(project(vec3_sub(p2, p1), normal));
+        let angle = vec3_dot(v1, v2).max(-1.0).min(1.0).acos();
+        result.os = vec3_add(result.os, vec3_scale(os, angle));
+        result.ot = vec3_add(result.ot, vec3_scale(ot, angle));
+        result.mag_s += angle * tri.mag_s;
+        result.mag_t += angle * tri.mag_t;
+        angle_sum += angle;
+    }
+    result.os = normalize_if_not_zero(result.os);
+    result.ot = normalize_if_not_zero(result.ot);
+    if angle_sum > 0.0 {
+        result.mag_s /= angle_sum;
+        result.mag_t /= angle_sum;
+    }
+    result
+}
+
+/// Averages two tangent spaces of the same polygon vertex.
+fn average_tspace(a: &TSpace, b: &TSpace) -> TSpace {
+    if a.mag_s == b.mag_s && a.mag_t == b.mag_t && a.os == b.os && a.ot == b.ot {
+        return *a;
+    }
+    TSpace {
+        os: normalize_if_not_zero(vec3_add(a.os, b.os)),
+        mag_s: 0.5 * (a.mag_s + b.mag_s),
+        ot: normalize_if_not_zero(vec3_add(a.ot, b.ot)),
+        mag_t: 0.5 * (a.mag_t + b.mag_t),
+        counter: a.counter,
+        orient_preserving: a.orient_preserving,
+    }
+}
+
+/// Projects the vector onto the plane perpendicular to the normal.
+fn project(v: [Real; 3], normal: [Real; 3]) -> [Real; 3] {
+    vec3_sub(v, vec3_scale(normal, vec3_dot(normal, v)))
+}
+
+fn not_zero(v: Real) -> bool {
+    v.abs() > ::std::f32::MIN_POSITIVE as Real
+}
+
+/// Normalizes the vector unless all of its components are (almost) zero.
+fn normalize_if_not_zero(v: [Real; 3]) -> [Real; 3] {
+    if not_zero(v[0]) || not_zero(v[1]) || not_zero(v[2]) {
+        vec3_scale(v, 1.0 / vec3_length(v))
+    } else {
+        v
+    }
+}
+
+/// Returns bits of the value for exact comparison, with `-0.0` treated as `0.0`.
+fn exact_bits(v: f64) -> u64 {
+    (v + 0.0).to_bits()
+}
+
+#[cfg(test)]
+mod tests {
+    use objects::geometry::mesh::{Mesh, MappingMode, ReferenceMode, LayerElement, VertexIndex};
+
+    fn layer_element<T: Copy>(data: Vec<T>) -> LayerElement<T> {
+        LayerElement {
+            channel: 0,
+            name: String::new(),
+            mapping_mode: MappingMode::ByPolygonVertex,
+            reference_mode: ReferenceMode::Direct,
+            data: Some(data),
+        }
+    }
+
+    fn quad() -> Mesh {
+        let vertices = vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+        Mesh::new(0, String::new(), vertices, VertexIndex::NotTriangulated(vec![0, 1, 2, !3]), Vec::new())
+    }
+
+    fn assert_tangents(tangents: &LayerElement<[f32; 4]>, expected: [f32; 4]) {
+        for t in tangents.data.as_ref().unwrap() {
+            for (v, e) in t.iter().zip(expected.iter()) {
+                assert!((v - e).abs() < 1e-5, "tangent {:?} != {:?}", t, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn planar_quad() {
+        let normals = layer_element(vec![[0.0, 0.0, 1.0]; 4]);
+        let uvs = layer_element(vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]);
+        let (tangents, binormals) = quad().generate_tangents(&uvs, &normals).unwrap();
+        assert_tangents(&tangents, [1.0, 0.0, 0.0, 1.0]);
+        assert_tangents(&binormals, [0.0, 1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn mirrored_uvs() {
+        let normals = layer_element(vec![[0.0, 0.0, 1.0]; 4]);
+        let uvs = layer_element(vec![[1.0, 0.0], [0.0, 0.0], [0.0, 1.0], [1.0, 1.0]]);
+        let (tangents, _) = quad().generate_tangents(&uvs, &normals).unwrap();
+        assert_tangents(&tangents, [-1.0, 0.0, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn no_uv_data() {
+        let normals = layer_element(vec![[0.0, 0.0, 1.0]; 4]);
+        let mut uvs = layer_element(Vec::new());
+        uvs.data = None;
+        assert!(quad().generate_tangents(&uvs, &normals).is_err());
+    }
+}
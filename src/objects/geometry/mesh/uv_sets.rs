@@ -0,0 +1,45 @@
+//! Contains UV set lookups of meshes.
+
+use objects::texture::Texture;
+use super::{Mesh, LayerElement};
+
+
+impl Mesh {
+    /// Returns the UV set (UV layer element) with the given name.
+    pub fn uv_set_by_name(&self, name: &str) -> Option<&LayerElement<[f32; 2]>> {
+        self.layer_element_uvs.iter().find(|le| le.name == name)
+    }
+
+    /// Returns the UV set (UV layer element) with the given layer element channel.
+    pub fn uv_set_by_channel(&self, channel: i32) -> Option<&LayerElement<[f32; 2]>> {
+        self.layer_element_uvs.iter().find(|le| le.channel == channel)
+    }
+
+    /// Returns the UV set (UV layer element) referred by the `Layer` with the given channel.
+    ///
+    /// If the layer refers multiple UV sets, the first one is returned.
+    pub fn uv_set_of_layer(&self, layer_channel: i32) -> Option<&LayerElement<[f32; 2]>> {
+        self.layers.iter()
+            .find(|layer| layer.channel == layer_channel)
+            .and_then(|layer| layer.uv.first())
+            .and_then(|&channel| self.uv_set_by_channel(channel))
+    }
+
+    /// Returns the UV set which the given texture should sample.
+    ///
+    /// The UV set is found by `Texture::uv_set`.
+    /// If the named UV set doesn't exist, the first UV set is returned (with a warning unless
+    /// the name is empty or `"default"`).
+    /// Returns `None` if the mesh has no UV set.
+    pub fn uv_set_for_texture(&self, texture: &Texture) -> Option<&LayerElement<[f32; 2]>> {
+        if let Some(uv_set) = self.uv_set_by_name(&texture.uv_set) {
+            return Some(uv_set);
+        }
+        let fallback = self.layer_element_uvs.first();
+        if !texture.uv_set.is_empty() && texture.uv_set != "default" {
+            warn!("UV set `{}` for texture (id={}) not found in mesh (id={}), using the first UV set {:?} instead",
+                  texture.uv_set, texture.id, self.id, fallback.map(|le| &le.name));
+        }
+        fallback
+    }
+}
@@ -1,6 +1,8 @@
 //! Contains `/Objects/Geometry` node-related stuff.
 
+pub use self::line::LineGeometry;
 pub use self::mesh::{Mesh, VertexIndex, MappingMode, ReferenceMode, LayerElement};
+pub use self::nurbs::{NurbsCurve, NurbsSurface, NurbsForm};
 pub use self::shape::Shape;
 
 use std::io::Read;
@@ -9,29 +11,42 @@ use definitions::Definitions;
 use error::Result;
 use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo};
 use objects::properties::ObjectProperties;
+use self::line::LineLoader;
 use self::mesh::MeshLoader;
+use self::nurbs::{NurbsCurveLoader, NurbsSurfaceLoader};
 use self::shape::ShapeLoader;
 
+pub mod line;
 pub mod mesh;
+pub mod nurbs;
 pub mod shape;
 
 
 #[derive(Debug, Clone)]
 pub enum Geometry {
+    Line(LineGeometry),
     Mesh(Mesh),
+    NurbsCurve(NurbsCurve),
+    NurbsSurface(NurbsSurface),
     Shape(Shape),
 }
 
 #[derive(Debug)]
 pub enum GeometryLoader<'a> {
+    Line(LineLoader<'a>),
     Mesh(MeshLoader<'a>),
+    NurbsCurve(NurbsCurveLoader<'a>),
+    NurbsSurface(NurbsSurfaceLoader<'a>),
     Shape(ShapeLoader<'a>),
 }
 
 impl<'a> GeometryLoader<'a> {
     pub fn new(definitions: &'a Definitions, obj_props: &'a ObjectProperties<'a>) -> Option<Self> {
         match obj_props.subclass {
+            "Line" => Some(GeometryLoader::Line(LineLoader::new(definitions, obj_props))),
             "Mesh" => Some(GeometryLoader::Mesh(MeshLoader::new(definitions, obj_props))),
+            "NurbsCurve" => Some(GeometryLoader::NurbsCurve(NurbsCurveLoader::new(definitions, obj_props))),
+            "NurbsSurface" => Some(GeometryLoader::NurbsSurface(NurbsSurfaceLoader::new(definitions, obj_props))),
             "Shape" => Some(GeometryLoader::Shape(ShapeLoader::new(definitions, obj_props))),
             v => {
                 warn!("Unknown subclass ({}) for `/Objects/Geometry`", v);
@@ -46,7 +61,10 @@ impl<'a> NodeLoaderCommon for GeometryLoader<'a> {
 
     fn on_finish(self) -> Result<Self::Target> {
         Ok(match self {
+            GeometryLoader::Line(loader) => try!(loader.on_finish()).map(Geometry::Line),
             GeometryLoader::Mesh(loader) => try!(loader.on_finish()).map(Geometry::Mesh),
+            GeometryLoader::NurbsCurve(loader) => try!(loader.on_finish()).map(Geometry::NurbsCurve),
+            GeometryLoader::NurbsSurface(loader) => try!(loader.on_finish()).map(Geometry::NurbsSurface),
             GeometryLoader::Shape(loader) => try!(loader.on_finish()).map(Geometry::Shape),
         })
     }
@@ -55,7 +73,10 @@ impl<'a> NodeLoaderCommon for GeometryLoader<'a> {
 impl<'a, R: Read> NodeLoader<R> for GeometryLoader<'a> {
     fn on_child_node(&mut self, reader: &mut EventReader<R>, node_info: RawNodeInfo) -> Result<()> {
         match *self {
+            GeometryLoader::Line(ref mut loader) => loader.on_child_node(reader, node_info),
             GeometryLoader::Mesh(ref mut loader) => loader.on_child_node(reader, node_info),
+            GeometryLoader::NurbsCurve(ref mut loader) => loader.on_child_node(reader, node_info),
+            GeometryLoader::NurbsSurface(ref mut loader) => loader.on_child_node(reader, node_info),
             GeometryLoader::Shape(ref mut loader) => loader.on_child_node(reader, node_info),
         }
     }
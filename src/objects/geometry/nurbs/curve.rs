@@ -0,0 +1,194 @@
+//! Contains `/Objects/Geometry(NurbsCurve)` node-related stuff.
+
+use std::io::Read;
+use fbx_binary_reader::EventReader;
+use definitions::Definitions;
+use error::Result;
+use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, ignore_current_node};
+use objects::properties::ObjectProperties;
+use Real;
+use super::{NurbsForm, Basis, sample_parameters, dehomogenize, load_control_points, load_knots};
+
+
+/// NURBS curve.
+#[derive(Debug, Clone)]
+pub struct NurbsCurve {
+    pub id: i64,
+    pub name: String,
+    /// Order (degree + 1).
+    pub order: u32,
+    /// Dimension (`2` for planar curves, `3` for space curves).
+    pub dimension: u32,
+    pub form: NurbsForm,
+    /// Whether the curve has control points with weights other than `1`.
+    pub rational: bool,
+    /// Control points as `[x, y, z, weight]`.
+    ///
+    /// Coordinates are not multiplied by the weight.
+    pub control_points: Vec<[Real; 4]>,
+    pub knots: Vec<Real>,
+}
+
+impl NurbsCurve {
+    fn basis(&self) -> Option<Basis> {
+        let basis = Basis::new(&self.knots, self.order, self.form, self.control_points.len());
+        if basis.is_none() {
+            warn!("Inconsistent order, knot vector or control points of NURBS curve (id={})", self.id);
+        }
+        basis
+    }
+
+    /// Returns whether the order, the knot vector and the control points are consistent.
+    pub fn is_valid(&self) -> bool {
+        Basis::new(&self.knots, self.order, self.form, self.control_points.len()).is_some()
+    }
+
+    /// Returns the parameter domain of the curve.
+    ///
+    /// Returns `None` if the curve is invalid.
+    pub fn domain(&self) -> Option<(Real, Real)> {
+        self.basis().map(|basis| basis.domain())
+    }
+
+    /// Evaluates the curve at the parameter `t`.
+    ///
+    /// `t` is clamped to the domain.
+    /// Returns `None` if the curve is invalid or the weights sum to zero at `t`.
+    pub fn evaluate(&self, t: Real) -> Option<[Real; 3]> {
+        self.basis().and_then(|basis| self.evaluate_with_basis(&basis, t))
+    }
+
+    fn evaluate_with_basis(&self, basis: &Basis, t: Real) -> Option<[Real; 3]> {
+        let (span, values) = basis.evaluate(t);
+        let mut sum = [0.0; 4];
+        for (i, &n) in values.iter().enumerate() {
+            let cp = self.control_points[(span - basis.degree + i) % self.control_points.len()];
+            let w = n * cp[3];
+            sum[0] += w * cp[0];
+            sum[1] += w * cp[1];
+            sum[2] += w * cp[2];
+            sum[3] += w;
+        }
+        dehomogenize(sum)
+    }
+
+    /// Tessellates the curve into a polyline.
+    ///
+    /// Each non-empty knot span is divided into `steps_per_span` segments.
+    /// For closed and periodic curves, the last point is the same as the first point.
+    /// Returns an empty vector if the curve is invalid.
+    pub fn tessellate(&self, steps_per_span: u32) -> Vec<[Real; 3]> {
+        let basis = match self.basis() {
+            Some(basis) => basis,
+            None => return Vec::new(),
+        };
+        sample_parameters(&basis, steps_per_span).into_iter()
+            .filter_map(|t| self.evaluate_with_basis(&basis, t))
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub struct NurbsCurveLoader<'a> {
+    obj_props: &'a ObjectProperties<'a>,
+    order: Option<u32>,
+    dimension: Option<u32>,
+    form: Option<NurbsForm>,
+    rational: Option<bool>,
+    control_points: Option<Vec<[Real; 4]>>,
+    knots: Option<Vec<Real>>,
+}
+
+impl<'a> NurbsCurveLoader<'a> {
+    pub fn new(_definitions: &'a Definitions, obj_props: &'a ObjectProperties<'a>) -> Self {
+        NurbsCurveLoader {
+            obj_props: obj_props,
+            order: None,
+            dimension: None,
+            form: None,
+            rational: None,
+            control_points: None,
+            knots: None,
+        }
+    }
+}
+
+impl<'a> NodeLoaderCommon for NurbsCurveLoader<'a> {
+    type Target = Option<NurbsCurve>;
+
+    fn on_finish(self) -> Result<Self::Target> {
+        if_all_some!{(
+            order=self.order,
+            control_points=self.control_points,
+            knots=self.knots,
+        ) {
+            let curve = NurbsCurve {
+                id: self.obj_props.id,
+                name: self.obj_props.name.to_owned(),
+                order: order,
+                dimension: self.dimension.unwrap_or(3),
+                form: self.form.unwrap_or_default(),
+                rational: self.rational.unwrap_or(false),
+                control_points: control_points,
+                knots: knots,
+            };
+            if !curve.is_valid() {
+                warn!("Inconsistent order, knot vector or control points of `/Objects/Geometry(NurbsCurve)` (id={})", curve.id);
+            }
+            Ok(Some(curve))
+        } else {
+            error!("Required property not found for `/Objects/Geometry(NurbsCurve)`");
+            Ok(None)
+        }}
+    }
+}
+
+impl<'a, R: Read> NodeLoader<R> for NurbsCurveLoader<'a> {
+    fn on_child_node(&mut self, reader: &mut EventReader<R>, node_info: RawNodeInfo) -> Result<()> {
+        let RawNodeInfo { name, properties } = node_info;
+        match name.as_ref() {
+            "Type" => {},
+            "NurbsCurveVersion" => {
+                match properties.iter().next().and_then(|p| p.get_i32()) {
+                    Some(100) => {},
+                    Some(v) => {
+                        warn!("Maybe unsupported version of `/Objects/Geometry(NurbsCurve)` node: ver={}", v);
+                    },
+                    None => {
+                        error!("Invalid proprety at `/Objects/Geometry(NurbsCurve)/NurbsCurveVersion`: type error");
+                    },
+                }
+            },
+            "GeometryVersion" => {},
+            "Order" => {
+                self.order = properties.iter().next().and_then(|p| p.get_i32()).and_then(|v| if v > 0 { Some(v as u32) } else { None });
+            },
+            "Dimension" => {
+                self.dimension = properties.iter().next().and_then(|p| p.get_i32()).map(|v| v as u32);
+            },
+            "Form" => {
+                self.form = properties.iter().next().and_then(|p| p.get_string()).and_then(|v| {
+                    let form = NurbsForm::from_str(v);
+                    if form.is_none() {
+                        error!("Invalid value at `/Objects/Geometry(NurbsCurve)/Form`: {}", v);
+                    }
+                    form
+                });
+            },
+            "Rational" => {
+                self.rational = properties.iter().next().and_then(|p| p.get_i32()).map(|v| v != 0);
+            },
+            "Points" => {
+                self.control_points = load_control_points(&properties);
+            },
+            "KnotVector" => {
+                self.knots = load_knots(&properties);
+            },
+            _ => {
+                warn!("Unknown node: `/Objects/Geometry(NurbsCurve)/{}`", name);
+            },
+        }
+        try!(ignore_current_node(reader));
+        Ok(())
+    }
+}
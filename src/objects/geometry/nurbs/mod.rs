@@ -0,0 +1,191 @@
+//! Contains `/Objects/Geometry(NurbsCurve)` and `/Objects/Geometry(NurbsSurface)` node-related
+//! stuff.
+
+pub use self::curve::{NurbsCurve, NurbsCurveLoader};
+pub use self::surface::{NurbsSurface, NurbsSurfaceLoader};
+
+use fbx_binary_reader::DelayedProperties;
+use node_loader::as_vec_real;
+use Real;
+
+pub mod curve;
+pub mod surface;
+
+
+/// Form of NURBS curves and surfaces.
+///
+/// See [Help: FbxNurbsCurve Class
+/// Reference](http://help.autodesk.com/view/FBX/2016/ENU/?guid=__cpp_ref_class_fbx_nurbs_curve_html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NurbsForm {
+    /// The curve is open.
+    Open,
+    /// The first and the last control points are the same.
+    Closed,
+    /// The first `order - 1` control points are repeated at the end, and the knot vector has
+    /// `num_control_points + 2 * order - 1` knots.
+    Periodic,
+}
+
+impl NurbsForm {
+    pub fn from_str<T: AsRef<str>>(val: T) -> Option<Self> {
+        match val.as_ref() {
+            "Open" => Some(NurbsForm::Open),
+            "Closed" => Some(NurbsForm::Closed),
+            "Periodic" => Some(NurbsForm::Periodic),
+            _ => None,
+        }
+    }
+}
+
+impl Default for NurbsForm {
+    fn default() -> Self {
+        NurbsForm::Open
+    }
+}
+
+/// B-spline basis of one parametric direction (knot vector, order and form).
+#[derive(Debug, Clone, Copy)]
+struct Basis<'a> {
+    knots: &'a [Real],
+    degree: usize,
+    /// Number of control points including repeated ones of the periodic form.
+    num_points: usize,
+}
+
+impl<'a> Basis<'a> {
+    /// Creates the basis, or returns `None` if the parameters are inconsistent.
+    fn new(knots: &'a [Real], order: u32, form: NurbsForm, num_control_points: usize) -> Option<Self> {
+        if order == 0 || num_control_points == 0 {
+            return None;
+        }
+        let degree = order as usize - 1;
+        let num_points = if form == NurbsForm::Periodic {
+            num_control_points + degree
+        } else {
+            num_control_points
+        };
+        if num_points <= degree || knots.len() != num_points + degree + 1 {
+            return None;
+        }
+        if knots.windows(2).any(|w| !(w[0] <= w[1])) {
+            return None;
+        }
+        Some(Basis {
+            knots: knots,
+            degree: degree,
+            num_points: num_points,
+        })
+    }
+
+    /// Returns the parameter domain.
+    fn domain(&self) -> (Real, Real) {
+        (self.knots[self.degree], self.knots[self.num_points])
+    }
+
+    /// Returns the non-empty knot spans in the domain.
+    fn spans(&self) -> Vec<(Real, Real)> {
+        (self.degree..self.num_points)
+            .map(|i| (self.knots[i], self.knots[i + 1]))
+            .filter(|&(t0, t1)| t0 < t1)
+            .collect()
+    }
+
+    /// Returns the knot span index of the parameter (clamped to the domain).
+    fn find_span(&self, t: Real) -> usize {
+        let (start, end) = self.domain();
+        if t >= end {
+            // The last non-empty span.
+            let mut span = self.num_points - 1;
+            while span > self.degree && self.knots[span] >= self.knots[span + 1] {
+                span -= 1;
+            }
+            return span;
+        }
+        if t <= start {
+            let mut span = self.degree;
+            while span < self.num_points - 1 && self.knots[span] >= self.knots[span + 1] {
+                span += 1;
+            }
+            return span;
+        }
+        let (mut low, mut high) = (self.degree, self.num_points);
+        let mut mid = (low + high) / 2;
+        while t < self.knots[mid] || t >= self.knots[mid + 1] {
+            if t < self.knots[mid] {
+                high = mid;
+            } else {
+                low = mid;
+            }
+            mid = (low + high) / 2;
+        }
+        mid
+    }
+
+    /// Returns the span index and the values of the `degree + 1` non-zero basis functions at the
+    /// parameter.
+    fn evaluate(&self, t: Real) -> (usize, Vec<Real>) {
+        let (start, end) = self.domain();
+        let t = t.max(start).min(end);
+        let span = self.find_span(t);
+        let p = self.degree;
+        let mut values = vec![0.0; p + 1];
+        let mut left = vec![0.0; p + 1];
+        let mut right = vec![0.0; p + 1];
+        values[0] = 1.0;
+        for j in 1..p + 1 {
+            left[j] = t - self.knots[span + 1 - j];
+            right[j] = self.knots[span + j] - t;
+            let mut saved = 0.0;
+            for r in 0..j {
+                let denom = right[r + 1] + left[j - r];
+                let temp = if denom != 0.0 { values[r] / denom } else { 0.0 };
+                values[r] = saved + right[r + 1] * temp;
+                saved = left[j - r] * temp;
+            }
+            values[j] = saved;
+        }
+        (span, values)
+    }
+}
+
+/// Returns the parameters to evaluate: `steps` subdivisions of each non-empty span.
+fn sample_parameters(basis: &Basis, steps: u32) -> Vec<Real> {
+    let steps = steps.max(1);
+    let spans = basis.spans();
+    let mut params = Vec::with_capacity(spans.len() * steps as usize + 1);
+    for &(t0, t1) in &spans {
+        for i in 0..steps {
+            params.push(t0 + (t1 - t0) * i as Real / steps as Real);
+        }
+    }
+    if let Some(&(_, t1)) = spans.last() {
+        params.push(t1);
+    }
+    params
+}
+
+/// Divides the weighted sum of homogeneous points.
+fn dehomogenize(p: [Real; 4]) -> Option<[Real; 3]> {
+    if p[3] != 0.0 && p[3].is_finite() {
+        Some([p[0] / p[3], p[1] / p[3], p[2] / p[3]])
+    } else {
+        None
+    }
+}
+
+/// Loads control points (`x, y, z, w` tuples) from the node property.
+fn load_control_points(properties: &DelayedProperties) -> Option<Vec<[Real; 4]>> {
+    properties.iter().next()
+        .and_then(|p| as_vec_real(&p)
+            .into_iter().find(|vec| vec.len() > 0) // Prevent vec.chunks() from panicking.
+            .map(|vec| {
+                let len = vec.len() / 4;
+                vec.chunks(4).take(len).map(|v| [v[0], v[1], v[2], v[3]]).collect()
+            }))
+}
+
+/// Loads a knot vector from the node property.
+fn load_knots(properties: &DelayedProperties) -> Option<Vec<Real>> {
+    properties.iter().next().and_then(|p| as_vec_real(&p))
+}
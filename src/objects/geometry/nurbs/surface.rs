@@ -0,0 +1,294 @@
+//! Contains `/Objects/Geometry(NurbsSurface)` node-related stuff.
+
+use std::io::Read;
+use fbx_binary_reader::{EventReader, DelayedProperties};
+use definitions::Definitions;
+use error::Result;
+use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, ignore_current_node};
+use objects::geometry::mesh::{Mesh, VertexIndex, MappingMode, ReferenceMode, LayerElement};
+use objects::properties::ObjectProperties;
+use Real;
+use super::{NurbsForm, Basis, sample_parameters, dehomogenize, load_control_points, load_knots};
+
+
+/// NURBS surface.
+///
+/// Values of `[u, v]` pairs are for U and V directions respectively.
+#[derive(Debug, Clone)]
+pub struct NurbsSurface {
+    pub id: i64,
+    pub name: String,
+    /// Orders (degree + 1).
+    pub order: [u32; 2],
+    /// Numbers of control points.
+    pub num_control_points: [usize; 2],
+    /// Numbers of divisions of each knot span for display.
+    pub step: [u32; 2],
+    pub form: [NurbsForm; 2],
+    /// Control points as `[x, y, z, weight]`.
+    ///
+    /// The control point at `(u, v)` is `control_points[v * num_control_points[0] + u]`.
+    /// Coordinates are not multiplied by the weight.
+    pub control_points: Vec<[Real; 4]>,
+    pub knots_u: Vec<Real>,
+    pub knots_v: Vec<Real>,
+    /// Whether the normals are flipped.
+    pub flip_normals: bool,
+}
+
+impl NurbsSurface {
+    fn basis(&self) -> Option<(Basis, Basis)> {
+        let (count_u, count_v) = (self.num_control_points[0], self.num_control_points[1]);
+        let bases = if count_u * count_v == self.control_points.len() {
+            match (Basis::new(&self.knots_u, self.order[0], self.form[0], count_u), Basis::new(&self.knots_v, self.order[1], self.form[1], count_v)) {
+                (Some(basis_u), Some(basis_v)) => Some((basis_u, basis_v)),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        if bases.is_none() {
+            warn!("Inconsistent orders, knot vectors or control points of NURBS surface (id={})", self.id);
+        }
+        bases
+    }
+
+    /// Returns whether the orders, the knot vectors and the control points are consistent.
+    pub fn is_valid(&self) -> bool {
+        let (count_u, count_v) = (self.num_control_points[0], self.num_control_points[1]);
+        count_u * count_v == self.control_points.len()
+            && Basis::new(&self.knots_u, self.order[0], self.form[0], count_u).is_some()
+            && Basis::new(&self.knots_v, self.order[1], self.form[1], count_v).is_some()
+    }
+
+    /// Returns the parameter domains of U and V directions.
+    ///
+    /// Returns `None` if the surface is invalid.
+    pub fn domain(&self) -> Option<[(Real, Real); 2]> {
+        self.basis().map(|(basis_u, basis_v)| [basis_u.domain(), basis_v.domain()])
+    }
+
+    /// Evaluates the surface at the parameter `(u, v)`.
+    ///
+    /// Parameters are clamped to the domain.
+    /// Returns `None` if the surface is invalid or the weights sum to zero at `(u, v)`.
+    pub fn evaluate(&self, u: Real, v: Real) -> Option<[Real; 3]> {
+        self.basis().and_then(|(basis_u, basis_v)| self.evaluate_with_basis(&basis_u, &basis_v, u, v))
+    }
+
+    fn evaluate_with_basis(&self, basis_u: &Basis, basis_v: &Basis, u: Real, v: Real) -> Option<[Real; 3]> {
+        let (count_u, count_v) = (self.num_control_points[0], self.num_control_points[1]);
+        let (span_u, values_u) = basis_u.evaluate(u);
+        let (span_v, values_v) = basis_v.evaluate(v);
+        let mut sum = [0.0; 4];
+        for (j, &nv) in values_v.iter().enumerate() {
+            let cp_v = (span_v - basis_v.degree + j) % count_v;
+            for (i, &nu) in values_u.iter().enumerate() {
+                let cp_u = (span_u - basis_u.degree + i) % count_u;
+                let cp = self.control_points[cp_v * count_u + cp_u];
+                let w = nu * nv * cp[3];
+                sum[0] += w * cp[0];
+                sum[1] += w * cp[1];
+                sum[2] += w * cp[2];
+                sum[3] += w;
+            }
+        }
+        dehomogenize(sum)
+    }
+
+    /// Tessellates the surface into a quad mesh.
+    ///
+    /// Each non-empty knot span is divided into `steps_per_span[0]` (U direction) and
+    /// `steps_per_span[1]` (V direction) parts. If `steps_per_span` is `None`, `self.step` is
+    /// used.
+    ///
+    /// The mesh has the same id and name as the surface, and has a `ByControlPoint` UV layer
+    /// element with parameters normalized to `[0, 1]`.
+    /// Control points on seams of closed and periodic surfaces are not merged (see
+    /// `Mesh::weld_control_points()`).
+    /// Returns `None` if the surface is invalid.
+    pub fn tessellate(&self, steps_per_span: Option<[u32; 2]>) -> Option<Mesh> {
+        let (basis_u, basis_v) = match self.basis() {
+            Some(bases) => bases,
+            None => return None,
+        };
+        let steps = steps_per_span.unwrap_or(self.step);
+        let params_u = sample_parameters(&basis_u, steps[0]);
+        let params_v = sample_parameters(&basis_v, steps[1]);
+        let (num_u, num_v) = (params_u.len(), params_v.len());
+        if num_u < 2 || num_v < 2 {
+            return None;
+        }
+
+        let (domain_u, domain_v) = (basis_u.domain(), basis_v.domain());
+        let normalize = |t: Real, (t0, t1): (Real, Real)| if t1 > t0 { (t - t0) / (t1 - t0) } else { 0.0 };
+        let mut vertices = Vec::with_capacity(num_u * num_v);
+        let mut uvs = Vec::with_capacity(num_u * num_v);
+        let mut num_failed = 0;
+        for &v in &params_v {
+            for &u in &params_u {
+                vertices.push(self.evaluate_with_basis(&basis_u, &basis_v, u, v).unwrap_or_else(|| {
+                    num_failed += 1;
+                    [0.0; 3]
+                }));
+                uvs.push([normalize(u, domain_u) as f32, normalize(v, domain_v) as f32]);
+            }
+        }
+        if num_failed > 0 {
+            warn!("Failed to evaluate {} points of NURBS surface (id={}), zero is used instead", num_failed, self.id);
+        }
+
+        let mut polygon_vertex_index = Vec::with_capacity((num_u - 1) * (num_v - 1) * 4);
+        for j in 0..num_v - 1 {
+            for i in 0..num_u - 1 {
+                let mut quad = [j * num_u + i, j * num_u + i + 1, (j + 1) * num_u + i + 1, (j + 1) * num_u + i];
+                if self.flip_normals {
+                    quad.reverse();
+                }
+                polygon_vertex_index.extend_from_slice(&[quad[0] as i32, quad[1] as i32, quad[2] as i32, !(quad[3] as i32)]);
+            }
+        }
+
+        let mut mesh = Mesh::new(self.id, self.name.clone(), vertices, VertexIndex::NotTriangulated(polygon_vertex_index), Vec::new());
+        mesh.layer_element_uvs.push(LayerElement {
+            channel: 0,
+            name: String::new(),
+            mapping_mode: MappingMode::ByControlPoint,
+            reference_mode: ReferenceMode::Direct,
+            data: Some(uvs),
+        });
+        Some(mesh)
+    }
+}
+
+#[derive(Debug)]
+pub struct NurbsSurfaceLoader<'a> {
+    obj_props: &'a ObjectProperties<'a>,
+    order: Option<[u32; 2]>,
+    num_control_points: Option<[usize; 2]>,
+    step: Option<[u32; 2]>,
+    form: Option<[NurbsForm; 2]>,
+    control_points: Option<Vec<[Real; 4]>>,
+    knots_u: Option<Vec<Real>>,
+    knots_v: Option<Vec<Real>>,
+    flip_normals: Option<bool>,
+}
+
+impl<'a> NurbsSurfaceLoader<'a> {
+    pub fn new(_definitions: &'a Definitions, obj_props: &'a ObjectProperties<'a>) -> Self {
+        NurbsSurfaceLoader {
+            obj_props: obj_props,
+            order: None,
+            num_control_points: None,
+            step: None,
+            form: None,
+            control_points: None,
+            knots_u: None,
+            knots_v: None,
+            flip_normals: None,
+        }
+    }
+}
+
+/// Loads a pair of non-negative integers.
+fn load_i32_pair(properties: &DelayedProperties) -> Option<[u32; 2]> {
+    let mut iter = properties.iter();
+    match (iter.next().and_then(|p| p.get_i32()), iter.next().and_then(|p| p.get_i32())) {
+        (Some(u), Some(v)) if u >= 0 && v >= 0 => Some([u as u32, v as u32]),
+        _ => None,
+    }
+}
+
+impl<'a> NodeLoaderCommon for NurbsSurfaceLoader<'a> {
+    type Target = Option<NurbsSurface>;
+
+    fn on_finish(self) -> Result<Self::Target> {
+        if_all_some!{(
+            order=self.order,
+            num_control_points=self.num_control_points,
+            control_points=self.control_points,
+            knots_u=self.knots_u,
+            knots_v=self.knots_v,
+        ) {
+            let surface = NurbsSurface {
+                id: self.obj_props.id,
+                name: self.obj_props.name.to_owned(),
+                order: order,
+                num_control_points: num_control_points,
+                step: self.step.unwrap_or([4, 4]),
+                form: self.form.unwrap_or([NurbsForm::Open; 2]),
+                control_points: control_points,
+                knots_u: knots_u,
+                knots_v: knots_v,
+                flip_normals: self.flip_normals.unwrap_or(false),
+            };
+            if !surface.is_valid() {
+                warn!("Inconsistent orders, knot vectors or control points of `/Objects/Geometry(NurbsSurface)` (id={})", surface.id);
+            }
+            Ok(Some(surface))
+        } else {
+            error!("Required property not found for `/Objects/Geometry(NurbsSurface)`");
+            Ok(None)
+        }}
+    }
+}
+
+impl<'a, R: Read> NodeLoader<R> for NurbsSurfaceLoader<'a> {
+    fn on_child_node(&mut self, reader: &mut EventReader<R>, node_info: RawNodeInfo) -> Result<()> {
+        let RawNodeInfo { name, properties } = node_info;
+        match name.as_ref() {
+            "Type" | "GeometryVersion" | "SurfaceDisplay" => {},
+            "NurbsSurfaceVersion" => {
+                match properties.iter().next().and_then(|p| p.get_i32()) {
+                    Some(100) => {},
+                    Some(v) => {
+                        warn!("Maybe unsupported version of `/Objects/Geometry(NurbsSurface)` node: ver={}", v);
+                    },
+                    None => {
+                        error!("Invalid proprety at `/Objects/Geometry(NurbsSurface)/NurbsSurfaceVersion`: type error");
+                    },
+                }
+            },
+            "NurbsSurfaceOrder" => {
+                self.order = load_i32_pair(&properties).and_then(|v| if v[0] > 0 && v[1] > 0 { Some(v) } else { None });
+            },
+            "Dimensions" => {
+                self.num_control_points = load_i32_pair(&properties).map(|v| [v[0] as usize, v[1] as usize]);
+            },
+            "Step" => {
+                self.step = load_i32_pair(&properties);
+            },
+            "Form" => {
+                let mut iter = properties.iter();
+                let forms = (iter.next().and_then(|p| p.get_string()), iter.next().and_then(|p| p.get_string()));
+                self.form = match forms {
+                    (Some(u), Some(v)) => match (NurbsForm::from_str(u), NurbsForm::from_str(v)) {
+                        (Some(u), Some(v)) => Some([u, v]),
+                        _ => {
+                            error!("Invalid value at `/Objects/Geometry(NurbsSurface)/Form`: ({}, {})", u, v);
+                            None
+                        },
+                    },
+                    _ => None,
+                };
+            },
+            "Points" => {
+                self.control_points = load_control_points(&properties);
+            },
+            "KnotVectorU" => {
+                self.knots_u = load_knots(&properties);
+            },
+            "KnotVectorV" => {
+                self.knots_v = load_knots(&properties);
+            },
+            "FlipNormals" => {
+                self.flip_normals = properties.iter().next().and_then(|p| p.get_i32()).map(|v| v != 0);
+            },
+            _ => {
+                warn!("Unknown node: `/Objects/Geometry(NurbsSurface)/{}`", name);
+            },
+        }
+        try!(ignore_current_node(reader));
+        Ok(())
+    }
+}
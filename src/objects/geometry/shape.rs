@@ -4,8 +4,11 @@ use std::io::Read;
 use fbx_binary_reader::EventReader;
 use definitions::Definitions;
 use error::Result;
-use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, ignore_current_node};
+use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, as_vec_real, ignore_current_node};
+use math::sanitize_values;
+use objects::geometry::mesh::ControlPointRemap;
 use objects::properties::ObjectProperties;
+use Real;
 
 
 #[derive(Debug, Clone)]
@@ -14,15 +17,54 @@ pub struct Shape {
     pub name: String,
     // Indices of control points of a target mesh.
     pub indices: Vec<u32>,
-    pub vertices: Vec<[f32; 3]>,
+    pub vertices: Vec<[Real; 3]>,
     pub normals: Option<Vec<[f32; 3]>>,
 }
 
+impl Shape {
+    /// Updates control points of the target mesh in accordance with the map.
+    ///
+    /// Entries for removed control points are dropped, and if some control points are merged
+    /// into one, the first entry is kept.
+    pub fn remap_control_points(&mut self, remap: &ControlPointRemap) {
+        let mut seen = vec![false; remap.num_new()];
+        let mut indices = Vec::with_capacity(self.indices.len());
+        let mut vertices = Vec::with_capacity(self.vertices.len());
+        let mut normals = self.normals.as_ref().map(|v| Vec::with_capacity(v.len()));
+        for (i, (&old, &vertex)) in self.indices.iter().zip(self.vertices.iter()).enumerate() {
+            if let Some(new) = remap.new_index(old as usize) {
+                if !seen[new] {
+                    seen[new] = true;
+                    indices.push(new as u32);
+                    vertices.push(vertex);
+                    if let (Some(normals), Some(src)) = (normals.as_mut(), self.normals.as_ref()) {
+                        normals.push(src.get(i).cloned().unwrap_or([0.0; 3]));
+                    }
+                }
+            }
+        }
+        self.indices = indices;
+        self.vertices = vertices;
+        self.normals = normals;
+    }
+
+    /// Replaces non-finite values of vertices and normals with zero.
+    ///
+    /// Returns the number of replaced values.
+    pub fn sanitize_non_finite(&mut self) -> usize {
+        let count = sanitize_values(&mut self.vertices) + self.normals.as_mut().map_or(0, |v| sanitize_values(v));
+        if count > 0 {
+            warn!("Replaced {} non-finite values with zero (shape id={})", count, self.id);
+        }
+        count
+    }
+}
+
 #[derive(Debug)]
 pub struct ShapeLoader<'a> {
     obj_props: &'a ObjectProperties<'a>,
     indices: Option<Vec<u32>>,
-    vertices: Option<Vec<[f32; 3]>>,
+    vertices: Option<Vec<[Real; 3]>>,
     normals: Option<Vec<[f32; 3]>>,
 }
 
@@ -80,7 +122,7 @@ impl<'a, R: Read> NodeLoader<R> for ShapeLoader<'a> {
             },
             "Vertices" => {
                 self.vertices = properties.iter().next()
-                    .and_then(|p| p.as_vec_f32()
+                    .and_then(|p| as_vec_real(&p)
                         .into_iter().find(|vec| vec.len() > 0) // Prevent vec.chunks() from panicking.
                         .map(|vec| {
                             let len = vec.len() / 3;
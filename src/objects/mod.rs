@@ -1,12 +1,15 @@
 //! Contains `/Objects` node-related stuff.
 
+pub use self::animation_curve::{AnimationCurve, AnimationKey, KeyInterpolation};
+pub use self::animation_curve_node::AnimationCurveNode;
+pub use self::cache::{Cache, CacheFileFormat};
 pub use self::collection::DisplayLayer;
-pub use self::deformer::{BlendShape, BlendShapeChannel, Cluster, Skin, SkinningType};
-pub use self::geometry::{Mesh, Shape, VertexIndex, MappingMode, ReferenceMode, LayerElement};
+pub use self::deformer::{BlendShape, BlendShapeChannel, Cluster, ClusterLinkMode, Skin, SkinningType, VertexCacheDeformer};
+pub use self::geometry::{LineGeometry, Mesh, NurbsCurve, NurbsSurface, Shape, VertexIndex, MappingMode, ReferenceMode, LayerElement};
 pub use self::material::{Material, ShadingParameters, LambertParameters, PhongParameters};
 pub use self::model::{CullingType, Model};
 pub use self::node_attribute::{LimbNodeAttribute, NullNodeAttribute, NodeAttributeType, NullNodeLook};
-pub use self::pose::{Pose, PoseNode};
+pub use self::pose::{Pose, PoseNode, PoseType};
 pub use self::texture::{Texture, BlendMode, WrapMode};
 pub use self::video::Video;
 
@@ -18,6 +21,9 @@ use fnv::FnvHasher;
 use definitions::Definitions;
 use error::Result;
 use node_loader::{FormatConvert, NodeLoader, NodeLoaderCommon, RawNodeInfo, ignore_current_node};
+use self::animation_curve::AnimationCurveLoader;
+use self::animation_curve_node::AnimationCurveNodeLoader;
+use self::cache::CacheLoader;
 use self::collection::{CollectionExclusive, CollectionExclusiveLoader};
 use self::deformer::{Deformer, DeformerLoader};
 use self::geometry::{Geometry, GeometryLoader};
@@ -61,6 +67,9 @@ mod macros {
     }
 }
 
+pub mod animation_curve;
+pub mod animation_curve_node;
+pub mod cache;
 pub mod collection;
 pub mod deformer;
 pub mod geometry;
@@ -78,14 +87,21 @@ pub type ObjectsMap<V> = HashMap<i64, V, BuildHasherDefault<FnvHasher>>;
 #[derive(Debug, Default)]
 pub struct Objects<I> {
     pub unknown: ObjectsMap<UnknownObject>,
+    pub animation_curves: ObjectsMap<AnimationCurve>,
+    pub animation_curve_nodes: ObjectsMap<AnimationCurveNode>,
     pub blend_shapes: ObjectsMap<BlendShape>,
     pub blend_shape_channels: ObjectsMap<BlendShapeChannel>,
+    pub caches: ObjectsMap<Cache>,
     pub clusters: ObjectsMap<Cluster>,
     pub display_layers: ObjectsMap<DisplayLayer>,
+    pub geometry_lines: ObjectsMap<LineGeometry>,
     pub geometry_meshes: ObjectsMap<Mesh>,
+    pub geometry_nurbs_curves: ObjectsMap<NurbsCurve>,
+    pub geometry_nurbs_surfaces: ObjectsMap<NurbsSurface>,
     pub geometry_shapes: ObjectsMap<Shape>,
     pub materials: ObjectsMap<Material>,
     pub model_limb_nodes: ObjectsMap<Model>,
+    pub model_lines: ObjectsMap<Model>,
     pub model_meshes: ObjectsMap<Model>,
     pub model_nulls: ObjectsMap<Model>,
     pub node_attribute_limb_nodes: ObjectsMap<LimbNodeAttribute>,
@@ -93,6 +109,7 @@ pub struct Objects<I> {
     pub poses: ObjectsMap<Pose>,
     pub skins: ObjectsMap<Skin>,
     pub textures: ObjectsMap<Texture>,
+    pub vertex_cache_deformers: ObjectsMap<VertexCacheDeformer>,
     pub videos: ObjectsMap<Video<I>>,
 }
 
@@ -105,14 +122,21 @@ impl<I> Objects<I> {
         //Default::default()
         Objects {
             unknown: Default::default(),
+            animation_curves: Default::default(),
+            animation_curve_nodes: Default::default(),
             blend_shapes: Default::default(),
             blend_shape_channels: Default::default(),
+            caches: Default::default(),
             clusters: Default::default(),
             display_layers: Default::default(),
+            geometry_lines: Default::default(),
             geometry_meshes: Default::default(),
+            geometry_nurbs_curves: Default::default(),
+            geometry_nurbs_surfaces: Default::default(),
             geometry_shapes: Default::default(),
             materials: Default::default(),
             model_limb_nodes: Default::default(),
+            model_lines: Default::default(),
             model_meshes: Default::default(),
             model_nulls: Default::default(),
             node_attribute_limb_nodes: Default::default(),
@@ -120,6 +144,7 @@ impl<I> Objects<I> {
             poses: Default::default(),
             skins: Default::default(),
             textures: Default::default(),
+            vertex_cache_deformers: Default::default(),
             videos: Default::default(),
         }
     }
@@ -135,14 +160,21 @@ macro_rules! implement_method_for_object {
     )
 }
 implement_method_for_object!(unknown, UnknownObject, add_unknown);
+implement_method_for_object!(animation_curves, AnimationCurve, add_animation_curve);
+implement_method_for_object!(animation_curve_nodes, AnimationCurveNode, add_animation_curve_node);
 implement_method_for_object!(blend_shapes, BlendShape, add_blend_shape);
 implement_method_for_object!(blend_shape_channels, BlendShapeChannel, add_blend_shape_channel);
+implement_method_for_object!(caches, Cache, add_cache);
 implement_method_for_object!(clusters, Cluster, add_cluster);
 implement_method_for_object!(display_layers, DisplayLayer, add_display_layer);
+implement_method_for_object!(geometry_lines, LineGeometry, add_geometry_line);
 implement_method_for_object!(geometry_meshes, Mesh, add_geometry_mesh);
+implement_method_for_object!(geometry_nurbs_curves, NurbsCurve, add_geometry_nurbs_curve);
+implement_method_for_object!(geometry_nurbs_surfaces, NurbsSurface, add_geometry_nurbs_surface);
 implement_method_for_object!(geometry_shapes, Shape, add_geometry_shape);
 implement_method_for_object!(materials, Material, add_material);
 implement_method_for_object!(model_limb_nodes, Model, add_model_limb_node);
+implement_method_for_object!(model_lines, Model, add_model_line);
 implement_method_for_object!(model_meshes, Model, add_model_mesh);
 implement_method_for_object!(model_nulls, Model, add_model_null);
 implement_method_for_object!(node_attribute_nulls, NullNodeAttribute, add_node_attribute_null);
@@ -150,20 +182,28 @@ implement_method_for_object!(node_attribute_limb_nodes, LimbNodeAttribute, add_n
 implement_method_for_object!(poses, Pose, add_pose);
 implement_method_for_object!(skins, Skin, add_skin);
 implement_method_for_object!(textures, Texture, add_texture);
+implement_method_for_object!(vertex_cache_deformers, VertexCacheDeformer, add_vertex_cache_deformer);
 implement_method_for_object!(videos, Video<I>, add_video);
 
 impl<I: Clone> Clone for Objects<I> {
     fn clone(&self) -> Self {
         Objects {
             unknown: self.unknown.clone(),
+            animation_curves: self.animation_curves.clone(),
+            animation_curve_nodes: self.animation_curve_nodes.clone(),
             blend_shapes: self.blend_shapes.clone(),
             blend_shape_channels: self.blend_shape_channels.clone(),
+            caches: self.caches.clone(),
             clusters: self.clusters.clone(),
             display_layers: self.display_layers.clone(),
+            geometry_lines: self.geometry_lines.clone(),
             geometry_meshes: self.geometry_meshes.clone(),
+            geometry_nurbs_curves: self.geometry_nurbs_curves.clone(),
+            geometry_nurbs_surfaces: self.geometry_nurbs_surfaces.clone(),
             geometry_shapes: self.geometry_shapes.clone(),
             materials: self.materials.clone(),
             model_limb_nodes: self.model_limb_nodes.clone(),
+            model_lines: self.model_lines.clone(),
             model_meshes: self.model_meshes.clone(),
             model_nulls: self.model_nulls.clone(),
             node_attribute_nulls: self.node_attribute_nulls.clone(),
@@ -171,6 +211,7 @@ impl<I: Clone> Clone for Objects<I> {
             poses: self.poses.clone(),
             skins: self.skins.clone(),
             textures: self.textures.clone(),
+            vertex_cache_deformers: self.vertex_cache_deformers.clone(),
             videos: self.videos.clone(),
         }
     }
@@ -211,6 +252,15 @@ impl<'a, R: Read, C: FormatConvert> NodeLoader<R> for ObjectsLoader<'a, C> {
             return Ok(());
         };
         match name.as_ref() {
+            "AnimationCurve" => if let Some(obj) = try!(AnimationCurveLoader::new(self.definitions, &obj_props).load(reader)) {
+                self.objects.add_animation_curve(obj);
+            },
+            "AnimationCurveNode" => if let Some(obj) = try!(AnimationCurveNodeLoader::new(self.definitions, &obj_props).load(reader)) {
+                self.objects.add_animation_curve_node(obj);
+            },
+            "Cache" => if let Some(obj) = try!(CacheLoader::new(self.definitions, &obj_props).load(reader)) {
+                self.objects.add_cache(obj);
+            },
             "CollectionExclusive" => if let Some(loader) = CollectionExclusiveLoader::new(self.definitions, &obj_props) {
                 match try!(loader.load(reader)) {
                     Some(CollectionExclusive::DisplayLayer(obj)) => self.objects.add_display_layer(obj),
@@ -230,6 +280,7 @@ impl<'a, R: Read, C: FormatConvert> NodeLoader<R> for ObjectsLoader<'a, C> {
                     Some(Deformer::BlendShapeChannel(obj)) => self.objects.add_blend_shape_channel(obj),
                     Some(Deformer::Cluster(obj)) => self.objects.add_cluster(obj),
                     Some(Deformer::Skin(obj)) => self.objects.add_skin(obj),
+                    Some(Deformer::VertexCacheDeformer(obj)) => self.objects.add_vertex_cache_deformer(obj),
                     None => {
                         error!("Failed to load `/Objects/Deformer`, treat as UnknownObject");
                         self.objects.add_unknown(UnknownObject::from_object_properties(&obj_props));
@@ -242,7 +293,10 @@ impl<'a, R: Read, C: FormatConvert> NodeLoader<R> for ObjectsLoader<'a, C> {
             },
             "Geometry" => if let Some(loader) = GeometryLoader::new(self.definitions, &obj_props) {
                 match try!(loader.load(reader)) {
+                    Some(Geometry::Line(obj)) => self.objects.add_geometry_line(obj),
                     Some(Geometry::Mesh(obj)) => self.objects.add_geometry_mesh(obj),
+                    Some(Geometry::NurbsCurve(obj)) => self.objects.add_geometry_nurbs_curve(obj),
+                    Some(Geometry::NurbsSurface(obj)) => self.objects.add_geometry_nurbs_surface(obj),
                     Some(Geometry::Shape(obj)) => self.objects.add_geometry_shape(obj),
                     None => {
                         error!("Failed to load `/Objects/Geometry`, treat as UnknownObject");
@@ -260,6 +314,7 @@ impl<'a, R: Read, C: FormatConvert> NodeLoader<R> for ObjectsLoader<'a, C> {
             "Model" => if let Some(obj) = try!(ModelLoader::new(self.definitions, &obj_props).load(reader)) {
                 match obj_props.subclass {
                     "LimbNode" => self.objects.add_model_limb_node(obj),
+                    "Line" => self.objects.add_model_line(obj),
                     "Mesh" => self.objects.add_model_mesh(obj),
                     "Null" => self.objects.add_model_null(obj),
                     val => {
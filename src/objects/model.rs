@@ -4,9 +4,11 @@ use std::io::Read;
 use fbx_binary_reader::EventReader;
 use definitions::Definitions;
 use error::Result;
+use math::{Mat4, mat4_identity, mat4_inverse, mat4_mul, mat4_rotation_axis, mat4_scaling, mat4_translation, vec3_cross, vec3_dot, vec3_length};
 use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, ignore_current_node};
 use objects::properties::ObjectProperties;
 use property::{GenericProperties, GenericPropertiesLoader, OptionalProperties};
+use Real;
 
 
 #[derive(Debug, Clone)]
@@ -18,6 +20,247 @@ pub struct Model {
     pub axis_len: f64,
     pub show: bool,
     pub inherit_type: InheritType,
+    /// Transform of the model.
+    pub transform: ModelTransform,
+}
+
+impl Model {
+    /// Returns the local transform matrix of the model (relative to the parent model).
+    pub fn local_transform(&self) -> [[Real; 4]; 4] {
+        self.transform.local_matrix()
+    }
+
+    /// Returns the geometric transform matrix of the model.
+    ///
+    /// Geometric transform is applied only to the geometry of the model, and not inherited by
+    /// the child models.
+    pub fn geometric_transform(&self) -> [[Real; 4]; 4] {
+        self.transform.geometric_matrix()
+    }
+}
+
+/// Transform properties of a model.
+///
+/// Rotations are euler angles in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelTransform {
+    /// `Lcl Translation`.
+    pub translation: [f64; 3],
+    /// `Lcl Rotation`.
+    pub rotation: [f64; 3],
+    /// `Lcl Scaling`.
+    pub scaling: [f64; 3],
+    /// `RotationOrder`.
+    pub rotation_order: RotationOrder,
+    /// `RotationActive`. If `false`, `pre_rotation` and `post_rotation` are ignored.
+    pub rotation_active: bool,
+    /// `PreRotation`.
+    pub pre_rotation: [f64; 3],
+    /// `PostRotation`.
+    pub post_rotation: [f64; 3],
+    /// `RotationOffset`.
+    pub rotation_offset: [f64; 3],
+    /// `RotationPivot`.
+    pub rotation_pivot: [f64; 3],
+    /// `ScalingOffset`.
+    pub scaling_offset: [f64; 3],
+    /// `ScalingPivot`.
+    pub scaling_pivot: [f64; 3],
+    /// `GeometricTranslation`.
+    pub geometric_translation: [f64; 3],
+    /// `GeometricRotation`.
+    pub geometric_rotation: [f64; 3],
+    /// `GeometricScaling`.
+    pub geometric_scaling: [f64; 3],
+}
+
+impl Default for ModelTransform {
+    fn default() -> Self {
+        ModelTransform {
+            translation: [0.0; 3],
+            rotation: [0.0; 3],
+            scaling: [1.0; 3],
+            rotation_order: RotationOrder::EulerXyz,
+            rotation_active: false,
+            pre_rotation: [0.0; 3],
+            post_rotation: [0.0; 3],
+            rotation_offset: [0.0; 3],
+            rotation_pivot: [0.0; 3],
+            scaling_offset: [0.0; 3],
+            scaling_pivot: [0.0; 3],
+            geometric_translation: [0.0; 3],
+            geometric_rotation: [0.0; 3],
+            geometric_scaling: [1.0; 3],
+        }
+    }
+}
+
+impl ModelTransform {
+    /// Loads transform properties, using defaults for missing properties.
+    fn from_properties(properties: &mut Option<GenericProperties>, defaults: Option<&GenericProperties>) -> Self {
+        let default = ModelTransform::default();
+        let mut vec3 = |name: &str, default: [f64; 3]| -> [f64; 3] {
+            properties.get_or_default(defaults, name)
+                .and_then(|p| p.value.get_vec_f64().and_then(|v| if v.len() >= 3 { Some([v[0], v[1], v[2]]) } else { None }))
+                .unwrap_or(default)
+        };
+        let translation = vec3("Lcl Translation", default.translation);
+        let rotation = vec3("Lcl Rotation", default.rotation);
+        let scaling = vec3("Lcl Scaling", default.scaling);
+        let pre_rotation = vec3("PreRotation", default.pre_rotation);
+        let post_rotation = vec3("PostRotation", default.post_rotation);
+        let rotation_offset = vec3("RotationOffset", default.rotation_offset);
+        let rotation_pivot = vec3("RotationPivot", default.rotation_pivot);
+        let scaling_offset = vec3("ScalingOffset", default.scaling_offset);
+        let scaling_pivot = vec3("ScalingPivot", default.scaling_pivot);
+        let geometric_translation = vec3("GeometricTranslation", default.geometric_translation);
+        let geometric_rotation = vec3("GeometricRotation", default.geometric_rotation);
+        let geometric_scaling = vec3("GeometricScaling", default.geometric_scaling);
+        let rotation_order = properties.get_or_default(defaults, "RotationOrder").and_then(|p| p.value.get_i64()).and_then(RotationOrder::from_i64).unwrap_or(default.rotation_order);
+        let rotation_active = properties.get_or_default(defaults, "RotationActive").and_then(|p| p.value.get_i64()).map_or(default.rotation_active, |v| v != 0);
+        ModelTransform {
+            translation: translation,
+            rotation: rotation,
+            scaling: scaling,
+            rotation_order: rotation_order,
+            rotation_active: rotation_active,
+            pre_rotation: pre_rotation,
+            post_rotation: post_rotation,
+            rotation_offset: rotation_offset,
+            rotation_pivot: rotation_pivot,
+            scaling_offset: scaling_offset,
+            scaling_pivot: scaling_pivot,
+            geometric_translation: geometric_translation,
+            geometric_rotation: geometric_rotation,
+            geometric_scaling: geometric_scaling,
+        }
+    }
+
+    /// Returns the local transform matrix.
+    ///
+    /// The matrix is `T * Roff * Rp * Rpre * R * Rpost^-1 * Rp^-1 * Soff * Sp * S * Sp^-1`.
+    /// See [Help: Computing transformation
+    /// matrix](http://help.autodesk.com/view/FBX/2016/ENU/?guid=__files_GUID_10CDD63C_79C1_4F2D_BB28_AD2BE65A02ED_htm).
+    pub fn local_matrix(&self) -> [[Real; 4]; 4] {
+        let (pre_rotation, post_rotation) = self.pre_post_rotation_matrices();
+        let post_rotation_inv = mat4_inverse(&post_rotation).unwrap_or_else(mat4_identity);
+        let rotation_pivot = to_real(self.rotation_pivot);
+        let scaling_pivot = to_real(self.scaling_pivot);
+        let matrices = [
+            mat4_translation(to_real(self.translation)),
+            mat4_translation(to_real(self.rotation_offset)),
+            mat4_translation(rotation_pivot),
+            pre_rotation,
+            euler_matrix(self.rotation, self.rotation_order),
+            post_rotation_inv,
+            mat4_translation([-rotation_pivot[0], -rotation_pivot[1], -rotation_pivot[2]]),
+            mat4_translation(to_real(self.scaling_offset)),
+            mat4_translation(scaling_pivot),
+            mat4_scaling(to_real(self.scaling)),
+            mat4_translation([-scaling_pivot[0], -scaling_pivot[1], -scaling_pivot[2]]),
+        ];
+        matrices.iter().fold(mat4_identity(), |acc, m| mat4_mul(&acc, m))
+    }
+
+    /// Sets `translation`, `rotation` and `scaling` so that `local_matrix()` returns the given
+    /// matrix, keeping the other properties.
+    ///
+    /// Shear of the matrix cannot be represented and is discarded.
+    pub fn set_local_matrix(&mut self, matrix: &Mat4) {
+        let (pre_rotation, post_rotation) = self.pre_post_rotation_matrices();
+        // Upper-left 3x3 of the local matrix is `Rpre * R * Rpost^-1 * S`.
+        let mut rotation_scaling = mat4_mul(&mat4_inverse(&pre_rotation).unwrap_or_else(mat4_identity), matrix);
+        rotation_scaling[3] = [0.0, 0.0, 0.0, 1.0];
+        let axis = |m: &Mat4, col: usize| [m[col][0], m[col][1], m[col][2]];
+        let mut scaling = [0.0; 3];
+        for (col, s) in scaling.iter_mut().enumerate() {
+            *s = vec3_length(axis(&rotation_scaling, col));
+        }
+        if vec3_dot(vec3_cross(axis(&rotation_scaling, 0), axis(&rotation_scaling, 1)), axis(&rotation_scaling, 2)) < 0.0 {
+            scaling[0] = -scaling[0];
+        }
+        for (col, &s) in scaling.iter().enumerate() {
+            if s != 0.0 {
+                for row in 0..3 {
+                    rotation_scaling[col][row] /= s;
+                }
+            }
+        }
+        let rotation = mat4_mul(&rotation_scaling, &post_rotation);
+
+        self.scaling = [scaling[0] as f64, scaling[1] as f64, scaling[2] as f64];
+        self.rotation = euler_angles(&rotation, self.rotation_order);
+        self.translation = [0.0; 3];
+        // Translation is applied last, so the rest of the matrix is not affected by it.
+        let rest = self.local_matrix();
+        self.translation = [
+            (matrix[3][0] - rest[3][0]) as f64,
+            (matrix[3][1] - rest[3][1]) as f64,
+            (matrix[3][2] - rest[3][2]) as f64,
+        ];
+    }
+
+    /// Returns `PreRotation` and `PostRotation` matrices, or identities if the rotation is not
+    /// active.
+    fn pre_post_rotation_matrices(&self) -> (Mat4, Mat4) {
+        if self.rotation_active {
+            (euler_matrix(self.pre_rotation, RotationOrder::EulerXyz), euler_matrix(self.post_rotation, RotationOrder::EulerXyz))
+        } else {
+            (mat4_identity(), mat4_identity())
+        }
+    }
+
+    /// Returns the geometric transform matrix.
+    pub fn geometric_matrix(&self) -> [[Real; 4]; 4] {
+        let matrices = [
+            mat4_translation(to_real(self.geometric_translation)),
+            euler_matrix(self.geometric_rotation, RotationOrder::EulerXyz),
+            mat4_scaling(to_real(self.geometric_scaling)),
+        ];
+        matrices.iter().fold(mat4_identity(), |acc, m| mat4_mul(&acc, m))
+    }
+}
+
+fn to_real(v: [f64; 3]) -> [Real; 3] {
+    [v[0] as Real, v[1] as Real, v[2] as Real]
+}
+
+/// Returns rotation axes in the order of application.
+fn rotation_axes(order: RotationOrder) -> [usize; 3] {
+    match order {
+        RotationOrder::EulerXyz | RotationOrder::SphericXyz => [0, 1, 2],
+        RotationOrder::EulerXzy => [0, 2, 1],
+        RotationOrder::EulerYzx => [1, 2, 0],
+        RotationOrder::EulerYxz => [1, 0, 2],
+        RotationOrder::EulerZxy => [2, 0, 1],
+        RotationOrder::EulerZyx => [2, 1, 0],
+    }
+}
+
+/// Returns the rotation matrix of the euler angles (in degrees).
+fn euler_matrix(angles: [f64; 3], order: RotationOrder) -> Mat4 {
+    let axes = rotation_axes(order);
+    axes.iter().fold(mat4_identity(), |acc, &axis| mat4_mul(&mat4_rotation_axis(axis, angles[axis] as Real), &acc))
+}
+
+/// Returns the euler angles (in degrees) of the rotation matrix.
+///
+/// This is the inverse of `euler_matrix()`. In gimbal lock, the angle of the last axis is 0.
+fn euler_angles(m: &Mat4, order: RotationOrder) -> [f64; 3] {
+    let axes = rotation_axes(order);
+    let (i, j, k) = (axes[0], axes[1], axes[2]);
+    let parity = if (i + 1) % 3 == j { 1.0 } else { -1.0 };
+    let r = |row: usize, col: usize| m[col][row];
+    let mut angles: [Real; 3] = [0.0; 3];
+    let sin_j = (-parity * r(k, i)).max(-1.0).min(1.0);
+    angles[j] = sin_j.asin();
+    if sin_j.abs() < 1.0 - 1e-6 {
+        angles[i] = (parity * r(k, j)).atan2(r(k, k));
+        angles[k] = (parity * r(j, i)).atan2(r(i, i));
+    } else {
+        angles[i] = (-parity * r(j, k)).atan2(r(j, j));
+    }
+    [angles[0].to_degrees() as f64, angles[1].to_degrees() as f64, angles[2].to_degrees() as f64]
 }
 
 #[derive(Debug)]
@@ -55,6 +298,7 @@ impl<'a> NodeLoaderCommon for ModelLoader<'a> {
         let axis_len = self.properties.get_or_default(defaults, "AxisLen").and_then(|p| p.value.get_f64());
         let show = self.properties.get_or_default(defaults, "Show").and_then(|p| p.value.get_i64()).map(|v| v != 0);
         let inherit_type = self.properties.get_or_default(defaults, "InheritType").and_then(|p| p.value.get_i64()).and_then(InheritType::from_i64);
+        let transform = ModelTransform::from_properties(&mut self.properties, defaults);
         // There still remains many properties to read. For more information, see [Help: FbxNode Class
         // Reference](http://help.autodesk.com/view/FBX/2016/ENU/?guid=__cpp_ref_class_fbx_node_html#pub-attribs).
         if_all_some!{(
@@ -72,6 +316,7 @@ impl<'a> NodeLoaderCommon for ModelLoader<'a> {
                 axis_len: axis_len,
                 show: show,
                 inherit_type: inherit_type,
+                transform: transform,
             }))
         } else {
             error!("Required property not found for `/Objects/Model({})`", self.obj_props.subclass);
@@ -154,3 +399,31 @@ impl InheritType {
         }
     }
 }
+
+/// See [Help: FbxNode Class
+/// Reference](http://help.autodesk.com/view/FBX/2016/ENU/?guid=__cpp_ref_class_fbx_node_html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotationOrder {
+    EulerXyz,
+    EulerXzy,
+    EulerYzx,
+    EulerYxz,
+    EulerZxy,
+    EulerZyx,
+    SphericXyz,
+}
+
+impl RotationOrder {
+    pub fn from_i64(v: i64) -> Option<RotationOrder> {
+        match v {
+            0 => Some(RotationOrder::EulerXyz),
+            1 => Some(RotationOrder::EulerXzy),
+            2 => Some(RotationOrder::EulerYzx),
+            3 => Some(RotationOrder::EulerYxz),
+            4 => Some(RotationOrder::EulerZxy),
+            5 => Some(RotationOrder::EulerZyx),
+            6 => Some(RotationOrder::SphericXyz),
+            _ => None,
+        }
+    }
+}
@@ -4,19 +4,51 @@ use std::io::Read;
 use fbx_binary_reader::EventReader;
 use definitions::Definitions;
 use error::Result;
-use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, ignore_current_node};
+use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, as_matrix, ignore_current_node};
 use objects::properties::ObjectProperties;
+use Real;
 
 #[derive(Debug, Clone)]
 pub struct Pose {
     pub id: i64,
     pub name: String,
+    pub pose_type: PoseType,
     pub pose_nodes: Vec<PoseNode>
 }
 
+impl Pose {
+    /// Returns the pose node of the given model.
+    pub fn pose_node(&self, model: i64) -> Option<&PoseNode> {
+        self.pose_nodes.iter().find(|node| node.node == model)
+    }
+}
+
+/// See [Help: FbxPose Class
+/// Reference](http://help.autodesk.com/view/FBX/2016/ENU/?guid=__cpp_ref_class_fbx_pose_html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoseType {
+    /// Pose of models at the time skins were bound.
+    BindPose,
+    /// Rest pose of models.
+    RestPose,
+    /// Any other pose (such as a character pose), with the value of `/Objects/Pose/Type`.
+    Other(String),
+}
+
+impl PoseType {
+    pub fn from_str<S: AsRef<str>>(s: S) -> Self {
+        match s.as_ref() {
+            "BindPose" => PoseType::BindPose,
+            "RestPose" => PoseType::RestPose,
+            s => PoseType::Other(s.to_owned()),
+        }
+    }
+}
+
 pub struct PoseLoader<'a> {
     //definitions: &'a Definitions,
     obj_props: &'a ObjectProperties<'a>,
+    pose_type: Option<PoseType>,
     nb_pose_nodes: Option<i32>,
     pose_nodes: Option<Vec<PoseNode>>,
 }
@@ -26,6 +58,7 @@ impl<'a> PoseLoader<'a> {
         PoseLoader {
             //definitions: definitions,
             obj_props: obj_props,
+            pose_type: None,
             nb_pose_nodes: None,
             pose_nodes: None,
         }
@@ -42,6 +75,10 @@ impl<'a> NodeLoaderCommon for PoseLoader<'a> {
             error!("Required property not found for `/Objects/Pose`: `/Objects/Pose/NbPoseNodes` not found");
             return Ok(None);
         };
+        let pose_type = self.pose_type.unwrap_or_else(|| {
+            warn!("`/Objects/Pose/Type` not found, treat as BindPose");
+            PoseType::BindPose
+        });
         if let Some(pose_nodes) = self.pose_nodes {
             if nb_pose_nodes != pose_nodes.len() {
                 error!("Number of `Pose/PoseNode`(={}) should be equal to the number specified by `NbPoseNodes`(={})", pose_nodes.len(), nb_pose_nodes);
@@ -50,6 +87,7 @@ impl<'a> NodeLoaderCommon for PoseLoader<'a> {
             Ok(Some(Pose {
                 id: self.obj_props.id,
                 name: self.obj_props.name.to_owned(),
+                pose_type: pose_type,
                 pose_nodes: pose_nodes,
             }))
         } else {
@@ -65,9 +103,8 @@ impl<'a, R: Read> NodeLoader<R> for PoseLoader<'a> {
         match name.as_ref() {
             "Type" => {
                 match properties.iter().next().and_then(|p| p.get_string()) {
-                    Some("BindPose") => {},
                     Some(t) => {
-                        warn!("Maybe unsupported type of `/Objects/Pose` node: type={}", t);
+                        self.pose_type = Some(PoseType::from_str(t));
                     },
                     None => {
                         error!("Invalid proprety at `/Objects/Pose/Type`: type error");
@@ -125,14 +162,19 @@ impl<'a, R: Read> NodeLoader<R> for PoseLoader<'a> {
 
 #[derive(Debug, Clone)]
 pub struct PoseNode {
+    /// Model id.
     pub node: i64,
-    pub matrix: [[f32; 4]; 4],
+    /// Global matrix of the model, or local matrix (relative to the parent model) if `local`
+    /// is `true`.
+    pub matrix: [[Real; 4]; 4],
+    pub local: bool,
 }
 
 #[derive(Debug, Default)]
 pub struct PoseNodeLoader {
     pub node: Option<i64>,
-    pub matrix: Option<[[f32; 4]; 4]>,
+    pub matrix: Option<[[Real; 4]; 4]>,
+    pub local: Option<bool>,
 }
 
 impl PoseNodeLoader {
@@ -152,6 +194,7 @@ impl NodeLoaderCommon for PoseNodeLoader {
                 return Ok(Some(PoseNode {
                     node: node,
                     matrix: matrix,
+                    local: self.local.unwrap_or(false),
                 }))
             } else {
                 error!("Required node not found for `/Objects/Pose/PoseNode`");
@@ -168,14 +211,10 @@ impl<R: Read> NodeLoader<R> for PoseNodeLoader {
                 self.node = properties.iter().next().and_then(|p| p.get_i64());
             },
             "Matrix" => {
-                self.matrix = properties.iter().next().and_then(|p| p.as_vec_f32().into_iter().find(|v| v.len() >= 16).map(|v| {
-                    [
-                        [v[0], v[1], v[2], v[3]],
-                        [v[4], v[5], v[6], v[7]],
-                        [v[8], v[9], v[10], v[11]],
-                        [v[12], v[13], v[14], v[15]],
-                    ]
-                }));
+                self.matrix = properties.iter().next().and_then(|p| as_matrix(&p));
+            },
+            "Local" => {
+                self.local = properties.iter().next().and_then(|p| p.get_bool().or_else(|| p.get_i32().map(|v| v != 0)));
             },
             _ => {
                 error!("Unknown node: `/Objects/Pose/PoseNode/{}`", name);
@@ -1,7 +1,7 @@
 //! Contains `/Objects/Texture` node-related stuff.
 
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use fbx_binary_reader::EventReader;
 use ::separate_name_class;
 use definitions::Definitions;
@@ -9,6 +9,7 @@ use error::Result;
 use node_loader::{NodeLoader, NodeLoaderCommon, RawNodeInfo, ignore_current_node};
 use objects::properties::ObjectProperties;
 use property::{GenericProperties, GenericPropertiesLoader, OptionalProperties};
+use utils::resolve_external_path;
 
 
 #[derive(Debug, Clone)]
@@ -25,6 +26,15 @@ pub struct Texture {
     pub wrap_mode_v: WrapMode,
 }
 
+impl Texture {
+    /// Returns the path of the existing texture file, or `None` if not found.
+    ///
+    /// `base_dir` is the directory of the FBX file. See `utils::resolve_external_path()`.
+    pub fn resolve_filename(&self, base_dir: &Path) -> Option<PathBuf> {
+        resolve_external_path(base_dir, &self.filename, &self.relative_filename)
+    }
+}
+
 #[derive(Debug)]
 pub struct TextureLoader<'a> {
     definitions: &'a Definitions,
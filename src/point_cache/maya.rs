@@ -0,0 +1,364 @@
+//! Contains the reader of Maya cache (`.mc` and `.mcx`) files.
+//!
+//! Maya cache files are big-endian IFF files. A chunk is a 4-byte tag followed by its size
+//! (4 bytes in `.mc` files, 8 bytes in `.mcx` files) and its data, padded to 4 (or 8) bytes.
+//! `FOR4` (or `FOR8`) chunks are groups, whose data is a 4-byte type followed by child chunks.
+//!
+//! A file starts with a `CACH` group containing the version (`VRSN`) and start and end times
+//! (`STIM`, `ETIM`), and is followed by `MYCH` groups with the time (`TIME`, absent in files
+//! containing a single frame) and channels, each of which consists of the name (`CHNM`), the
+//! number of elements (`SIZE`) and the data (`FVCA` or `DVCA` for vectors).
+
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use Real;
+use super::{PointCache, PointCacheFrame, invalid_data};
+
+
+/// Ticks per second of times in Maya cache files.
+const TICKS_PER_SECOND: f64 = 6000.0;
+
+#[derive(Debug, Clone, Copy)]
+struct Chunk<'a> {
+    tag: &'a [u8],
+    data: &'a [u8],
+}
+
+impl<'a> Chunk<'a> {
+    /// Returns the type and children of the group chunk, or `None` if the chunk is not a group.
+    fn group(&self, wide: bool) -> Option<io::Result<(&'a [u8], Vec<Chunk<'a>>)>> {
+        if self.tag != b"FOR4" && self.tag != b"FOR8" {
+            return None;
+        }
+        if self.data.len() < 4 {
+            return Some(Err(invalid_data("Too short group chunk in Maya cache file")));
+        }
+        Some(read_chunks(&self.data[4..], wide).map(|children| (&self.data[0..4], children)))
+    }
+
+    fn as_uint(&self) -> Option<u64> {
+        match self.data.len() {
+            4 | 8 => Some(be_uint(self.data)),
+            _ => None,
+        }
+    }
+}
+
+fn be_uint(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |acc, &b| (acc << 8) | b as u64)
+}
+
+/// Reads the sequence of chunks.
+fn read_chunks(data: &[u8], wide: bool) -> io::Result<Vec<Chunk>> {
+    let (size_len, align) = if wide { (8, 8) } else { (4, 4) };
+    let mut chunks = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        if data.len() - pos < 4 + size_len {
+            return Err(invalid_data("Truncated chunk header in Maya cache file"));
+        }
+        let size = be_uint(&data[pos + 4..pos + 4 + size_len]);
+        let start = pos + 4 + size_len;
+        if size > (data.len() - start) as u64 {
+            return Err(invalid_data("Truncated chunk data in Maya cache file"));
+        }
+        let end = start + size as usize;
+        chunks.push(Chunk {
+            tag: &data[pos..pos + 4],
+            data: &data[start..end],
+        });
+        pos = (end + align - 1) / align * align;
+    }
+    Ok(chunks)
+}
+
+/// Reads vectors of the `FVCA` or `DVCA` chunk.
+fn read_vectors(chunk: &Chunk) -> Option<Vec<[Real; 3]>> {
+    match chunk.tag {
+        b"FVCA" => Some(chunk.data.chunks(12).filter(|v| v.len() == 12).map(|v| {
+            let c = |i: usize| f32::from_bits(be_uint(&v[i * 4..i * 4 + 4]) as u32) as Real;
+            [c(0), c(1), c(2)]
+        }).collect()),
+        b"DVCA" => Some(chunk.data.chunks(24).filter(|v| v.len() == 24).map(|v| {
+            let c = |i: usize| f64::from_bits(be_uint(&v[i * 8..i * 8 + 8])) as Real;
+            [c(0), c(1), c(2)]
+        }).collect()),
+        _ => None,
+    }
+}
+
+/// Reads the channel from children of the `MYCH` group.
+///
+/// The first vector channel is read if `channel` is `None`.
+fn read_channel(children: &[Chunk], channel: Option<&str>) -> Option<Vec<[Real; 3]>> {
+    let mut current_name = None;
+    let mut size = None;
+    for chunk in children {
+        match chunk.tag {
+            b"CHNM" => {
+                let name = chunk.data.split(|&b| b == 0).next().unwrap_or(&[]);
+                current_name = Some(String::from_utf8_lossy(name).into_owned());
+                size = None;
+            },
+            b"SIZE" => {
+                size = chunk.as_uint();
+            },
+            _ => if let Some(vectors) = read_vectors(chunk) {
+                let matched = match (channel, current_name.as_ref()) {
+                    (None, _) => true,
+                    (Some(channel), Some(name)) => channel == name,
+                    (Some(_), None) => false,
+                };
+                if matched {
+                    if let Some(size) = size {
+                        if size != vectors.len() as u64 {
+                            warn!("Channel `{}` of Maya cache has {} elements, but SIZE is {}",
+                                  current_name.as_ref().map_or("", String::as_ref), vectors.len(), size);
+                        }
+                    }
+                    return Some(vectors);
+                }
+            },
+        }
+    }
+    None
+}
+
+/// Reads a Maya cache data file (`.mc` or `.mcx`).
+///
+/// The channel named `channel` (or the first vector channel, if `channel` is `None`) is read.
+/// Frames without the channel are skipped with warnings.
+pub fn read_maya_cache<R: Read>(mut reader: R, channel: Option<&str>) -> io::Result<PointCache> {
+    let mut data = Vec::new();
+    try!(reader.read_to_end(&mut data));
+    let wide = match data.get(0..4) {
+        Some(b"FOR4") => false,
+        Some(b"FOR8") => true,
+        _ => return Err(invalid_data("Invalid signature of Maya cache file")),
+    };
+    let chunks = try!(read_chunks(&data, wide));
+
+    let mut start_time = None;
+    let mut frames = Vec::new();
+    for chunk in &chunks {
+        let (group_type, children) = match chunk.group(wide) {
+            Some(group) => try!(group),
+            None => {
+                warn!("Unknown chunk `{}` in Maya cache file", String::from_utf8_lossy(chunk.tag));
+                continue;
+            },
+        };
+        match group_type {
+            b"CACH" => {
+                start_time = children.iter().find(|c| c.tag == b"STIM").and_then(Chunk::as_uint);
+            },
+            b"MYCH" => {
+                let time = match children.iter().find(|c| c.tag == b"TIME").and_then(Chunk::as_uint).or(start_time) {
+                    Some(v) => v as i32 as f64 / TICKS_PER_SECOND,
+                    None => {
+                        error!("Time not found for a frame of Maya cache file");
+                        continue;
+                    },
+                };
+                match read_channel(&children, channel) {
+                    Some(positions) => frames.push(PointCacheFrame {
+                        time: time,
+                        positions: positions,
+                    }),
+                    None => {
+                        warn!("Channel `{}` not found in a frame (time={}) of Maya cache file", channel.unwrap_or("*"), time);
+                    },
+                }
+            },
+            val => {
+                warn!("Unknown group `{}` in Maya cache file", String::from_utf8_lossy(val));
+            },
+        }
+    }
+    Ok(PointCache {
+        frames: frames,
+    })
+}
+
+/// Returns the frame (and tick, if any) of the data file name (`<stem>Frame<frame>[Tick<tick>]`).
+fn frame_of_file_name(name: &str) -> (i64, i64) {
+    let parse = |s: &str| s.chars().take_while(|c| c.is_digit(10) || *c == '-').collect::<String>().parse().unwrap_or(0);
+    let frame = name.rfind("Frame").map_or(0, |pos| parse(&name[pos + 5..]));
+    let tick = name.rfind("Tick").map_or(0, |pos| parse(&name[pos + 4..]));
+    (frame, tick)
+}
+
+/// Returns data files of the Maya cache with the given description (`.xml`) file.
+///
+/// Data files are in the same directory as the description file. A cache consists of either
+/// one file (`<stem>.mc` or `<stem>.mcx`) or one file per frame (`<stem>Frame<N>.mc`, and
+/// `<stem>Frame<N>Tick<M>.mc` for subframes), and files are sorted by frames.
+pub fn maya_cache_data_files(description: &Path) -> io::Result<Vec<PathBuf>> {
+    let dir = description.parent().unwrap_or(Path::new(""));
+    let stem = match description.file_stem().and_then(OsStr::to_str) {
+        Some(v) => v,
+        None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid Maya cache description file name")),
+    };
+    let frame_prefix = format!("{}Frame", stem);
+    let mut files = Vec::new();
+    for entry in try!(fs::read_dir(if dir.as_os_str().is_empty() { Path::new(".") } else { dir })) {
+        let path = try!(entry).path();
+        match path.extension().and_then(OsStr::to_str).map(|ext| ext.to_lowercase()) {
+            Some(ref ext) if ext == "mc" || ext == "mcx" => {},
+            _ => continue,
+        }
+        let file_stem = match path.file_stem().and_then(OsStr::to_str) {
+            Some(v) => v.to_owned(),
+            None => continue,
+        };
+        if file_stem == stem {
+            return Ok(vec![path]);
+        }
+        if file_stem.starts_with(&frame_prefix) {
+            files.push((frame_of_file_name(&file_stem[stem.len()..]), path));
+        }
+    }
+    files.sort();
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Reads all data files of the Maya cache with the given description (`.xml`) file.
+///
+/// See `maya_cache_data_files()` and `read_maya_cache()`.
+pub fn read_maya_cache_files(description: &Path, channel: Option<&str>) -> io::Result<PointCache> {
+    let files = try!(maya_cache_data_files(description));
+    if files.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("Data files not found for Maya cache: {}", description.display())));
+    }
+    let mut frames = Vec::new();
+    for file in files {
+        let cache = try!(read_maya_cache(BufReader::new(try!(File::open(&file))), channel));
+        frames.extend(cache.frames);
+    }
+    frames.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(::std::cmp::Ordering::Equal));
+    Ok(PointCache {
+        frames: frames,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{Chunk, read_chunks, read_maya_cache, read_vectors};
+
+    /// Returns the bytes of a chunk, padded to the alignment.
+    fn chunk(tag: &[u8], data: &[u8], wide: bool) -> Vec<u8> {
+        let (size_len, align) = if wide { (8, 8) } else { (4, 4) };
+        let mut bytes = tag.to_vec();
+        let size = data.len() as u64;
+        bytes.extend((0..size_len).rev().map(|i| (size >> (i * 8)) as u8));
+        bytes.extend_from_slice(data);
+        while bytes.len() % align != 0 {
+            bytes.push(0);
+        }
+        bytes
+    }
+
+    fn group(group_type: &[u8], children: &[Vec<u8>], wide: bool) -> Vec<u8> {
+        let mut data = group_type.to_vec();
+        for child in children {
+            data.extend_from_slice(child);
+        }
+        chunk(if wide { b"FOR8" } else { b"FOR4" }, &data, wide)
+    }
+
+    fn be_f32s(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| {
+            let v = v.to_bits();
+            vec![(v >> 24) as u8, (v >> 16) as u8, (v >> 8) as u8, v as u8]
+        }).collect()
+    }
+
+    fn cache_file(wide: bool) -> Vec<u8> {
+        let mut data = group(b"CACH", &[
+            chunk(b"VRSN", b"0.1\0", wide),
+            chunk(b"STIM", &[0, 0, 0x17, 0x70], wide),
+            chunk(b"ETIM", &[0, 0, 0x17, 0x70], wide),
+        ], wide);
+        data.extend(group(b"MYCH", &[
+            chunk(b"CHNM", b"shape\0", wide),
+            chunk(b"SIZE", &[0, 0, 0, 2], wide),
+            chunk(b"FVCA", &be_f32s(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]), wide),
+        ], wide));
+        data
+    }
+
+    #[test]
+    fn read_padded_chunks() {
+        let mut data = chunk(b"ABCD", b"12345", false);
+        data.extend(chunk(b"EFGH", b"", false));
+        let chunks = read_chunks(&data, false).unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].tag, b"ABCD");
+        assert_eq!(chunks[0].data, b"12345");
+        assert_eq!(chunks[1].tag, b"EFGH");
+        assert!(chunks[1].data.is_empty());
+    }
+
+    #[test]
+    fn read_wide_chunks() {
+        let mut data = chunk(b"ABCD", b"123456789", true);
+        data.extend(chunk(b"EFGH", b"x", true));
+        assert_eq!(data.len(), 24 + 16);
+        let chunks = read_chunks(&data, true).unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].data, b"123456789");
+        assert_eq!(chunks[1].data, b"x");
+    }
+
+    #[test]
+    fn truncated_chunks() {
+        let data = chunk(b"ABCD", b"12345678", false);
+        assert!(read_chunks(&data[..6], false).is_err());
+        assert!(read_chunks(&data[..10], false).is_err());
+        let data = chunk(b"ABCD", b"12345678", true);
+        assert!(read_chunks(&data[..10], true).is_err());
+        assert!(read_chunks(&data[..14], true).is_err());
+        // Missing padding at the end of data is allowed.
+        assert_eq!(read_chunks(&chunk(b"ABCD", b"123", false)[..11], false).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn huge_wide_size() {
+        let mut data = b"ABCD".to_vec();
+        data.extend_from_slice(&[0xff; 8]);
+        data.extend_from_slice(b"1234");
+        assert!(read_chunks(&data, true).is_err());
+    }
+
+    #[test]
+    fn misaligned_vectors() {
+        let mut data = be_f32s(&[1.0, 2.0, 3.0]);
+        data.extend_from_slice(&[0, 0]);
+        let vectors = read_vectors(&Chunk { tag: b"FVCA", data: &data }).unwrap();
+        assert_eq!(vectors, vec![[1.0, 2.0, 3.0]]);
+        assert!(read_vectors(&Chunk { tag: b"DVCA", data: &data }).unwrap().is_empty());
+        assert!(read_vectors(&Chunk { tag: b"SIZE", data: &data }).is_none());
+    }
+
+    #[test]
+    fn read_cache() {
+        for &wide in &[false, true] {
+            let cache = read_maya_cache(&cache_file(wide)[..], Some("shape")).unwrap();
+            assert_eq!(cache.frames.len(), 1);
+            assert_eq!(cache.frames[0].time, 1.0);
+            assert_eq!(cache.frames[0].positions, vec![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+            assert!(read_maya_cache(&cache_file(wide)[..], Some("other")).unwrap().frames.is_empty());
+        }
+    }
+
+    #[test]
+    fn truncated_cache() {
+        let data = cache_file(false);
+        assert!(read_maya_cache(&data[..data.len() - 4], None).is_err());
+        assert!(read_maya_cache(&b"FORM"[..], None).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+//! Contains readers of external point cache files used by vertex cache deformers.
+
+pub use self::maya::{maya_cache_data_files, read_maya_cache, read_maya_cache_files};
+pub use self::pc2::read_pc2;
+
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::path::Path;
+use objects::cache::{Cache, CacheFileFormat};
+use Real;
+
+mod maya;
+mod pc2;
+
+
+/// Vertex positions read from point cache files.
+#[derive(Debug, Clone, Default)]
+pub struct PointCache {
+    /// Frames sorted by time.
+    pub frames: Vec<PointCacheFrame>,
+}
+
+impl PointCache {
+    /// Returns the last frame at or before the given time (in seconds).
+    ///
+    /// Returns the first frame if the time is before it, or `None` if the cache has no frames.
+    pub fn frame_at(&self, time: f64) -> Option<&PointCacheFrame> {
+        self.frames.iter().rev().find(|frame| frame.time <= time).or_else(|| self.frames.first())
+    }
+}
+
+/// Vertex positions at a time.
+#[derive(Debug, Clone)]
+pub struct PointCacheFrame {
+    /// Time in seconds.
+    pub time: f64,
+    /// Positions of control points of the deformed mesh.
+    pub positions: Vec<[Real; 3]>,
+}
+
+impl Cache {
+    /// Reads the point cache files.
+    ///
+    /// `base_dir` is the directory of the FBX file (see `Cache::resolve_filename()`).
+    /// `channel` is the channel name for Maya caches (usually `VertexCacheDeformer::channel`),
+    /// and the first channel is used if it is `None`.
+    /// `frame_rate` is used to convert frames of PC2 files into seconds.
+    ///
+    /// If the file format is unknown, it is guessed from the extension of the file.
+    /// Alembic caches are not supported.
+    pub fn read_point_cache(&self, base_dir: &Path, channel: Option<&str>, frame_rate: f64) -> io::Result<PointCache> {
+        let path = match self.resolve_filename(base_dir) {
+            Some(v) => v,
+            None => {
+                return Err(io::Error::new(io::ErrorKind::NotFound, format!("Cache file not found: {}", self.filename.display())));
+            },
+        };
+        let extension = path.extension().and_then(OsStr::to_str).map(|ext| ext.to_lowercase());
+        let file_format = match (self.file_format, extension.as_ref().map(String::as_ref)) {
+            (CacheFileFormat::Unknown, Some("pc2")) => CacheFileFormat::MaxPointCacheV2,
+            (CacheFileFormat::Unknown, Some("xml")) |
+            (CacheFileFormat::Unknown, Some("mc")) |
+            (CacheFileFormat::Unknown, Some("mcx")) => CacheFileFormat::MayaCache,
+            (format, _) => format,
+        };
+        match file_format {
+            CacheFileFormat::MaxPointCacheV2 => read_pc2(BufReader::new(try!(File::open(&path))), frame_rate),
+            CacheFileFormat::MayaCache => match extension.as_ref().map(String::as_ref) {
+                Some("mc") | Some("mcx") => read_maya_cache(BufReader::new(try!(File::open(&path))), channel),
+                _ => read_maya_cache_files(&path, channel),
+            },
+            format => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Unsupported cache file format: {:?}", format)))
+            },
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
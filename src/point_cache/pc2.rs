@@ -0,0 +1,130 @@
+//! Contains the reader of 3ds Max point cache (`.pc2`) files.
+
+use std::io;
+use std::io::Read;
+use Real;
+use super::{PointCache, PointCacheFrame, invalid_data};
+
+
+const PC2_SIGNATURE: &'static [u8; 12] = b"POINTCACHE2\0";
+
+fn le_u32(bytes: &[u8]) -> u32 {
+    (bytes[0] as u32) | ((bytes[1] as u32) << 8) | ((bytes[2] as u32) << 16) | ((bytes[3] as u32) << 24)
+}
+
+fn le_f32(bytes: &[u8]) -> f32 {
+    f32::from_bits(le_u32(bytes))
+}
+
+/// Reads a PC2 file.
+///
+/// PC2 files are little-endian, and consist of a 32-byte header (signature, version, number of
+/// points, start frame, sample rate in frames and number of samples) followed by `f32` xyz
+/// positions of all points for each sample.
+/// Times of frames are computed from frames with the given `frame_rate` (frames per second).
+pub fn read_pc2<R: Read>(mut reader: R, frame_rate: f64) -> io::Result<PointCache> {
+    if !(frame_rate > 0.0) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid frame rate: {}", frame_rate)));
+    }
+    let mut header = [0u8; 32];
+    try!(reader.read_exact(&mut header));
+    if &header[0..12] != PC2_SIGNATURE {
+        return Err(invalid_data("Invalid signature of PC2 file"));
+    }
+    let version = le_u32(&header[12..16]) as i32;
+    if version != 1 {
+        warn!("Maybe unsupported version of PC2 file: ver={}", version);
+    }
+    let num_points = le_u32(&header[16..20]) as i32;
+    let start_frame = le_f32(&header[20..24]) as f64;
+    let sample_rate = le_f32(&header[24..28]) as f64;
+    let num_samples = le_u32(&header[28..32]) as i32;
+    if num_points < 0 || num_samples < 0 {
+        return Err(invalid_data("Negative number of points or samples in PC2 file"));
+    }
+
+    let frame_len = match (num_points as u64).checked_mul(12) {
+        Some(v) => v,
+        None => return Err(invalid_data("Too many points in PC2 file")),
+    };
+
+    // Header values are not trusted: buffers grow only as data is actually read.
+    let mut buffer = Vec::new();
+    let mut frames = Vec::new();
+    for sample in 0..num_samples {
+        buffer.clear();
+        try!(reader.by_ref().take(frame_len).read_to_end(&mut buffer));
+        if (buffer.len() as u64) < frame_len {
+            warn!("PC2 file is truncated: {} of {} samples are read", sample, num_samples);
+            break;
+        }
+        let positions = buffer.chunks(12)
+            .map(|p| [le_f32(&p[0..4]) as Real, le_f32(&p[4..8]) as Real, le_f32(&p[8..12]) as Real])
+            .collect();
+        frames.push(PointCacheFrame {
+            time: (start_frame + sample as f64 * sample_rate) / frame_rate,
+            positions: positions,
+        });
+    }
+    Ok(PointCache {
+        frames: frames,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::read_pc2;
+
+    fn header(num_points: u32, num_samples: u32) -> Vec<u8> {
+        let mut data = b"POINTCACHE2\0".to_vec();
+        for &v in &[1, num_points, 0f32.to_bits(), 1f32.to_bits(), num_samples] {
+            data.extend_from_slice(&[v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8]);
+        }
+        data
+    }
+
+    fn push_f32(data: &mut Vec<u8>, v: f32) {
+        let v = v.to_bits();
+        data.extend_from_slice(&[v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8]);
+    }
+
+    #[test]
+    fn read_frames() {
+        let mut data = header(2, 2);
+        for i in 0..12 {
+            push_f32(&mut data, i as f32);
+        }
+        let cache = read_pc2(&data[..], 2.0).unwrap();
+        assert_eq!(cache.frames.len(), 2);
+        assert_eq!(cache.frames[1].time, 0.5);
+        assert_eq!(cache.frames[1].positions, vec![[6.0, 7.0, 8.0], [9.0, 10.0, 11.0]]);
+    }
+
+    #[test]
+    fn truncated_header() {
+        assert!(read_pc2(&header(1, 1)[..20], 1.0).is_err());
+    }
+
+    #[test]
+    fn truncated_samples() {
+        let mut data = header(1, 3);
+        for i in 0..5 {
+            push_f32(&mut data, i as f32);
+        }
+        let cache = read_pc2(&data[..], 1.0).unwrap();
+        assert_eq!(cache.frames.len(), 1);
+    }
+
+    #[test]
+    fn huge_header_values() {
+        // Must not try to allocate buffers for the sizes claimed by the header.
+        let cache = read_pc2(&header(0x7fff_ffff, 0x7fff_ffff)[..], 1.0).unwrap();
+        assert!(cache.frames.is_empty());
+    }
+
+    #[test]
+    fn negative_sizes() {
+        assert!(read_pc2(&header(0xffff_ffff, 1)[..], 1.0).is_err());
+    }
+}
@@ -7,7 +7,14 @@ use error::{Error, Result};
 use fbx_header_extension::{FbxHeaderExtension, FbxHeaderExtensionLoader};
 use node_loader::{FormatConvert, NodeLoader, NodeLoaderCommon, RawNodeInfo, ignore_current_node};
 use connections::{Connection, ConnectionsLoader};
-use objects::{Objects, ObjectsLoader};
+use objects::{Cache, Objects, ObjectsLoader};
+use math::{mat4_identity, mat4_inverse, mat4_mul, mat4_transform_point};
+use objects::geometry::{LineGeometry, Mesh, LayerElement};
+use objects::geometry::mesh::{Aabb, CleanupOptions, ControlPointRemap};
+use objects::model::Model;
+use objects::pose::{Pose, PoseType};
+use objects::texture::Texture;
+use Real;
 
 
 #[derive(Debug)]
@@ -19,12 +26,368 @@ pub struct FbxScene<I> {
 
 impl<I> FbxScene<I> {
     pub fn triangulate<F>(&mut self, triangulator: F)
-        where F: Fn(&[[f32; 3]], &[u32], &mut Vec<u32>) -> u32
+        where F: Fn(&[[Real; 3]], &[u32], &mut Vec<u32>) -> u32
     {
         for (_id, mesh) in &mut self.objects.geometry_meshes {
             mesh.triangulate(&triangulator);
         }
     }
+
+    /// Returns ids of objects connected to the given object as children.
+    pub fn children(&self, parent: i64) -> Vec<i64> {
+        self.connections.iter()
+            .filter(|conn| conn.parent == parent && !conn.parent_is_property && !conn.child_is_property)
+            .map(|conn| conn.child)
+            .collect()
+    }
+
+    /// Returns ids of clusters of skins deforming the given mesh.
+    pub fn mesh_clusters(&self, mesh: i64) -> Vec<i64> {
+        self.mesh_skins(mesh).into_iter()
+            .flat_map(|skin| self.children(skin))
+            .filter(|id| self.objects.clusters.contains_key(id))
+            .collect()
+    }
+
+    /// Returns ids of skins deforming the given mesh.
+    pub fn mesh_skins(&self, mesh: i64) -> Vec<i64> {
+        self.children(mesh).into_iter()
+            .filter(|id| self.objects.skins.contains_key(id))
+            .collect()
+    }
+
+    /// Returns the model linked to the given cluster (i.e. the bone).
+    pub fn cluster_link_model(&self, cluster: i64) -> Option<&Model> {
+        self.children(cluster).into_iter().filter_map(|id| self.model(id)).next()
+    }
+
+    /// Returns the associate model of the given cluster (used with `ClusterLinkMode::Additive`).
+    ///
+    /// This is the model connected to `SrcModelReference` property of the cluster.
+    pub fn cluster_associate_model(&self, cluster: i64) -> Option<&Model> {
+        self.connections.iter()
+            .filter(|conn| conn.parent == cluster && conn.parent_is_property && !conn.child_is_property && conn.has_attribute("SrcModelReference"))
+            .filter_map(|conn| self.model(conn.child))
+            .next()
+    }
+
+    /// Returns ids of blend shapes deforming the given mesh.
+    pub fn mesh_blend_shapes(&self, mesh: i64) -> Vec<i64> {
+        self.children(mesh).into_iter()
+            .filter(|id| self.objects.blend_shapes.contains_key(id))
+            .collect()
+    }
+
+    /// Returns ids of shapes of blend shapes deforming the given mesh.
+    pub fn mesh_shapes(&self, mesh: i64) -> Vec<i64> {
+        self.mesh_blend_shapes(mesh).into_iter()
+            .flat_map(|blend_shape| self.children(blend_shape))
+            .filter(|id| self.objects.blend_shape_channels.contains_key(id))
+            .flat_map(|channel| self.children(channel))
+            .filter(|id| self.objects.geometry_shapes.contains_key(id))
+            .collect()
+    }
+
+    /// Returns ids of vertex cache deformers deforming the given mesh.
+    pub fn mesh_vertex_cache_deformers(&self, mesh: i64) -> Vec<i64> {
+        self.children(mesh).into_iter()
+            .filter(|id| self.objects.vertex_cache_deformers.contains_key(id))
+            .collect()
+    }
+
+    /// Returns the cache connected to the given vertex cache deformer.
+    pub fn vertex_cache_deformer_cache(&self, deformer: i64) -> Option<&Cache> {
+        self.children(deformer).into_iter().filter_map(|id| self.objects.caches.get(&id)).next()
+    }
+
+    /// Runs cleanup passes on all meshes, and updates clusters and shapes deforming them.
+    ///
+    /// See `Mesh::cleanup()`.
+    pub fn cleanup_meshes(&mut self, options: &CleanupOptions) {
+        let mesh_ids = self.objects.geometry_meshes.keys().cloned().collect::<Vec<_>>();
+        for mesh_id in mesh_ids {
+            let remap = self.objects.geometry_meshes.get_mut(&mesh_id).unwrap().cleanup(options);
+            if options.sanitize_non_finite {
+                for shape in self.mesh_shapes(mesh_id) {
+                    self.objects.geometry_shapes.get_mut(&shape).unwrap().sanitize_non_finite();
+                }
+            }
+            self.apply_control_point_remap(mesh_id, &remap);
+        }
+    }
+
+    /// Updates skins, clusters and shapes deforming the given mesh, in accordance with the map of
+    /// control points of the mesh.
+    pub fn apply_control_point_remap(&mut self, mesh: i64, remap: &ControlPointRemap) {
+        if remap.is_identity() {
+            return;
+        }
+        for skin in self.mesh_skins(mesh) {
+            self.objects.skins.get_mut(&skin).unwrap().remap_control_points(remap);
+        }
+        for cluster in self.mesh_clusters(mesh) {
+            self.objects.clusters.get_mut(&cluster).unwrap().remap_control_points(remap);
+        }
+        for shape in self.mesh_shapes(mesh) {
+            self.objects.geometry_shapes.get_mut(&shape).unwrap().remap_control_points(remap);
+        }
+    }
+
+    /// Returns the model with the given id.
+    pub fn model(&self, id: i64) -> Option<&Model> {
+        self.objects.model_meshes.get(&id)
+            .or_else(|| self.objects.model_limb_nodes.get(&id))
+            .or_else(|| self.objects.model_nulls.get(&id))
+            .or_else(|| self.objects.model_lines.get(&id))
+    }
+
+    fn model_mut(&mut self, id: i64) -> Option<&mut Model> {
+        let objects = &mut self.objects;
+        if objects.model_meshes.contains_key(&id) {
+            objects.model_meshes.get_mut(&id)
+        } else if objects.model_limb_nodes.contains_key(&id) {
+            objects.model_limb_nodes.get_mut(&id)
+        } else if objects.model_nulls.contains_key(&id) {
+            objects.model_nulls.get_mut(&id)
+        } else {
+            objects.model_lines.get_mut(&id)
+        }
+    }
+
+    /// Returns the parent model of the given model.
+    ///
+    /// Returns `None` if the model is a root (i.e. connected to the scene root).
+    pub fn parent_model(&self, id: i64) -> Option<&Model> {
+        self.connections.iter()
+            .filter(|conn| conn.child == id && !conn.parent_is_property && !conn.child_is_property)
+            .filter_map(|conn| self.model(conn.parent))
+            .next()
+    }
+
+    /// Returns the global transform matrix of the given model.
+    ///
+    /// This is the product of local transforms of the model and its ancestors.
+    /// `Model::inherit_type` is not considered (all models are treated as `RSrs`).
+    pub fn global_transform(&self, id: i64) -> Option<[[Real; 4]; 4]> {
+        self.global_transform_by(id, Model::local_transform)
+    }
+
+    /// Returns the global transform matrix of the given model at the given time.
+    ///
+    /// This is the product of local transforms at the time (see
+    /// `FbxScene::local_transform_at_time()`) of the model and its ancestors.
+    pub fn global_transform_at_time(&self, id: i64, time: i64) -> Option<[[Real; 4]; 4]> {
+        self.global_transform_by(id, |model| self.local_transform_at_time(model.id, time).unwrap_or_else(|| model.local_transform()))
+    }
+
+    /// Returns the product of local transforms of the model and its ancestors, using the given
+    /// function to get local transforms.
+    fn global_transform_by<F>(&self, id: i64, local_transform: F) -> Option<[[Real; 4]; 4]>
+        where F: Fn(&Model) -> [[Real; 4]; 4]
+    {
+        let mut model = if let Some(v) = self.model(id) {
+            v
+        } else {
+            return None;
+        };
+        let mut transform = local_transform(model);
+        let mut visited = vec![model.id];
+        while let Some(parent) = self.parent_model(model.id) {
+            if visited.contains(&parent.id) {
+                error!("Cyclic model hierarchy detected (model id={})", parent.id);
+                break;
+            }
+            visited.push(parent.id);
+            transform = mat4_mul(&local_transform(parent), &transform);
+            model = parent;
+        }
+        Some(transform)
+    }
+
+    /// Returns bind poses, sorted by ids.
+    pub fn bind_poses(&self) -> Vec<&Pose> {
+        let mut poses = self.objects.poses.values()
+            .filter(|pose| pose.pose_type == PoseType::BindPose)
+            .collect::<Vec<_>>();
+        poses.sort_by_key(|pose| pose.id);
+        poses
+    }
+
+    /// Returns the bind pose containing the given model.
+    ///
+    /// If several bind poses contain the model, the one with the smallest id is returned.
+    pub fn model_bind_pose(&self, model: i64) -> Option<&Pose> {
+        self.bind_poses().into_iter().find(|pose| pose.pose_node(model).is_some())
+    }
+
+    /// Returns the bind pose of the given skin.
+    ///
+    /// This is the bind pose containing the most bones (models linked to clusters) of the skin,
+    /// and the one with the smallest id is returned if tied.
+    pub fn skin_bind_pose(&self, skin: i64) -> Option<&Pose> {
+        let bones = self.children(skin).into_iter()
+            .filter(|id| self.objects.clusters.contains_key(id))
+            .filter_map(|cluster| self.cluster_link_model(cluster))
+            .map(|model| model.id)
+            .collect::<Vec<_>>();
+        let mut result = None;
+        let mut max_count = 0;
+        for pose in self.bind_poses() {
+            let count = bones.iter().filter(|&&bone| pose.pose_node(bone).is_some()).count();
+            if count > max_count {
+                result = Some(pose);
+                max_count = count;
+            }
+        }
+        result
+    }
+
+    /// Returns the global transform matrix of the given model in the pose.
+    ///
+    /// Local matrices of pose nodes and local transforms of models not in the pose are
+    /// multiplied by the global matrix of the nearest ancestor with a global pose matrix.
+    /// Returns `None` if the pose or the model does not exist.
+    pub fn pose_global_transform(&self, pose: i64, model: i64) -> Option<[[Real; 4]; 4]> {
+        let pose = match self.objects.poses.get(&pose) {
+            Some(v) => v,
+            None => return None,
+        };
+        if self.model(model).is_none() && pose.pose_node(model).is_none() {
+            return None;
+        }
+        let mut transform = mat4_identity();
+        let mut current = model;
+        let mut visited = vec![model];
+        loop {
+            match pose.pose_node(current) {
+                Some(node) if !node.local => return Some(mat4_mul(&node.matrix, &transform)),
+                Some(node) => transform = mat4_mul(&node.matrix, &transform),
+                None => if let Some(model) = self.model(current) {
+                    transform = mat4_mul(&model.local_transform(), &transform);
+                },
+            }
+            match self.parent_model(current) {
+                Some(parent) => {
+                    if visited.contains(&parent.id) {
+                        error!("Cyclic model hierarchy detected (model id={})", parent.id);
+                        return Some(transform);
+                    }
+                    visited.push(parent.id);
+                    current = parent.id;
+                },
+                None => return Some(transform),
+            }
+        }
+    }
+
+    /// Applies the pose to transforms of models in the pose.
+    ///
+    /// `Lcl Translation`, `Lcl Rotation` and `Lcl Scaling` of each model in the pose are updated
+    /// so that its global transform is the one in the pose (see
+    /// `FbxScene::pose_global_transform()` and `ModelTransform::set_local_matrix()`).
+    /// Returns `false` if the pose does not exist.
+    pub fn apply_pose(&mut self, pose: i64) -> bool {
+        let mut targets = match self.objects.poses.get(&pose) {
+            Some(pose) => pose.pose_nodes.iter()
+                .filter(|node| self.model(node.node).is_some())
+                .map(|node| (self.model_depth(node.node), node.node))
+                .collect::<Vec<_>>(),
+            None => return false,
+        };
+        // Parents are updated before their children.
+        targets.sort();
+        let targets = targets.into_iter()
+            .map(|(_, model)| (model, self.pose_global_transform(pose, model).unwrap()))
+            .collect::<Vec<_>>();
+        for (model, global) in targets {
+            let parent_global = self.parent_model(model).and_then(|parent| self.global_transform(parent.id)).unwrap_or_else(mat4_identity);
+            let parent_inverse = mat4_inverse(&parent_global).unwrap_or_else(|| {
+                warn!("Global transform of the parent of model (id={}) is not invertible", model);
+                mat4_identity()
+            });
+            let local = mat4_mul(&parent_inverse, &global);
+            self.model_mut(model).unwrap().transform.set_local_matrix(&local);
+        }
+        true
+    }
+
+    /// Returns the number of ancestors of the given model.
+    fn model_depth(&self, id: i64) -> usize {
+        let mut visited = vec![id];
+        let mut current = id;
+        while let Some(parent) = self.parent_model(current) {
+            if visited.contains(&parent.id) {
+                break;
+            }
+            visited.push(parent.id);
+            current = parent.id;
+        }
+        visited.len() - 1
+    }
+
+    /// Returns mesh geometries attached to the given model.
+    pub fn model_meshes(&self, model: i64) -> Vec<&Mesh> {
+        self.children(model).into_iter().filter_map(|id| self.objects.geometry_meshes.get(&id)).collect()
+    }
+
+    /// Returns line geometries attached to the given model.
+    pub fn model_lines(&self, model: i64) -> Vec<&LineGeometry> {
+        self.children(model).into_iter().filter_map(|id| self.objects.geometry_lines.get(&id)).collect()
+    }
+
+    /// Returns world-space line segments of all line geometries in the scene.
+    ///
+    /// Points are transformed by global transforms and geometric transforms of models.
+    pub fn world_line_segments(&self) -> Vec<([Real; 3], [Real; 3])> {
+        let mut result = Vec::new();
+        for model in self.objects.model_lines.values() {
+            let transform = match self.global_transform(model.id) {
+                Some(global) => mat4_mul(&global, &model.geometric_transform()),
+                None => continue,
+            };
+            for line in self.model_lines(model.id) {
+                result.extend(line.segment_positions().map(|(p1, p2)| (mat4_transform_point(&transform, p1), mat4_transform_point(&transform, p2))));
+            }
+        }
+        result
+    }
+
+    /// Returns the world-space axis-aligned bounding box of all meshes in the scene.
+    ///
+    /// Bounding boxes of meshes are transformed by global transforms and geometric transforms
+    /// of models.
+    /// Returns `None` if the scene has no mesh with finite control points.
+    pub fn world_aabb(&self) -> Option<Aabb> {
+        let mut result: Option<Aabb> = None;
+        for model in self.objects.model_meshes.values() {
+            let transform = match self.global_transform(model.id) {
+                Some(global) => mat4_mul(&global, &model.geometric_transform()),
+                None => continue,
+            };
+            for mesh in self.model_meshes(model.id) {
+                if let Some(aabb) = mesh.aabb().and_then(|aabb| aabb.transformed(&transform)) {
+                    result = Some(result.map_or(aabb, |r| r.union(&aabb)));
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns textures connected to the given material, with the attribute of the connection
+    /// (such as `"DiffuseColor"`).
+    pub fn material_textures(&self, material: i64) -> Vec<(&Texture, Option<&str>)> {
+        self.connections.iter()
+            .filter(|conn| conn.parent == material && !conn.child_is_property)
+            .filter_map(|conn| self.objects.textures.get(&conn.child).map(|tex| (tex, conn.attribute.as_ref().map(|s| s.as_ref()))))
+            .collect()
+    }
+
+    /// Resolves the UV set of the mesh which each texture on the given material should sample.
+    ///
+    /// See `Mesh::uv_set_for_texture()` for details.
+    pub fn material_texture_uv_sets<'a>(&'a self, mesh: &'a Mesh, material: i64) -> Vec<(&'a Texture, Option<&'a LayerElement<[f32; 2]>>)> {
+        self.material_textures(material).into_iter().map(|(tex, _)| (tex, mesh.uv_set_for_texture(tex))).collect()
+    }
 }
 
 impl<I: Clone> Clone for FbxScene<I> {
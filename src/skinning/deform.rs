@@ -0,0 +1,467 @@
+//! Contains CPU skinning of meshes.
+
+use error::MeshResult;
+use math::{Mat4, Quat, mat4_identity, mat4_inverse, mat4_mul, mat4_transform_point, mat4_transform_vector, mat4_transpose, quat_conjugate, quat_from_mat4, quat_mul, quat_rotate, vec3_add, vec3_normalize, vec3_scale, vec3_to_f32, vec3_to_real};
+use objects::deformer::{ClusterLinkMode, SkinningType};
+use objects::geometry::Mesh;
+use scene::FbxScene;
+use super::influences::Influence;
+use Real;
+
+
+/// CPU skin deformer of a mesh, created by `FbxScene::skin_deformer()`.
+///
+/// Deformed positions and normals are in the space of the mesh (as control points are).
+#[derive(Debug, Clone)]
+pub struct SkinDeformer {
+    /// Mesh id.
+    pub mesh: i64,
+    /// Skin id.
+    pub skin: i64,
+    pub skinning_type: SkinningType,
+    /// Link mode of the (first) cluster of the skin.
+    pub link_mode: ClusterLinkMode,
+    /// Ids of bone models.
+    pub bones: Vec<i64>,
+    /// Inverse bind matrix of each bone.
+    ///
+    /// This is `transform_link^-1 * transform` of the (first) cluster of the bone, which maps
+    /// control points at the bind time to the space of the bone.
+    /// Bones without clusters (the model of the mesh for `ClusterLinkMode::TotalOne`) have the
+    /// identity.
+    pub inverse_bind_matrices: Vec<Mat4>,
+    /// Associate model of each bone for `ClusterLinkMode::Additive`, as the index of the model in
+    /// `self.bones` and `transform^-1 * transform_associate_model` of the (first) cluster of the
+    /// bone.
+    ///
+    /// Associate models which are not bones are appended to `self.bones` without influences.
+    pub associate_models: Vec<Option<(usize, Mat4)>>,
+    /// Influences on each control point (see `SkinInfluences::influences`).
+    pub influences: Vec<Vec<Influence>>,
+    /// Blend weight of each control point for `SkinningType::Blend`.
+    ///
+    /// `0` means linear skinning and `1` means dual quaternion skinning.
+    /// Missing values are treated as `0`.
+    pub blend_weights: Vec<f32>,
+}
+
+/// Deformed mesh, created by `SkinDeformer::deform_mesh()` or `DeformedMesh::from_mesh()`.
+#[derive(Debug, Clone)]
+pub struct DeformedMesh {
+    /// Deformed control points.
+    pub positions: Vec<[Real; 3]>,
+    /// Deformed normals of polygon vertices (from the first normal layer element with data).
+    ///
+    /// This is `None` if the mesh has no normals or they are inconsistent with the mesh.
+    pub normals: Option<Vec<[f32; 3]>>,
+}
+
+impl DeformedMesh {
+    /// Creates the undeformed mesh from control points and normals of the mesh.
+    pub fn from_mesh(mesh: &Mesh) -> Self {
+        let normals = mesh.layer_element_normals.iter().find(|le| le.has_data()).and_then(|le| {
+            match mesh.polygons().map(|polygon| polygon.elements(le)).collect::<MeshResult<Vec<_>>>() {
+                Ok(v) => Some(v.concat()),
+                Err(err) => {
+                    warn!("Normals are ignored: {}", err);
+                    None
+                },
+            }
+        });
+        DeformedMesh {
+            positions: mesh.vertices.clone(),
+            normals: normals,
+        }
+    }
+}
+
+/// Transform of a control point.
+#[derive(Debug, Clone, Copy)]
+struct VertexTransform {
+    /// Blended matrix for linear skinning.
+    linear: Mat4,
+    /// Inverse transpose of `linear` (or `linear` itself if it is not invertible) for normals.
+    normal_matrix: Mat4,
+    /// Blended dual quaternion (real and dual parts) for dual quaternion skinning.
+    dual_quaternion: Option<(Quat, Quat)>,
+    /// Weight of dual quaternion skinning.
+    blend: Real,
+}
+
+impl VertexTransform {
+    fn transform_point(&self, p: [Real; 3]) -> [Real; 3] {
+        let linear = mat4_transform_point(&self.linear, p);
+        match self.dual_quaternion {
+            Some((real, dual)) if self.blend > 0.0 => {
+                let t = quat_mul(dual, quat_conjugate(real));
+                let dq = vec3_add(quat_rotate(real, p), [2.0 * t[0], 2.0 * t[1], 2.0 * t[2]]);
+                vec3_add(vec3_scale(linear, 1.0 - self.blend), vec3_scale(dq, self.blend))
+            },
+            _ => linear,
+        }
+    }
+
+    fn transform_normal(&self, n: [f32; 3]) -> [f32; 3] {
+        let n = vec3_to_real(n);
+        let linear = vec3_normalize(mat4_transform_vector(&self.normal_matrix, n));
+        vec3_to_f32(match self.dual_quaternion {
+            Some((real, _)) if self.blend > 0.0 => {
+                let dq = quat_rotate(real, n);
+                vec3_normalize(vec3_add(vec3_scale(linear, 1.0 - self.blend), vec3_scale(dq, self.blend)))
+            },
+            _ => linear,
+        })
+    }
+}
+
+impl SkinDeformer {
+    /// Returns skinning matrices of bones.
+    ///
+    /// `bone_matrices` are global matrices of bones (in the order of `self.bones`), and
+    /// `mesh_matrix` is the global matrix of the model of the mesh, at the time to deform.
+    /// Matrices of a pose or an animation time are given by `FbxScene::pose_bone_matrices()` or
+    /// `FbxScene::bone_matrices_at_time()`.
+    /// Bones with associate models are relative to the associate models, and their skinning
+    /// matrices are `transform^-1 * transform_associate_model * associate^-1 * bone *
+    /// inverse_bind` (where `associate` is the current global matrix of the associate model),
+    /// which don't depend on `mesh_matrix`.
+    /// Returns `None` if the number of bone matrices does not match or `mesh_matrix` (or the
+    /// matrix of an associate model) is not invertible.
+    pub fn skinning_matrices(&self, bone_matrices: &[Mat4], mesh_matrix: &Mat4) -> Option<Vec<Mat4>> {
+        if bone_matrices.len() != self.bones.len() {
+            error!("Number of bone matrices ({}) does not match the number of bones ({})", bone_matrices.len(), self.bones.len());
+            return None;
+        }
+        let mesh_inverse = match mat4_inverse(mesh_matrix) {
+            Some(v) => v,
+            None => {
+                error!("Mesh matrix is not invertible (mesh id={})", self.mesh);
+                return None;
+            },
+        };
+        let mut result = Vec::with_capacity(bone_matrices.len());
+        for (i, (bone, inverse_bind)) in bone_matrices.iter().zip(self.inverse_bind_matrices.iter()).enumerate() {
+            let deformation = mat4_mul(bone, inverse_bind);
+            result.push(match self.associate_models.get(i) {
+                Some(&Some((associate, ref associate_bind))) => match mat4_inverse(&bone_matrices[associate]) {
+                    Some(associate_inverse) => mat4_mul(associate_bind, &mat4_mul(&associate_inverse, &deformation)),
+                    None => {
+                        error!("Matrix of the associate model (id={}) is not invertible (mesh id={})", self.bones[associate], self.mesh);
+                        return None;
+                    },
+                },
+                _ => mat4_mul(&mesh_inverse, &deformation),
+            });
+        }
+        Some(result)
+    }
+
+    /// Returns whether the skinning matrices can be used for influences of the deformer.
+    fn check_skinning_matrices(&self, skinning_matrices: &[Mat4]) -> bool {
+        if skinning_matrices.len() != self.bones.len() {
+            error!("Number of skinning matrices ({}) does not match the number of bones ({}) (mesh id={})", skinning_matrices.len(), self.bones.len(), self.mesh);
+            return false;
+        }
+        if self.influences.iter().flat_map(|influences| influences.iter()).any(|inf| inf.bone >= skinning_matrices.len()) {
+            error!("Influence refers to a nonexistent bone (mesh id={})", self.mesh);
+            return false;
+        }
+        true
+    }
+
+    /// Returns the transform of the control point, or `None` if it has no influences.
+    ///
+    /// `skinning_matrices` should be checked by `SkinDeformer::check_skinning_matrices()`.
+    fn vertex_transform(&self, control_point: usize, skinning_matrices: &[Mat4]) -> Option<VertexTransform> {
+        let influences = match self.influences.get(control_point) {
+            Some(v) if !v.is_empty() => v,
+            _ => return None,
+        };
+        let weight_sum = influences.iter().fold(0.0, |sum, inf| sum + inf.weight);
+        if !(weight_sum > 0.0) {
+            return None;
+        }
+        let blend = match self.skinning_type {
+            SkinningType::Rigid | SkinningType::Linear => 0.0,
+            SkinningType::DualQuaternion => 1.0,
+            SkinningType::Blend => self.blend_weights.get(control_point).cloned().unwrap_or(0.0).max(0.0).min(1.0) as Real,
+        };
+
+        // Weighted matrices to blend.
+        let terms = match self.link_mode {
+            ClusterLinkMode::Normalize => {
+                influences.iter().map(|inf| ((inf.weight / weight_sum) as Real, skinning_matrices[inf.bone])).collect::<Vec<_>>()
+            },
+            ClusterLinkMode::TotalOne => {
+                let mut terms = influences.iter().map(|inf| (inf.weight as Real, skinning_matrices[inf.bone])).collect::<Vec<_>>();
+                if weight_sum < 1.0 {
+                    terms.push(((1.0 - weight_sum) as Real, mat4_identity()));
+                }
+                terms
+            },
+            ClusterLinkMode::Additive => {
+                // Deformations are applied in the order of bones (i.e. clusters).
+                let mut influences = influences.clone();
+                influences.sort_by_key(|inf| inf.bone);
+                let identity = mat4_identity();
+                let product = influences.iter().fold(identity, |product, inf| {
+                    let w = inf.weight as Real;
+                    let mut m = [[0.0; 4]; 4];
+                    for col in 0..4 {
+                        for row in 0..4 {
+                            m[col][row] = skinning_matrices[inf.bone][col][row] * w + identity[col][row] * (1.0 - w);
+                        }
+                    }
+                    mat4_mul(&m, &product)
+                });
+                vec![(1.0, product)]
+            },
+        };
+
+        let mut linear = [[0.0; 4]; 4];
+        for &(w, ref m) in &terms {
+            for col in 0..4 {
+                for row in 0..4 {
+                    linear[col][row] += m[col][row] * w;
+                }
+            }
+        }
+
+        let dual_quaternion = if blend > 0.0 {
+            let mut real = [0.0; 4];
+            let mut dual = [0.0; 4];
+            let mut pivot: Option<Quat> = None;
+            for &(w, ref m) in &terms {
+                let q = quat_from_mat4(m);
+                let d = quat_mul([m[3][0], m[3][1], m[3][2], 0.0], q);
+                // Blend quaternions in the same hemisphere.
+                let sign = match pivot {
+                    Some(p) if p[0] * q[0] + p[1] * q[1] + p[2] * q[2] + p[3] * q[3] < 0.0 => -1.0,
+                    Some(_) => 1.0,
+                    None => {
+                        pivot = Some(q);
+                        1.0
+                    },
+                };
+                for i in 0..4 {
+                    real[i] += q[i] * sign * w;
+                    dual[i] += 0.5 * d[i] * sign * w;
+                }
+            }
+            let len = (real[0] * real[0] + real[1] * real[1] + real[2] * real[2] + real[3] * real[3]).sqrt();
+            if len > 0.0 {
+                Some(([real[0] / len, real[1] / len, real[2] / len, real[3] / len], [dual[0] / len, dual[1] / len, dual[2] / len, dual[3] / len]))
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        Some(VertexTransform {
+            linear: linear,
+            normal_matrix: mat4_inverse(&linear).map_or(linear, |inv| mat4_transpose(&inv)),
+            dual_quaternion: dual_quaternion,
+            blend: blend,
+        })
+    }
+
+    /// Deforms the control points.
+    ///
+    /// Weights of each control point are combined in accordance with `self.link_mode`, and
+    /// control points without influences are not moved.
+    /// With `ClusterLinkMode::Additive`, deformations of bones are blended with the identity by
+    /// their weights, and applied in the order of bones (i.e. clusters).
+    /// `SkinningType::Rigid` is treated as `SkinningType::Linear` (rigid skins have one influence
+    /// per control point).
+    /// Dual quaternion skinning ignores scaling of bones.
+    ///
+    /// `skinning_matrices` should be given for each bone (see `SkinDeformer::skinning_matrices()`),
+    /// and the control points are returned unchanged if the number of them does not match.
+    pub fn deform_positions(&self, positions: &[[Real; 3]], skinning_matrices: &[Mat4]) -> Vec<[Real; 3]> {
+        if !self.check_skinning_matrices(skinning_matrices) {
+            return positions.to_vec();
+        }
+        positions.iter().enumerate().map(|(cp, &p)| {
+            match self.vertex_transform(cp, skinning_matrices) {
+                Some(t) => t.transform_point(p),
+                None => p,
+            }
+        }).collect()
+    }
+
+    /// Deforms the mesh control points and normals.
+    ///
+    /// See `SkinDeformer::deform_positions()`.
+    pub fn deform_mesh(&self, mesh: &Mesh, skinning_matrices: &[Mat4]) -> DeformedMesh {
+        self.deform_mesh_from(mesh, &DeformedMesh::from_mesh(mesh), skinning_matrices)
+    }
+
+    /// Deforms the mesh control points and normals already deformed by other deformers (such as
+    /// blend shapes, which are applied before skins).
+    ///
+    /// `source` should have the same number of control points and polygon vertices as the mesh.
+    /// See `SkinDeformer::deform_positions()`.
+    pub fn deform_mesh_from(&self, mesh: &Mesh, source: &DeformedMesh, skinning_matrices: &[Mat4]) -> DeformedMesh {
+        if !self.check_skinning_matrices(skinning_matrices) {
+            return source.clone();
+        }
+        let transforms = (0..source.positions.len()).map(|cp| self.vertex_transform(cp, skinning_matrices)).collect::<Vec<_>>();
+        let positions = source.positions.iter().zip(transforms.iter()).map(|(&p, t)| {
+            t.as_ref().map_or(p, |t| t.transform_point(p))
+        }).collect();
+        let normals = source.normals.as_ref().map(|normals| {
+            mesh.polygons().flat_map(|polygon| polygon.control_points()).zip(normals.iter()).map(|(cp, &n)| {
+                match cp.ok().and_then(|cp| transforms.get(cp)) {
+                    Some(&Some(ref t)) => t.transform_normal(n),
+                    _ => n,
+                }
+            }).collect()
+        });
+        DeformedMesh {
+            positions: positions,
+            normals: normals,
+        }
+    }
+}
+
+impl<I> FbxScene<I> {
+    /// Creates the CPU skin deformer of the given mesh.
+    ///
+    /// Returns `None` if the mesh is not skinned.
+    /// See `FbxScene::mesh_skin_influences()`.
+    pub fn skin_deformer(&self, mesh: i64) -> Option<SkinDeformer> {
+        let influences = match self.mesh_skin_influences(mesh) {
+            Some(v) => v,
+            None => return None,
+        };
+        let skin = &self.objects.skins[&influences.skin];
+        let blend_weights = skin.control_point_blend_weights(influences.influences.len());
+        let mut bones = influences.bones;
+        let mut inverse_bind_matrices = Vec::with_capacity(bones.len());
+        let mut associate_models = Vec::with_capacity(bones.len());
+        for clusters in &influences.bone_clusters {
+            let cluster = match clusters.first() {
+                Some(cluster) => &self.objects.clusters[cluster],
+                None => {
+                    inverse_bind_matrices.push(mat4_identity());
+                    associate_models.push(None);
+                    continue;
+                },
+            };
+            inverse_bind_matrices.push(match mat4_inverse(&cluster.transform_link) {
+                Some(inverse_link) => mat4_mul(&inverse_link, &cluster.transform),
+                None => {
+                    warn!("`TransformLink` of cluster (id={}) is not invertible", cluster.id);
+                    mat4_identity()
+                },
+            });
+            if influences.link_mode != ClusterLinkMode::Additive {
+                associate_models.push(None);
+                continue;
+            }
+            let associate = self.cluster_associate_model(cluster.id).map(|model| model.id);
+            associate_models.push(match (associate, cluster.transform_associate_model, mat4_inverse(&cluster.transform)) {
+                (Some(associate), Some(associate_bind), Some(inverse_transform)) => {
+                    let index = bones.iter().position(|&bone| bone == associate).unwrap_or_else(|| {
+                        bones.push(associate);
+                        bones.len() - 1
+                    });
+                    Some((index, mat4_mul(&inverse_transform, &associate_bind)))
+                },
+                (Some(_), None, _) => {
+                    warn!("Cluster (id={}) has an associate model but no `TransformAssociateModel`", cluster.id);
+                    None
+                },
+                (Some(_), _, None) => {
+                    warn!("`Transform` of cluster (id={}) is not invertible", cluster.id);
+                    None
+                },
+                (None, _, _) => None,
+            });
+        }
+        // Associate models appended to the bones.
+        while inverse_bind_matrices.len() < bones.len() {
+            inverse_bind_matrices.push(mat4_identity());
+            associate_models.push(None);
+        }
+        Some(SkinDeformer {
+            mesh: influences.mesh,
+            skin: influences.skin,
+            skinning_type: skin.skinning_type,
+            link_mode: influences.link_mode,
+            bones: bones,
+            inverse_bind_matrices: inverse_bind_matrices,
+            associate_models: associate_models,
+            influences: influences.influences,
+            blend_weights: blend_weights,
+        })
+    }
+
+    /// Returns current global matrices of the given models (see `FbxScene::global_transform()`).
+    ///
+    /// Identity is used for unknown models.
+    pub fn bone_matrices(&self, bones: &[i64]) -> Vec<Mat4> {
+        bones.iter().map(|&bone| self.global_transform(bone).unwrap_or_else(|| {
+            warn!("Model (id={}) not found, identity is used as the global matrix", bone);
+            mat4_identity()
+        })).collect()
+    }
+
+    /// Returns global matrices of the given models at the given time.
+    ///
+    /// See `FbxScene::global_transform_at_time()`.
+    /// Identity is used for unknown models.
+    pub fn bone_matrices_at_time(&self, bones: &[i64], time: i64) -> Vec<Mat4> {
+        bones.iter().map(|&bone| self.global_transform_at_time(bone, time).unwrap_or_else(|| {
+            warn!("Model (id={}) not found, identity is used as the global matrix", bone);
+            mat4_identity()
+        })).collect()
+    }
+
+    /// Returns global matrices of the given models in the pose.
+    ///
+    /// See `FbxScene::pose_global_transform()`.
+    /// Returns `None` if the pose does not exist.
+    pub fn pose_bone_matrices(&self, pose: i64, bones: &[i64]) -> Option<Vec<Mat4>> {
+        self.objects.poses.get(&pose).map(|_| {
+            bones.iter().map(|&bone| {
+                self.pose_global_transform(pose, bone).unwrap_or_else(|| self.bone_matrices(&[bone])[0])
+            }).collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use math::{mat4_identity, mat4_mul, mat4_rotation_axis, mat4_translation};
+    use objects::deformer::{ClusterLinkMode, SkinningType};
+    use skinning::Influence;
+    use super::SkinDeformer;
+
+    fn deformer(skinning_type: SkinningType) -> SkinDeformer {
+        SkinDeformer {
+            mesh: 0,
+            skin: 1,
+            skinning_type: skinning_type,
+            link_mode: ClusterLinkMode::Normalize,
+            bones: vec![2],
+            inverse_bind_matrices: vec![mat4_identity()],
+            associate_models: vec![None],
+            influences: vec![vec![Influence { bone: 0, weight: 1.0 }]],
+            blend_weights: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn single_rotated_bone() {
+        let bone = mat4_mul(&mat4_translation([0.0, 0.0, 1.0]), &mat4_rotation_axis(2, 90.0));
+        for &skinning_type in &[SkinningType::Linear, SkinningType::DualQuaternion] {
+            let deformed = deformer(skinning_type).deform_positions(&[[1.0, 0.0, 0.0]], &[bone]);
+            let expected = [0.0, 1.0, 1.0];
+            for i in 0..3 {
+                assert!((deformed[0][i] - expected[i]).abs() < 1e-5, "{:?}: {:?}", skinning_type, deformed);
+            }
+        }
+    }
+}
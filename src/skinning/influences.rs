@@ -0,0 +1,307 @@
+//! Contains resolution of skins into per-vertex bone influences.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+use fnv::FnvHasher;
+use objects::deformer::ClusterLinkMode;
+use objects::geometry::mesh::FlattenedMesh;
+use scene::FbxScene;
+
+
+/// Influence of a bone on a control point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Influence {
+    /// Index of the bone in `SkinInfluences::bones`.
+    pub bone: usize,
+    pub weight: f32,
+}
+
+/// Bone influences of control points of a skinned mesh, created by
+/// `FbxScene::mesh_skin_influences()`.
+#[derive(Debug, Clone)]
+pub struct SkinInfluences {
+    /// Mesh id.
+    pub mesh: i64,
+    /// Skin id.
+    pub skin: i64,
+    /// Link mode of the first cluster of the skin, which is used for all clusters.
+    pub link_mode: ClusterLinkMode,
+    /// Ids of bone models.
+    pub bones: Vec<i64>,
+    /// Ids of clusters linked to each bone.
+    ///
+    /// A bone usually has one cluster, but several clusters may be linked to the same bone.
+    /// The model of the mesh has no clusters (see `FbxScene::mesh_skin_influences()`).
+    pub bone_clusters: Vec<Vec<i64>>,
+    /// Influences on each control point, sorted by weight in descending order.
+    ///
+    /// Weights are as stored in clusters (not normalized), except for the weights on the model
+    /// of the mesh with `ClusterLinkMode::TotalOne`.
+    pub influences: Vec<Vec<Influence>>,
+}
+
+impl SkinInfluences {
+    /// Returns influences on the given control point as pairs of the bone model id and the
+    /// weight.
+    pub fn control_point_influences(&self, control_point: usize) -> Vec<(i64, f32)> {
+        self.influences.get(control_point)
+            .map(|influences| influences.iter().map(|inf| (self.bones[inf.bone], inf.weight)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the maximum number of influences on a control point.
+    pub fn max_influences(&self) -> usize {
+        self.influences.iter().map(|v| v.len()).max().unwrap_or(0)
+    }
+
+    /// Returns influences on each control point limited and normalized in accordance with the
+    /// options.
+    pub fn limited_influences(&self, options: &JointWeightOptions) -> Vec<Vec<Influence>> {
+        let max = options.max_influences.max(1);
+        let mut num_truncated = 0;
+        let result = self.influences.iter().map(|influences| {
+            let mut influences = influences.iter()
+                .cloned()
+                .filter(|inf| inf.weight > options.min_weight)
+                .collect::<Vec<_>>();
+            if influences.len() > max {
+                num_truncated += 1;
+                let dropped = influences.split_off(max).iter().fold(0.0, |sum, inf| sum + inf.weight);
+                match options.excess_policy {
+                    ExcessInfluencePolicy::Discard => {},
+                    ExcessInfluencePolicy::AddToLargest => influences[0].weight += dropped,
+                }
+            }
+            if options.normalize {
+                let sum = influences.iter().fold(0.0, |sum, inf| sum + inf.weight);
+                if sum > 0.0 {
+                    for inf in &mut influences {
+                        inf.weight /= sum;
+                    }
+                }
+            }
+            influences
+        }).collect();
+        if num_truncated > 0 {
+            warn!("{} control points have more than {} influences (mesh id={}, skin id={})", num_truncated, max, self.mesh, self.skin);
+        }
+        result
+    }
+
+    /// Creates fixed-width joint indices and weights for vertices of the flattened mesh.
+    ///
+    /// The flattened mesh should be created from the mesh of the skin, and joint weights
+    /// should be recreated after vertices are reordered.
+    /// Returns `None` for `ClusterLinkMode::Additive`, which cannot be represented by blending
+    /// joints linearly (use `SkinDeformer` instead).
+    pub fn vertex_joint_weights(&self, flattened: &FlattenedMesh, options: &JointWeightOptions) -> Option<VertexJointWeights> {
+        if self.link_mode == ClusterLinkMode::Additive {
+            error!("Additive clusters cannot be represented by joint weights (mesh id={}, skin id={})", self.mesh, self.skin);
+            return None;
+        }
+        let width = options.max_influences.max(1);
+        let limited = self.limited_influences(options);
+        let mut joints = Vec::with_capacity(flattened.num_vertices() * width);
+        let mut weights = Vec::with_capacity(flattened.num_vertices() * width);
+        for &cp in &flattened.control_points {
+            let influences = limited.get(cp as usize).map_or(&[][..], |v| &v[..]);
+            for i in 0..width {
+                match influences.get(i) {
+                    Some(inf) => {
+                        joints.push(inf.bone as u32);
+                        weights.push(inf.weight);
+                    },
+                    None => {
+                        joints.push(0);
+                        weights.push(0.0);
+                    },
+                }
+            }
+        }
+        Some(VertexJointWeights {
+            width: width,
+            joints: joints,
+            weights: weights,
+        })
+    }
+}
+
+/// Policy to handle influences exceeding `JointWeightOptions::max_influences`.
+///
+/// The smallest influences are always dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExcessInfluencePolicy {
+    /// Drops the smallest influences.
+    ///
+    /// The remaining weights are still normalized if `JointWeightOptions::normalize` is true.
+    Discard,
+    /// Drops the smallest influences, and adds their weights to the largest influence.
+    AddToLargest,
+}
+
+/// Options for `SkinInfluences::limited_influences()` and
+/// `SkinInfluences::vertex_joint_weights()`.
+#[derive(Debug, Clone, Copy)]
+pub struct JointWeightOptions {
+    /// Maximum number of influences per vertex (usually 4 or 8).
+    pub max_influences: usize,
+    pub excess_policy: ExcessInfluencePolicy,
+    /// Whether to normalize weights so that they sum to 1.
+    pub normalize: bool,
+    /// Influences with weights not greater than this value are dropped.
+    pub min_weight: f32,
+}
+
+impl Default for JointWeightOptions {
+    fn default() -> Self {
+        JointWeightOptions {
+            max_influences: 4,
+            excess_policy: ExcessInfluencePolicy::Discard,
+            normalize: true,
+            min_weight: 0.0,
+        }
+    }
+}
+
+/// Fixed-width joint indices and weights of vertices of a flattened mesh.
+#[derive(Debug, Clone)]
+pub struct VertexJointWeights {
+    /// Number of joints per vertex.
+    pub width: usize,
+    /// Joint indices (indices of `SkinInfluences::bones`), `width` elements per vertex.
+    ///
+    /// Unused slots have the joint `0` with the weight `0`.
+    pub joints: Vec<u32>,
+    /// Joint weights, `width` elements per vertex.
+    pub weights: Vec<f32>,
+}
+
+impl VertexJointWeights {
+    /// Returns joint indices of the given vertex.
+    pub fn vertex_joints(&self, vertex: usize) -> &[u32] {
+        &self.joints[vertex * self.width..(vertex + 1) * self.width]
+    }
+
+    /// Returns joint weights of the given vertex.
+    pub fn vertex_weights(&self, vertex: usize) -> &[f32] {
+        &self.weights[vertex * self.width..(vertex + 1) * self.width]
+    }
+}
+
+impl<I> FbxScene<I> {
+    /// Resolves the skin of the given mesh into bone influences on control points.
+    ///
+    /// Connections Mesh → Skin → Cluster → Model are followed, and any model type (`LimbNode`,
+    /// `Null`, `Mesh`) can be a bone.
+    /// If the mesh has several skins, the first one is used.
+    /// Clusters without linked models, out-of-range indices and non-finite or zero weights are
+    /// ignored.
+    ///
+    /// The link mode of the first cluster is used for all clusters (as the FBX SDK does).
+    /// With `ClusterLinkMode::TotalOne`, the remainder (`1 - sum`) of weights of each control
+    /// point is assigned to the model of the mesh as a bone, so that the control point keeps
+    /// the undeformed position for the remainder.
+    /// Returns `None` if the mesh does not exist or is not skinned.
+    pub fn mesh_skin_influences(&self, mesh: i64) -> Option<SkinInfluences> {
+        let num_control_points = match self.objects.geometry_meshes.get(&mesh) {
+            Some(mesh) => mesh.vertices.len(),
+            None => return None,
+        };
+        let skins = self.mesh_skins(mesh);
+        let skin = match skins.first() {
+            Some(&skin) => skin,
+            None => return None,
+        };
+        if skins.len() > 1 {
+            warn!("Mesh (id={}) has {} skins, only the first one (id={}) is used", mesh, skins.len(), skin);
+        }
+
+        let mut result = SkinInfluences {
+            mesh: mesh,
+            skin: skin,
+            link_mode: ClusterLinkMode::Normalize,
+            bones: Vec::new(),
+            bone_clusters: Vec::new(),
+            influences: vec![Vec::new(); num_control_points],
+        };
+        let mut bone_indices: HashMap<i64, usize, BuildHasherDefault<FnvHasher>> = Default::default();
+        let mut num_ignored = 0;
+        let mut link_mode = None;
+        for cluster_id in self.children(skin) {
+            let cluster = match self.objects.clusters.get(&cluster_id) {
+                Some(cluster) => cluster,
+                None => continue,
+            };
+            match link_mode {
+                None => link_mode = Some(cluster.link_mode),
+                Some(mode) if mode != cluster.link_mode => {
+                    warn!("Cluster (id={}) has link mode `{:?}` different from `{:?}` of the first cluster", cluster_id, cluster.link_mode, mode);
+                },
+                Some(_) => {},
+            }
+            let bone_id = match self.cluster_link_model(cluster_id) {
+                Some(model) => model.id,
+                None => {
+                    warn!("Cluster (id={}) has no linked model", cluster_id);
+                    continue;
+                },
+            };
+            let bone = *bone_indices.entry(bone_id).or_insert_with(|| {
+                result.bones.push(bone_id);
+                result.bone_clusters.push(Vec::new());
+                result.bones.len() - 1
+            });
+            result.bone_clusters[bone].push(cluster_id);
+            for (&cp, &weight) in cluster.indices.iter().zip(cluster.weights.iter()) {
+                let influences = match result.influences.get_mut(cp as usize) {
+                    Some(v) if weight.is_finite() && weight != 0.0 => v,
+                    _ => {
+                        num_ignored += 1;
+                        continue;
+                    },
+                };
+                // Clusters linked to the same bone are merged.
+                if let Some(inf) = influences.iter_mut().find(|inf| inf.bone == bone) {
+                    inf.weight += weight;
+                    continue;
+                }
+                influences.push(Influence {
+                    bone: bone,
+                    weight: weight,
+                });
+            }
+        }
+        if num_ignored > 0 {
+            warn!("Ignored {} invalid influences (mesh id={}, skin id={})", num_ignored, mesh, skin);
+        }
+        result.link_mode = link_mode.unwrap_or_default();
+        if result.link_mode == ClusterLinkMode::TotalOne {
+            if let Some(mesh_model) = self.parent_model(mesh).map(|model| model.id) {
+                let bone = result.bones.iter().position(|&b| b == mesh_model).unwrap_or_else(|| {
+                    result.bones.push(mesh_model);
+                    result.bone_clusters.push(Vec::new());
+                    result.bones.len() - 1
+                });
+                for influences in &mut result.influences {
+                    let sum = influences.iter().fold(0.0, |sum, inf| sum + inf.weight);
+                    if !influences.is_empty() && sum < 1.0 {
+                        match influences.iter_mut().find(|inf| inf.bone == bone) {
+                            Some(inf) => inf.weight += 1.0 - sum,
+                            None => influences.push(Influence {
+                                bone: bone,
+                                weight: 1.0 - sum,
+                            }),
+                        }
+                    }
+                }
+            } else {
+                warn!("Model of mesh (id={}) not found, remainders of weights are ignored", mesh);
+            }
+        }
+        for influences in &mut result.influences {
+            influences.sort_by(|a, b| b.weight.partial_cmp(&a.weight).unwrap_or(Ordering::Equal));
+        }
+        Some(result)
+    }
+}
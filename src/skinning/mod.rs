@@ -0,0 +1,11 @@
+//! Contains skinning-related utilities built on top of skins, clusters and bone models.
+
+pub use self::deform::{SkinDeformer, DeformedMesh};
+pub use self::influences::{SkinInfluences, Influence, JointWeightOptions, ExcessInfluencePolicy, VertexJointWeights};
+pub use self::skeleton::{Skeleton, Joint, BindMatrixSource, BindMatrixMismatch};
+pub use self::validate::{SkinIssue, SkinIssueKind, SkinRepairOptions};
+
+mod deform;
+mod influences;
+mod skeleton;
+mod validate;
@@ -0,0 +1,202 @@
+//! Contains extraction of skeletons.
+
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+use fnv::FnvHasher;
+use math::{Mat4, mat4_identity, mat4_inverse, mat4_max_difference};
+use objects::deformer::Cluster;
+use scene::FbxScene;
+use Real;
+
+
+/// Tolerance of differences between bind matrices from clusters and bind poses.
+pub const BIND_MISMATCH_TOLERANCE: Real = 1e-3;
+
+/// Source of the bind matrix of a joint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindMatrixSource {
+    /// `Cluster::transform_link`.
+    TransformLink,
+    /// Global matrix of a bind pose (see `FbxScene::pose_global_transform()`).
+    BindPose,
+    /// Current global transform of the model (no bind information found).
+    GlobalTransform,
+}
+
+/// Joint of a skeleton.
+#[derive(Debug, Clone)]
+pub struct Joint {
+    /// Model id.
+    pub model: i64,
+    pub name: String,
+    /// Index of the parent joint (which always precedes this joint).
+    pub parent: Option<usize>,
+    /// Inverse of the global matrix of the joint at the bind time.
+    pub inverse_bind_matrix: Mat4,
+    pub bind_matrix_source: BindMatrixSource,
+}
+
+/// Mismatch between the bind matrices of a joint from a cluster and a bind pose.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BindMatrixMismatch {
+    /// Model id.
+    pub model: i64,
+    pub cluster: i64,
+    pub pose: i64,
+    /// Maximum absolute difference of matrix elements.
+    pub max_difference: Real,
+}
+
+/// Skeleton, created by `FbxScene::skeleton()` or `FbxScene::mesh_skeleton()`.
+#[derive(Debug, Clone, Default)]
+pub struct Skeleton {
+    /// Joints, sorted so that parents precede their children.
+    pub joints: Vec<Joint>,
+    /// Joints whose bind matrices from clusters and bind poses are different.
+    pub mismatches: Vec<BindMatrixMismatch>,
+}
+
+impl Skeleton {
+    /// Returns the number of joints.
+    pub fn num_joints(&self) -> usize {
+        self.joints.len()
+    }
+
+    /// Returns the index of the joint of the given model.
+    pub fn joint_index(&self, model: i64) -> Option<usize> {
+        self.joints.iter().position(|joint| joint.model == model)
+    }
+
+    /// Returns parent joint indices (`-1` for roots).
+    pub fn parent_indices(&self) -> Vec<i32> {
+        self.joints.iter().map(|joint| joint.parent.map_or(-1, |p| p as i32)).collect()
+    }
+}
+
+impl<I> FbxScene<I> {
+    /// Extracts the skeleton of all `LimbNode` models and models linked to clusters.
+    pub fn skeleton(&self) -> Skeleton {
+        let mut models = self.objects.model_limb_nodes.keys().cloned().collect::<Vec<_>>();
+        models.extend(self.objects.clusters.keys().filter_map(|&cluster| self.cluster_link_model(cluster)).map(|model| model.id));
+        self.skeleton_of_models(models)
+    }
+
+    /// Extracts the skeleton of bones of the skin of the given mesh.
+    ///
+    /// `LimbNode` ancestors of the bones are also included.
+    /// Returns `None` if the mesh is not skinned.
+    pub fn mesh_skeleton(&self, mesh: i64) -> Option<Skeleton> {
+        self.mesh_skin_influences(mesh).map(|influences| {
+            let mut models = influences.bones.clone();
+            for &bone in &influences.bones {
+                let mut current = bone;
+                while let Some(parent) = self.parent_model(current) {
+                    if models.contains(&parent.id) || !self.objects.model_limb_nodes.contains_key(&parent.id) {
+                        break;
+                    }
+                    models.push(parent.id);
+                    current = parent.id;
+                }
+            }
+            self.skeleton_of_models(models)
+        })
+    }
+
+    fn skeleton_of_models(&self, mut models: Vec<i64>) -> Skeleton {
+        models.sort();
+        models.dedup();
+
+        // Parent of each model in the set (the nearest ancestor in the set).
+        let mut parents: HashMap<i64, Option<i64>, BuildHasherDefault<FnvHasher>> = Default::default();
+        for &model in &models {
+            let mut visited = vec![model];
+            let mut current = model;
+            let mut parent = None;
+            while let Some(p) = self.parent_model(current) {
+                if visited.contains(&p.id) {
+                    error!("Cyclic model hierarchy detected (model id={})", p.id);
+                    break;
+                }
+                visited.push(p.id);
+                if models.binary_search(&p.id).is_ok() {
+                    parent = Some(p.id);
+                    break;
+                }
+                current = p.id;
+            }
+            parents.insert(model, parent);
+        }
+
+        // Cluster (with the smallest id) linked to each model.
+        let mut clusters: HashMap<i64, &Cluster, BuildHasherDefault<FnvHasher>> = Default::default();
+        for cluster in self.objects.clusters.values() {
+            if let Some(model) = self.cluster_link_model(cluster.id) {
+                let entry = clusters.entry(model.id).or_insert(cluster);
+                if cluster.id < entry.id {
+                    *entry = cluster;
+                }
+            }
+        }
+
+        // Sort topologically (depth-first from roots, in the order of model ids).
+        let mut skeleton = Skeleton::default();
+        let mut indices: HashMap<i64, usize, BuildHasherDefault<FnvHasher>> = Default::default();
+        let mut stack = models.iter().rev().cloned().filter(|m| parents[m].is_none()).collect::<Vec<_>>();
+        while let Some(model) = stack.pop() {
+            if indices.contains_key(&model) {
+                continue;
+            }
+            let parent = parents[&model].and_then(|p| indices.get(&p).cloned());
+            indices.insert(model, skeleton.joints.len());
+            self.push_joint(&mut skeleton, model, parent, clusters.get(&model).cloned());
+            stack.extend(models.iter().rev().cloned().filter(|m| parents[m] == Some(model)));
+        }
+        if skeleton.joints.len() < models.len() {
+            // Models in cycles are unreachable from roots.
+            for &model in &models {
+                if !indices.contains_key(&model) {
+                    indices.insert(model, skeleton.joints.len());
+                    self.push_joint(&mut skeleton, model, None, clusters.get(&model).cloned());
+                }
+            }
+        }
+        skeleton
+    }
+
+    fn push_joint(&self, skeleton: &mut Skeleton, model: i64, parent: Option<usize>, cluster: Option<&Cluster>) {
+        let pose_matrix = self.model_bind_pose(model)
+            .and_then(|pose| self.pose_global_transform(pose.id, model).map(|matrix| (pose.id, matrix)));
+
+        if let (Some(cluster), Some((pose, matrix))) = (cluster, pose_matrix) {
+            let diff = mat4_max_difference(&cluster.transform_link, &matrix);
+            if diff > BIND_MISMATCH_TOLERANCE {
+                warn!("Bind matrices of model (id={}) from cluster (id={}) and pose (id={}) are different", model, cluster.id, pose);
+                skeleton.mismatches.push(BindMatrixMismatch {
+                    model: model,
+                    cluster: cluster.id,
+                    pose: pose,
+                    max_difference: diff,
+                });
+            }
+        }
+
+        let (bind_matrix, source) = if let Some(cluster) = cluster {
+            (cluster.transform_link, BindMatrixSource::TransformLink)
+        } else if let Some((_, matrix)) = pose_matrix {
+            (matrix, BindMatrixSource::BindPose)
+        } else {
+            (self.global_transform(model).unwrap_or_else(mat4_identity), BindMatrixSource::GlobalTransform)
+        };
+        let inverse_bind_matrix = mat4_inverse(&bind_matrix).unwrap_or_else(|| {
+            warn!("Bind matrix of model (id={}) is not invertible", model);
+            mat4_identity()
+        });
+        skeleton.joints.push(Joint {
+            model: model,
+            name: self.model(model).map_or_else(String::new, |m| m.name.clone()),
+            parent: parent,
+            inverse_bind_matrix: inverse_bind_matrix,
+            bind_matrix_source: source,
+        });
+    }
+}
@@ -0,0 +1,311 @@
+//! Contains validation and repair of skins.
+
+use math::mat4_max_difference;
+use objects::deformer::ClusterLinkMode;
+use objects::pose::PoseNode;
+use scene::FbxScene;
+use Real;
+use super::skeleton::BIND_MISMATCH_TOLERANCE;
+
+
+/// Tolerance of differences between 1 and sums of weights of control points.
+const WEIGHT_SUM_TOLERANCE: f32 = 1e-3;
+
+/// Issue of a skin, found by `FbxScene::validate_mesh_skins()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkinIssue {
+    /// Mesh id.
+    pub mesh: i64,
+    /// Skin id.
+    pub skin: i64,
+    /// Cluster id, or `None` for issues of the whole skin.
+    pub cluster: Option<i64>,
+    pub kind: SkinIssueKind,
+}
+
+/// Kind of an issue of a skin.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkinIssueKind {
+    /// Lengths of `Cluster::indices` and `Cluster::weights` are different.
+    LengthMismatch {
+        num_indices: usize,
+        num_weights: usize,
+    },
+    /// Some indices of the cluster are not less than the number of control points.
+    IndicesOutOfRange {
+        /// Number of out-of-range indices.
+        count: usize,
+        num_control_points: usize,
+    },
+    /// Sums of weights of some control points are not 1 (or greater than 1, with
+    /// `ClusterLinkMode::TotalOne`).
+    ///
+    /// Control points without influences and skins with `ClusterLinkMode::Additive` are not
+    /// checked.
+    UnnormalizedWeights {
+        /// Number of control points with unnormalized weights.
+        count: usize,
+        /// Maximum absolute difference between sums of weights and 1.
+        max_error: f32,
+    },
+    /// The cluster has no linked model.
+    MissingBone,
+    /// The skin has no bind pose containing its bones.
+    MissingBindPose,
+    /// The bone is not in the bind pose of the skin.
+    BoneNotInBindPose {
+        /// Model id.
+        bone: i64,
+        pose: i64,
+    },
+    /// Matrices of the bone from `Cluster::transform_link` and the bind pose are different.
+    BindMatrixMismatch {
+        /// Model id.
+        bone: i64,
+        pose: i64,
+        /// Maximum absolute difference of matrix elements.
+        max_difference: Real,
+    },
+}
+
+/// Options for `FbxScene::repair_mesh_skins()`.
+#[derive(Debug, Clone, Copy)]
+pub struct SkinRepairOptions {
+    /// Truncate indices and weights of clusters to the shorter length, and remove
+    /// out-of-range indices.
+    pub truncate: bool,
+    /// Normalize weights so that they sum to 1 for each control point (or scale them down if
+    /// they exceed 1, with `ClusterLinkMode::TotalOne`).
+    pub normalize: bool,
+    /// Set matrices of bones in the bind pose of the skin to `Cluster::transform_link`,
+    /// adding bones missing from the pose.
+    ///
+    /// No bind poses are created for skins without ones.
+    pub rebuild_bind_pose: bool,
+}
+
+impl Default for SkinRepairOptions {
+    fn default() -> Self {
+        SkinRepairOptions {
+            truncate: true,
+            normalize: true,
+            rebuild_bind_pose: true,
+        }
+    }
+}
+
+impl<I> FbxScene<I> {
+    /// Validates skins of all meshes.
+    ///
+    /// See `FbxScene::validate_mesh_skins()`.
+    pub fn validate_skins(&self) -> Vec<SkinIssue> {
+        let mut meshes = self.objects.geometry_meshes.keys().cloned().collect::<Vec<_>>();
+        meshes.sort();
+        meshes.into_iter().flat_map(|mesh| self.validate_mesh_skins(mesh)).collect()
+    }
+
+    /// Validates skins of the given mesh, and returns issues found.
+    ///
+    /// Returns an empty vector if the mesh does not exist or is not skinned.
+    pub fn validate_mesh_skins(&self, mesh: i64) -> Vec<SkinIssue> {
+        let num_control_points = match self.objects.geometry_meshes.get(&mesh) {
+            Some(mesh) => mesh.vertices.len(),
+            None => return Vec::new(),
+        };
+        let mut issues = Vec::new();
+        for skin in self.mesh_skins(mesh) {
+            let issue = |cluster: Option<i64>, kind: SkinIssueKind| SkinIssue {
+                mesh: mesh,
+                skin: skin,
+                cluster: cluster,
+                kind: kind,
+            };
+            let clusters = self.skin_clusters(skin);
+
+            let mut weight_sums = vec![None; num_control_points];
+            for &cluster_id in &clusters {
+                let cluster = &self.objects.clusters[&cluster_id];
+                if cluster.indices.len() != cluster.weights.len() {
+                    issues.push(issue(Some(cluster_id), SkinIssueKind::LengthMismatch {
+                        num_indices: cluster.indices.len(),
+                        num_weights: cluster.weights.len(),
+                    }));
+                }
+                let mut num_out_of_range = 0;
+                for (&cp, &weight) in cluster.indices.iter().zip(cluster.weights.iter()) {
+                    match weight_sums.get_mut(cp as usize) {
+                        Some(sum) => *sum = Some(sum.unwrap_or(0.0) + weight),
+                        None => num_out_of_range += 1,
+                    }
+                }
+                if num_out_of_range > 0 {
+                    issues.push(issue(Some(cluster_id), SkinIssueKind::IndicesOutOfRange {
+                        count: num_out_of_range,
+                        num_control_points: num_control_points,
+                    }));
+                }
+                if self.cluster_link_model(cluster_id).is_none() {
+                    issues.push(issue(Some(cluster_id), SkinIssueKind::MissingBone));
+                }
+            }
+
+            let link_mode = clusters.first().map(|cluster| self.objects.clusters[cluster].link_mode);
+            if link_mode != Some(ClusterLinkMode::Additive) {
+                let errors = weight_sums.iter()
+                    .filter_map(|&sum| sum)
+                    .filter(|&sum| sum > 1.0 + WEIGHT_SUM_TOLERANCE || (link_mode != Some(ClusterLinkMode::TotalOne) && sum < 1.0 - WEIGHT_SUM_TOLERANCE))
+                    .map(|sum| (sum - 1.0).abs())
+                    .collect::<Vec<_>>();
+                if !errors.is_empty() {
+                    issues.push(issue(None, SkinIssueKind::UnnormalizedWeights {
+                        count: errors.len(),
+                        max_error: errors.iter().fold(0.0, |max, &e| if e > max { e } else { max }),
+                    }));
+                }
+            }
+
+            let bones = clusters.iter()
+                .filter_map(|&cluster| self.cluster_link_model(cluster).map(|model| (cluster, model.id)))
+                .collect::<Vec<_>>();
+            if bones.is_empty() {
+                continue;
+            }
+            let pose = match self.skin_bind_pose(skin) {
+                Some(pose) => pose,
+                None => {
+                    issues.push(issue(None, SkinIssueKind::MissingBindPose));
+                    continue;
+                },
+            };
+            for (cluster_id, bone) in bones {
+                if pose.pose_node(bone).is_none() {
+                    issues.push(issue(Some(cluster_id), SkinIssueKind::BoneNotInBindPose {
+                        bone: bone,
+                        pose: pose.id,
+                    }));
+                    continue;
+                }
+                let matrix = self.pose_global_transform(pose.id, bone).unwrap();
+                let diff = mat4_max_difference(&self.objects.clusters[&cluster_id].transform_link, &matrix);
+                if diff > BIND_MISMATCH_TOLERANCE {
+                    issues.push(issue(Some(cluster_id), SkinIssueKind::BindMatrixMismatch {
+                        bone: bone,
+                        pose: pose.id,
+                        max_difference: diff,
+                    }));
+                }
+            }
+        }
+        issues
+    }
+
+    /// Repairs skins of all meshes.
+    ///
+    /// See `FbxScene::repair_mesh_skins()`.
+    pub fn repair_skins(&mut self, options: &SkinRepairOptions) -> Vec<SkinIssue> {
+        let mut meshes = self.objects.geometry_meshes.keys().cloned().collect::<Vec<_>>();
+        meshes.sort();
+        meshes.into_iter().flat_map(|mesh| self.repair_mesh_skins(mesh, options)).collect()
+    }
+
+    /// Repairs skins of the given mesh in accordance with the options, and returns issues found
+    /// before the repair.
+    ///
+    /// Missing bones and missing bind poses cannot be repaired.
+    pub fn repair_mesh_skins(&mut self, mesh: i64, options: &SkinRepairOptions) -> Vec<SkinIssue> {
+        let issues = self.validate_mesh_skins(mesh);
+        if issues.is_empty() {
+            return issues;
+        }
+        let num_control_points = self.objects.geometry_meshes[&mesh].vertices.len();
+        for skin in self.mesh_skins(mesh) {
+            let clusters = self.skin_clusters(skin);
+            if options.truncate {
+                for cluster_id in &clusters {
+                    let cluster = self.objects.clusters.get_mut(cluster_id).unwrap();
+                    let len = cluster.indices.len().min(cluster.weights.len());
+                    cluster.indices.truncate(len);
+                    cluster.weights.truncate(len);
+                    if cluster.indices.iter().any(|&cp| cp as usize >= num_control_points) {
+                        let (indices, weights) = cluster.indices.iter().cloned()
+                            .zip(cluster.weights.iter().cloned())
+                            .filter(|&(cp, _)| (cp as usize) < num_control_points)
+                            .unzip();
+                        cluster.indices = indices;
+                        cluster.weights = weights;
+                    }
+                }
+            }
+            if options.normalize {
+                self.normalize_skin_weights(skin, &clusters, num_control_points);
+            }
+            if options.rebuild_bind_pose {
+                self.rebuild_skin_bind_pose(skin, &clusters);
+            }
+        }
+        issues
+    }
+
+    /// Returns ids of clusters of the given skin.
+    fn skin_clusters(&self, skin: i64) -> Vec<i64> {
+        self.children(skin).into_iter()
+            .filter(|id| self.objects.clusters.contains_key(id))
+            .collect()
+    }
+
+    fn normalize_skin_weights(&mut self, skin: i64, clusters: &[i64], num_control_points: usize) {
+        let link_mode = match clusters.first() {
+            Some(cluster) => self.objects.clusters[cluster].link_mode,
+            None => return,
+        };
+        if link_mode == ClusterLinkMode::Additive {
+            warn!("Weights of skin (id={}) with additive clusters are not normalized", skin);
+            return;
+        }
+        let mut sums = vec![0.0f32; num_control_points];
+        for cluster in clusters {
+            let cluster = &self.objects.clusters[cluster];
+            for (&cp, &weight) in cluster.indices.iter().zip(cluster.weights.iter()) {
+                if let Some(sum) = sums.get_mut(cp as usize) {
+                    *sum += weight;
+                }
+            }
+        }
+        for cluster in clusters {
+            let cluster = self.objects.clusters.get_mut(cluster).unwrap();
+            for (&cp, weight) in cluster.indices.iter().zip(cluster.weights.iter_mut()) {
+                match sums.get(cp as usize) {
+                    Some(&sum) if sum > 0.0 && (link_mode != ClusterLinkMode::TotalOne || sum > 1.0) => *weight /= sum,
+                    _ => {},
+                }
+            }
+        }
+    }
+
+    fn rebuild_skin_bind_pose(&mut self, skin: i64, clusters: &[i64]) {
+        let pose_id = match self.skin_bind_pose(skin) {
+            Some(pose) => pose.id,
+            None => {
+                warn!("Skin (id={}) has no bind pose to rebuild", skin);
+                return;
+            },
+        };
+        let bones = clusters.iter()
+            .filter_map(|&cluster| self.cluster_link_model(cluster).map(|model| (model.id, self.objects.clusters[&cluster].transform_link)))
+            .collect::<Vec<_>>();
+        let pose = self.objects.poses.get_mut(&pose_id).unwrap();
+        for (bone, transform_link) in bones {
+            match pose.pose_nodes.iter().position(|node| node.node == bone) {
+                Some(index) => {
+                    pose.pose_nodes[index].matrix = transform_link;
+                    pose.pose_nodes[index].local = false;
+                },
+                None => pose.pose_nodes.push(PoseNode {
+                    node: bone,
+                    matrix: transform_link,
+                    local: false,
+                }),
+            }
+        }
+    }
+}
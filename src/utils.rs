@@ -1,27 +1,31 @@
 //! Contains utilities useful (but not required) to use with FBX data.
 
+use std::path::{Path, PathBuf};
+use Real;
+
+
 /// Triangulates single polygon and returns the number of new triangles.
 ///
 /// Triangulate the polygon (`vertices[poly_indices[0]]`, .., `vertices[poly_indices[n]]`)
 /// (where `n` is `poly_indices.len()`) into (`vertices[poly_indices[triangulated[0]]]`, ..,
 /// `vertices[poly_indices[triangulated[3*m+2]]]`) (whene `m` is the number of new triangles)
 /// and push `[triangulated[0], .., triangulated[3*m+2]]` to the `target`.
-pub fn triangulate_polygon(vertices: &[[f32; 3]], poly_indices: &[u32], target: &mut Vec<u32>) -> u32 {
-    let vec_cross = |v1: &[f32; 3], v2: &[f32; 3]| {
+pub fn triangulate_polygon(vertices: &[[Real; 3]], poly_indices: &[u32], target: &mut Vec<u32>) -> u32 {
+    let vec_cross = |v1: &[Real; 3], v2: &[Real; 3]| {
         [
             v1[1] * v2[2] - v1[2] * v2[1],
             v1[2] * v2[0] - v1[0] * v2[1],
             v1[0] * v2[1] - v1[1] * v2[1],
         ]
     };
-    let vec_sub = |v1: &[f32; 3], v2: &[f32; 3]| {
+    let vec_sub = |v1: &[Real; 3], v2: &[Real; 3]| {
         [
             v1[0] - v2[0],
             v1[1] - v2[1],
             v1[2] - v2[2],
         ]
     };
-    let vec_dot = |v1: &[f32; 3], v2: &[f32; 3]| {
+    let vec_dot = |v1: &[Real; 3], v2: &[Real; 3]| {
         v1[0] * v2[0] + v1[1] * v2[1] + v1[2] * v2[2]
     };
 
@@ -72,3 +76,186 @@ pub fn triangulate_polygon(vertices: &[[f32; 3]], poly_indices: &[u32], target:
         },
     }
 }
+
+/// Simulates a FIFO post-transform vertex cache and returns ACMR (average cache miss ratio, the
+/// number of cache misses per triangle) of the triangle list.
+pub fn acmr(indices: &[u32], cache_size: usize) -> f32 {
+    use std::collections::VecDeque;
+
+    let num_triangles = indices.len() / 3;
+    if num_triangles == 0 {
+        return 0.0;
+    }
+    let mut cache = VecDeque::with_capacity(cache_size + 1);
+    let mut misses = 0;
+    for &index in &indices[..num_triangles * 3] {
+        if !cache.contains(&index) {
+            misses += 1;
+            cache.push_back(index);
+            if cache.len() > cache_size {
+                cache.pop_front();
+            }
+        }
+    }
+    misses as f32 / num_triangles as f32
+}
+
+/// Reorders triangles of the triangle list for the post-transform vertex cache, using Tom
+/// Forsyth's algorithm ([Linear-Speed Vertex Cache
+/// Optimisation](https://tomforsyth1000.github.io/papers/fast_vert_cache_opt.html)).
+///
+/// Returns the source triangle index of each new triangle.
+pub fn optimize_vertex_cache(indices: &mut [u32], num_vertices: usize, cache_size: usize) -> Vec<u32> {
+    const CACHE_DECAY_POWER: f32 = 1.5;
+    const LAST_TRI_SCORE: f32 = 0.75;
+    const VALENCE_BOOST_SCALE: f32 = 2.0;
+    const VALENCE_BOOST_POWER: f32 = 0.5;
+
+    let num_triangles = indices.len() / 3;
+    let identity = (0..num_triangles as u32).collect::<Vec<_>>();
+    if indices.iter().any(|&i| i as usize >= num_vertices) {
+        warn!("Vertex index out of range, triangles are not reordered");
+        return identity;
+    }
+    let cache_size = cache_size.max(4);
+    let vertex_score = |cache_pos: Option<usize>, remaining: usize| -> f32 {
+        if remaining == 0 {
+            return -1.0;
+        }
+        let cache_score = match cache_pos {
+            Some(pos) if pos < 3 => LAST_TRI_SCORE,
+            Some(pos) => (1.0 - (pos - 3) as f32 / (cache_size - 3) as f32).powf(CACHE_DECAY_POWER),
+            None => 0.0,
+        };
+        cache_score + VALENCE_BOOST_SCALE * (remaining as f32).powf(-VALENCE_BOOST_POWER)
+    };
+
+    // Triangles using each vertex.
+    let mut vertex_triangles = vec![Vec::new(); num_vertices];
+    for tri in 0..num_triangles {
+        for &v in &indices[tri * 3..tri * 3 + 3] {
+            vertex_triangles[v as usize].push(tri as u32);
+        }
+    }
+    let mut remaining = vertex_triangles.iter().map(|t| t.len()).collect::<Vec<_>>();
+    let mut cache_pos = vec![None; num_vertices];
+    let mut scores = (0..num_vertices).map(|v| vertex_score(None, remaining[v])).collect::<Vec<_>>();
+    let mut tri_scores = (0..num_triangles).map(|tri| indices[tri * 3..tri * 3 + 3].iter().map(|&v| scores[v as usize]).sum::<f32>()).collect::<Vec<_>>();
+    let mut emitted = vec![false; num_triangles];
+    let mut cache: Vec<u32> = Vec::with_capacity(cache_size + 3);
+    let mut order = Vec::with_capacity(num_triangles);
+    let mut best = None;
+    let mut next_unemitted = 0;
+
+    while order.len() < num_triangles {
+        let tri = match best {
+            Some(tri) => tri,
+            None => {
+                // No candidate in the cache. Find the best triangle from all triangles.
+                while emitted[next_unemitted] {
+                    next_unemitted += 1;
+                }
+                (next_unemitted..num_triangles).filter(|&t| !emitted[t]).fold(next_unemitted, |best, t| if tri_scores[t] > tri_scores[best] { t } else { best })
+            },
+        };
+        emitted[tri] = true;
+        order.push(tri as u32);
+        let tri_vertices = [indices[tri * 3], indices[tri * 3 + 1], indices[tri * 3 + 2]];
+        for &v in &tri_vertices {
+            remaining[v as usize] -= 1;
+        }
+
+        // Update the LRU cache.
+        // Vertices of degenerate triangles (such as `[a, b, a]`) are inserted only once.
+        let mut new_cache = Vec::with_capacity(cache.len() + 3);
+        for &v in &tri_vertices {
+            if !new_cache.contains(&v) {
+                new_cache.push(v);
+            }
+        }
+        new_cache.extend(cache.iter().cloned().filter(|v| !tri_vertices.contains(v)));
+        for (pos, &v) in new_cache.iter().enumerate() {
+            cache_pos[v as usize] = if pos < cache_size { Some(pos) } else { None };
+        }
+        // Update scores of vertices in the cache (including evicted ones) and their triangles.
+        for &v in &new_cache {
+            scores[v as usize] = vertex_score(cache_pos[v as usize], remaining[v as usize]);
+        }
+        best = None;
+        let mut best_score = -1.0;
+        for &v in &new_cache {
+            for &t in &vertex_triangles[v as usize] {
+                let t = t as usize;
+                if emitted[t] {
+                    continue;
+                }
+                tri_scores[t] = indices[t * 3..t * 3 + 3].iter().map(|&v| scores[v as usize]).sum();
+                if tri_scores[t] > best_score {
+                    best_score = tri_scores[t];
+                    best = Some(t);
+                }
+            }
+        }
+        new_cache.truncate(cache_size);
+        cache = new_cache;
+    }
+
+    let src = indices[..num_triangles * 3].to_vec();
+    for (new, &old) in order.iter().enumerate() {
+        let old = old as usize;
+        indices[new * 3..new * 3 + 3].copy_from_slice(&src[old * 3..old * 3 + 3]);
+    }
+    order
+}
+
+/// Reorders vertices in the order of the first use by the indices, and rewrites the indices.
+///
+/// Vertices not used by the indices are placed after used vertices.
+/// Returns the source vertex index of each new vertex (see `reorder()`).
+pub fn optimize_vertex_fetch(indices: &mut [u32], num_vertices: usize) -> Vec<u32> {
+    let mut old_to_new = vec![::std::u32::MAX; num_vertices];
+    let mut new_to_old = Vec::with_capacity(num_vertices);
+    for index in indices.iter_mut() {
+        if let Some(new) = old_to_new.get_mut(*index as usize) {
+            if *new == ::std::u32::MAX {
+                *new = new_to_old.len() as u32;
+                new_to_old.push(*index);
+            }
+            *index = *new;
+        }
+    }
+    for old in 0..num_vertices {
+        if old_to_new[old] == ::std::u32::MAX {
+            new_to_old.push(old as u32);
+        }
+    }
+    new_to_old
+}
+
+/// Returns values reordered by the map from new indices to source indices.
+pub fn reorder<T: Clone>(values: &[T], new_to_old: &[u32]) -> Vec<T> {
+    new_to_old.iter().map(|&old| values[old as usize].clone()).collect()
+}
+
+/// Resolves the path of an external file referred to by an FBX file.
+///
+/// `filename` is the (usually absolute) path stored in the FBX file, and `relative_filename` is
+/// the path relative to the FBX file, which is in `base_dir`.
+/// Candidates are tried in the following order, and the first existing one is returned:
+///
+/// 1. `base_dir` joined with `relative_filename`,
+/// 2. `filename`,
+/// 3. `base_dir` joined with the file name of `filename`.
+pub fn resolve_external_path(base_dir: &Path, filename: &Path, relative_filename: &Path) -> Option<PathBuf> {
+    let mut candidates = Vec::new();
+    if relative_filename.as_os_str().len() > 0 {
+        candidates.push(base_dir.join(relative_filename));
+    }
+    if filename.as_os_str().len() > 0 {
+        candidates.push(filename.to_path_buf());
+    }
+    if let Some(name) = filename.file_name().or_else(|| relative_filename.file_name()) {
+        candidates.push(base_dir.join(name));
+    }
+    candidates.into_iter().find(|path| path.is_file())
+}